@@ -0,0 +1,51 @@
+//! Spending from another wallet through an allowance it granted.
+//!
+//! The transfer endpoints take a `from` address, when it is not the authenticated wallet the transfer
+//! is made from that wallet and counted against the allowance it granted the authenticated one. Such
+//! transfers are tagged with `spender=<address>` so the owner can tell them apart.
+use rust_decimal::Decimal;
+use surrealdb::{engine::any::Any, Surreal};
+
+use crate::database::models::allowance::{Model as Allowance, SpendOutcome};
+use crate::database::models::transaction;
+use crate::errors::krist::{allowance::AllowanceError, transaction::TransactionError, KristError};
+use crate::utils::common_meta;
+
+/// The metadata of a transfer made through an allowance, the spender's own followed by `spender=<address>`.
+pub fn spender_meta(metadata: Option<String>, spender: &str) -> String {
+    common_meta::join([metadata.unwrap_or_default(), format!("spender={spender}")])
+}
+
+/// Transfer from `owner` on behalf of `spender`, failing when their allowance does not cover it.
+pub async fn spend(
+    db: &Surreal<Any>,
+    owner: String,
+    spender: &str,
+    to: String,
+    amount: Decimal,
+    metadata: Option<String>,
+) -> Result<transaction::Model, KristError> {
+    let outcome = Allowance::spend(
+        db,
+        owner.clone(),
+        spender.to_owned(),
+        to.clone(),
+        amount,
+        Some(spender_meta(metadata, spender)),
+    )
+    .await?;
+
+    match outcome {
+        SpendOutcome::Spent { transaction } => Ok(*transaction),
+        SpendOutcome::NoAllowance => Err(KristError::Allowance(AllowanceError::NotGranted(owner))),
+        SpendOutcome::RecipientNotAllowed => Err(KristError::Allowance(
+            AllowanceError::RecipientNotAllowed(to),
+        )),
+        SpendOutcome::ExceedsAllowance { remaining } => {
+            Err(KristError::Allowance(AllowanceError::Exceeded(remaining)))
+        }
+        SpendOutcome::InsufficientFunds => {
+            Err(KristError::Transaction(TransactionError::InsufficientFunds))
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Recording privileged and financial changes in the audit log.
+//!
+//! Entries are written after the change they describe has been made, a failure to write one is logged
+//! but does not fail the request.
+use std::future::{ready, Ready};
+use std::net::IpAddr;
+
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use serde::Serialize;
+use serde_json::Value;
+use surrealdb::{engine::any::Any, Surreal, Uuid};
+
+use crate::auth;
+use crate::database::models::audit_log::{ActorType, AuditLogCreateData, Model as AuditLog};
+use crate::guards::InternalKey;
+
+/// Clients may pass their own request ID to correlate audit entries with their logs.
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Who made a change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Actor {
+    InternalKey(String),
+    Wallet(String),
+    System(&'static str),
+}
+
+impl From<&InternalKey> for Actor {
+    fn from(key: &InternalKey) -> Self {
+        Actor::InternalKey(key.name.clone())
+    }
+}
+
+/// Where a change came from, taken from the HTTP request or websocket session that made it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestInfo {
+    pub request_id: String,
+    pub ip: Option<IpAddr>,
+}
+
+impl RequestInfo {
+    /// Changes made over a websocket are attributed to the session.
+    pub fn websocket(session: &Uuid, ip: Option<IpAddr>) -> Self {
+        Self {
+            request_id: format!("ws:{session}"),
+            ip,
+        }
+    }
+}
+
+impl FromRequest for RequestInfo {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        if let Some(info) = req.extensions().get::<RequestInfo>() {
+            return ready(Ok(info.clone()));
+        }
+
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|id| id.to_str().ok())
+            .filter(|id| !id.is_empty() && id.len() <= 64)
+            .map(str::to_owned)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let info = RequestInfo {
+            request_id,
+            ip: auth::request_ip(req),
+        };
+        req.extensions_mut().insert(info.clone());
+
+        ready(Ok(info))
+    }
+}
+
+/// Before and after values are stored as objects, anything else is wrapped in one.
+fn to_object(value: impl Serialize) -> Option<Value> {
+    match serde_json::to_value(value).ok()? {
+        Value::Null => None,
+        value @ Value::Object(_) => Some(value),
+        value => Some(serde_json::json!({ "value": value })),
+    }
+}
+
+/// A single change to record, see [`AuditEntry::write`].
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    actor: Actor,
+    action: &'static str,
+    target: Option<String>,
+    before: Option<Value>,
+    after: Option<Value>,
+    request: Option<RequestInfo>,
+}
+
+impl AuditEntry {
+    /// `action` names the change, e.g. `wallet.give_money`.
+    pub fn new(actor: impl Into<Actor>, action: &'static str) -> Self {
+        Self {
+            actor: actor.into(),
+            action,
+            target: None,
+            before: None,
+            after: None,
+            request: None,
+        }
+    }
+
+    /// The address, name or session that was changed.
+    pub fn target(mut self, target: impl ToString) -> Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    pub fn before(mut self, before: impl Serialize) -> Self {
+        self.before = to_object(before);
+        self
+    }
+
+    pub fn after(mut self, after: impl Serialize) -> Self {
+        self.after = to_object(after);
+        self
+    }
+
+    pub fn request(mut self, request: &RequestInfo) -> Self {
+        self.request = Some(request.clone());
+        self
+    }
+
+    fn into_create_data(self) -> AuditLogCreateData {
+        let (actor_type, actor) = match self.actor {
+            Actor::InternalKey(name) => (ActorType::InternalKey, name),
+            Actor::Wallet(address) => (ActorType::Wallet, address),
+            Actor::System(job) => (ActorType::System, job.to_owned()),
+        };
+
+        AuditLogCreateData {
+            actor_type,
+            actor,
+            action: self.action.to_owned(),
+            target: self.target,
+            before: self.before,
+            after: self.after,
+            request_id: self.request.as_ref().map(|info| info.request_id.clone()),
+            ip: self
+                .request
+                .and_then(|info| info.ip)
+                .map(|ip| ip.to_string()),
+        }
+    }
+
+    /// Append the entry to the audit log.
+    pub async fn write(self, db: &Surreal<Any>) {
+        let data = self.into_create_data();
+        tracing::info!(
+            target: "audit",
+            actor = %data.actor,
+            request_id = data.request_id.as_deref().unwrap_or("-"),
+            "{} {}",
+            data.action,
+            data.target.as_deref().unwrap_or("-")
+        );
+
+        if let Err(err) = AuditLog::create(db, data).await {
+            tracing::error!("Failed to write audit log entry: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_entries() {
+        let request = RequestInfo {
+            request_id: "abc".to_owned(),
+            ip: Some("127.0.0.1".parse().unwrap()),
+        };
+        let data = AuditEntry::new(Actor::System("dormant_wallets"), "wallet.purge_dormant")
+            .target("kaaaaaaaaa")
+            .before(serde_json::json!({ "balance": 0 }))
+            .request(&request)
+            .into_create_data();
+
+        assert_eq!(data.actor_type, ActorType::System);
+        assert_eq!(data.actor, "dormant_wallets");
+        assert_eq!(data.target.as_deref(), Some("kaaaaaaaaa"));
+        assert_eq!(data.after, None);
+        assert_eq!(data.request_id.as_deref(), Some("abc"));
+        assert_eq!(data.ip.as_deref(), Some("127.0.0.1"));
+    }
+}
@@ -0,0 +1,244 @@
+//! Authentication of wallet owners on mutating routes.
+//!
+//! A request proves ownership of a wallet with its private key, an `Authorization: Bearer <token>`
+//! header carrying an API token of that wallet, or by signing the request with the wallet's keypair.
+//! Private keys are checked through [`verify_private_key`], which locks out brute force attempts.
+pub mod lockout;
+pub mod signature;
+
+use std::net::IpAddr;
+
+use actix_web::{http::header, web, HttpMessage, HttpRequest};
+use rust_decimal::Decimal;
+use surrealdb::{engine::any::Any, Surreal};
+
+use crate::database::models::api_token::Model as ApiToken;
+use crate::database::models::wallet::{Model as Wallet, VerifyResponse};
+use crate::errors::krist::{
+    address::AddressError, generic::GenericError, token::TokenError, KristError,
+};
+use crate::models::tokens::TokenScope;
+use crate::utils::{crypto, net};
+use crate::websockets::WebSocketServer;
+use crate::AppState;
+use signature::SignedBy;
+
+/// A wallet whose ownership was proven by the request.
+#[derive(Debug, Clone)]
+pub struct Authenticated {
+    pub wallet: Wallet,
+    /// The API token used, `None` when the private key was given or the request was signed.
+    pub token: Option<ApiToken>,
+}
+
+impl Authenticated {
+    /// Count an amount towards the daily limit of the token, if one was used.
+    pub async fn reserve_spend(
+        &self,
+        db: &Surreal<Any>,
+        amount: Decimal,
+    ) -> Result<(), KristError> {
+        let Some(token) = &self.token else {
+            return Ok(());
+        };
+
+        if !token.reserve_spend(db, amount).await? {
+            return Err(KristError::Token(TokenError::SpendLimitExceeded));
+        }
+
+        Ok(())
+    }
+
+    /// Undo [`Authenticated::reserve_spend`] after the spend failed.
+    pub async fn release_spend(
+        &self,
+        db: &Surreal<Any>,
+        amount: Decimal,
+    ) -> Result<(), KristError> {
+        if let Some(token) = &self.token {
+            token.release_spend(db, amount).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The API token given in the `Authorization` header, if any.
+pub fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+/// Authenticate a request.
+///
+/// A request signed through [`signature::Signed`] is authenticated as its signer, otherwise an API
+/// token is preferred over the private key from the body.
+pub async fn authenticate(
+    db: &Surreal<Any>,
+    req: &HttpRequest,
+    private_key: Option<String>,
+    scope: TokenScope,
+) -> Result<Authenticated, KristError> {
+    if let Some(SignedBy(wallet)) = req.extensions().get::<SignedBy>().cloned() {
+        return Ok(Authenticated {
+            wallet,
+            token: None,
+        });
+    }
+
+    if let Some(token) = bearer_token(req) {
+        return authenticate_token(db, token, scope).await;
+    }
+
+    let private_key = private_key.ok_or_else(|| {
+        KristError::Generic(GenericError::MissingParameter("privatekey".to_string()))
+    })?;
+
+    let wallet = verify_request_key(req, &private_key, None).await?;
+
+    Ok(Authenticated {
+        wallet,
+        token: None,
+    })
+}
+
+/// The IP address a request came from, `X-Forwarded-For` is only trusted when `WS_TRUST_FORWARDED_FOR` is set.
+pub fn request_ip(req: &HttpRequest) -> Option<IpAddr> {
+    let trust_forwarded_for = req
+        .app_data::<web::Data<WebSocketServer>>()
+        .is_some_and(|server| server.config.trust_forwarded_for);
+
+    net::client_ip(req, trust_forwarded_for)
+}
+
+/// Verify a private key, locking out IPs and addresses that fail too often.
+///
+/// `v1_address` is the legacy address the key belongs to, if the client gave one. When failed logins
+/// pile up the owner of the address gets a `security` event through `server`.
+pub async fn verify_private_key(
+    state: &AppState,
+    server: Option<&WebSocketServer>,
+    ip: Option<IpAddr>,
+    private_key: &str,
+    v1_address: Option<&str>,
+) -> Result<Wallet, KristError> {
+    let db = &state.db;
+    let tracker = &state.auth_tracker;
+    let address = match v1_address {
+        Some(address) => address.to_owned(),
+        None => crypto::make_v2_address(private_key, "k"),
+    };
+
+    if let Some(locked_for) = tracker.locked_for(ip, Some(&address)) {
+        tracing::info!("Refusing login to {address} from {ip:?}, locked out");
+        return Err(KristError::Address(AddressError::TooManyAttempts(
+            locked_for.as_secs().max(1),
+        )));
+    }
+
+    let wallet = match v1_address {
+        Some(address) => Wallet::verify_v1_address(db, private_key, address).await?,
+        None => Wallet::verify_address(db, private_key)
+            .await?
+            .and_then(VerifyResponse::into_authed),
+    };
+
+    if let Some(wallet) = wallet {
+        tracker.record_success(&address);
+        return Ok(wallet);
+    }
+
+    if let Some(alert) = tracker.record_failure(ip, Some(&address)) {
+        tracing::warn!(
+            "Address {} received {} failed logins from {} IPs",
+            alert.address,
+            alert.failures,
+            alert.sources
+        );
+        if let Some(server) = server {
+            server.broadcast_event(alert.into_event()).await;
+        }
+    }
+
+    Err(KristError::Address(AddressError::AuthFailed))
+}
+
+/// [`verify_private_key`] for the private key given in an HTTP request.
+pub async fn verify_request_key(
+    req: &HttpRequest,
+    private_key: &str,
+    v1_address: Option<&str>,
+) -> Result<Wallet, KristError> {
+    let state = req
+        .app_data::<web::Data<AppState>>()
+        .expect("AppState should be registered");
+    let server = req.app_data::<web::Data<WebSocketServer>>();
+
+    verify_private_key(
+        state,
+        server.map(|server| server.get_ref()),
+        request_ip(req),
+        private_key,
+        v1_address,
+    )
+    .await
+}
+
+#[tracing::instrument(skip_all)]
+async fn authenticate_token(
+    db: &Surreal<Any>,
+    token: &str,
+    scope: TokenScope,
+) -> Result<Authenticated, KristError> {
+    let token = ApiToken::get_by_token(db, token)
+        .await?
+        .ok_or(KristError::Token(TokenError::InvalidToken))?;
+
+    if token.is_expired() {
+        return Err(KristError::Token(TokenError::Expired));
+    }
+    if !token.has_scope(scope) {
+        tracing::info!("API token for {} lacks the {scope} scope", token.address);
+        return Err(KristError::Token(TokenError::MissingScope(scope)));
+    }
+
+    let wallet = Wallet::get_by_address(db, token.address.clone())
+        .await?
+        .filter(|wallet| !wallet.locked)
+        .ok_or(KristError::Address(AddressError::AuthFailed))?;
+
+    token.touch(db).await?;
+    wallet.touch(db).await?;
+
+    Ok(Authenticated {
+        wallet,
+        token: Some(token),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    #[test]
+    fn parses_bearer_token() {
+        let req = TestRequest::default()
+            .insert_header((header::AUTHORIZATION, "Bearer kt_secret"))
+            .to_http_request();
+        assert_eq!(bearer_token(&req), Some("kt_secret"));
+
+        let req = TestRequest::default()
+            .insert_header((header::AUTHORIZATION, "Basic a3Q6c2VjcmV0"))
+            .to_http_request();
+        assert_eq!(bearer_token(&req), None);
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(bearer_token(&req), None);
+    }
+}
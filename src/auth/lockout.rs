@@ -0,0 +1,291 @@
+//! Protection against brute forcing wallet logins.
+//!
+//! Failed logins are counted per source IP and per target address. Once either reaches its threshold
+//! it is locked out, and every further failure doubles the lockout up to a maximum. Failures are
+//! forgotten once none happened for a while. A successful login only clears its address, the IP
+//! keeps its failures so it can't reset them by logging into a wallet of its own in between.
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use serde::Serialize;
+
+use crate::models::websockets::{WebSocketEvent, WebSocketMessage};
+use crate::utils::env::env_or;
+
+#[derive(Debug, Clone)]
+pub struct LockoutConfig {
+    /// Failed logins from a single IP before it is locked out.
+    pub ip_threshold: u32,
+    /// Failed logins against a single address before it is locked out.
+    pub address_threshold: u32,
+    /// Lockout once a threshold is reached, doubled for every failure after that.
+    pub base_lockout: Duration,
+    /// Upper bound of a lockout.
+    pub max_lockout: Duration,
+    /// Failures are forgotten once none happened for this long.
+    pub window: Duration,
+    /// Failed logins against an address before its owner is alerted, and again for every multiple of it.
+    pub alert_threshold: u32,
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            ip_threshold: 5,
+            address_threshold: 20,
+            base_lockout: Duration::from_secs(30),
+            max_lockout: Duration::from_secs(3600),
+            window: Duration::from_secs(900),
+            alert_threshold: 10,
+        }
+    }
+}
+
+impl LockoutConfig {
+    /// Read the config from the environment, anything that is not set falls back to the default.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            ip_threshold: env_or("AUTH_LOCKOUT_IP_THRESHOLD", default.ip_threshold),
+            address_threshold: env_or("AUTH_LOCKOUT_ADDRESS_THRESHOLD", default.address_threshold),
+            base_lockout: Duration::from_secs(env_or(
+                "AUTH_LOCKOUT_BASE",
+                default.base_lockout.as_secs(),
+            )),
+            max_lockout: Duration::from_secs(env_or(
+                "AUTH_LOCKOUT_MAX",
+                default.max_lockout.as_secs(),
+            )),
+            window: Duration::from_secs(env_or("AUTH_LOCKOUT_WINDOW", default.window.as_secs())),
+            alert_threshold: env_or("AUTH_ALERT_THRESHOLD", default.alert_threshold),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Failures {
+    count: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+    /// IPs the failures came from, only tracked for addresses.
+    sources: HashSet<IpAddr>,
+}
+
+impl Failures {
+    fn new() -> Self {
+        Self {
+            count: 0,
+            last_failure: Instant::now(),
+            locked_until: None,
+            sources: HashSet::new(),
+        }
+    }
+
+    fn locked_for(&self) -> Option<Duration> {
+        self.locked_until?
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    fn is_stale(&self, window: Duration) -> bool {
+        self.last_failure.elapsed() > window && self.locked_for().is_none()
+    }
+
+    fn fail(&mut self, threshold: u32, config: &LockoutConfig) {
+        if self.is_stale(config.window) {
+            *self = Self::new();
+        }
+
+        self.count += 1;
+        self.last_failure = Instant::now();
+
+        if self.count >= threshold {
+            let doublings = (self.count - threshold).min(16);
+            let lockout = config
+                .base_lockout
+                .saturating_mul(1 << doublings)
+                .min(config.max_lockout);
+            self.locked_until = Some(self.last_failure + lockout);
+        }
+    }
+}
+
+/// An address that is the target of repeated failed logins.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AttackedAddress {
+    pub address: String,
+    pub failures: u32,
+    /// Amount of distinct IPs the failures came from.
+    pub sources: usize,
+    /// Seconds since the last failed login.
+    pub last_failure: u64,
+    /// Seconds until the address can be logged into again, if it is locked out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locked_for: Option<u64>,
+}
+
+/// Raised once failed logins against an address pile up, see [`LockoutConfig::alert_threshold`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityAlert {
+    pub address: String,
+    pub failures: u32,
+    pub sources: usize,
+}
+
+impl SecurityAlert {
+    /// The `security` event sent to the owner of the address.
+    pub fn into_event(self) -> WebSocketMessage {
+        WebSocketMessage::new_event(WebSocketEvent::Security {
+            address: self.address,
+            failures: self.failures,
+            sources: self.sources,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FailedAuthTracker {
+    config: LockoutConfig,
+    ips: DashMap<IpAddr, Failures>,
+    addresses: DashMap<String, Failures>,
+}
+
+impl FailedAuthTracker {
+    pub fn new(config: LockoutConfig) -> Self {
+        Self {
+            config,
+            ips: DashMap::new(),
+            addresses: DashMap::new(),
+        }
+    }
+
+    /// Time left until a login from the IP to the address may be attempted, `None` if neither is locked out.
+    pub fn locked_for(&self, ip: Option<IpAddr>, address: Option<&str>) -> Option<Duration> {
+        let ip = ip.and_then(|ip| self.ips.get(&ip)?.locked_for());
+        let address = address.and_then(|address| self.addresses.get(address)?.locked_for());
+
+        ip.max(address)
+    }
+
+    /// Count a failed login, returning an alert when the owner of the address should be warned.
+    pub fn record_failure(
+        &self,
+        ip: Option<IpAddr>,
+        address: Option<&str>,
+    ) -> Option<SecurityAlert> {
+        self.prune();
+
+        if let Some(ip) = ip {
+            self.ips
+                .entry(ip)
+                .or_insert_with(Failures::new)
+                .fail(self.config.ip_threshold, &self.config);
+        }
+
+        let address = address?;
+        let mut failures = self
+            .addresses
+            .entry(address.to_owned())
+            .or_insert_with(Failures::new);
+        failures.fail(self.config.address_threshold, &self.config);
+        failures.sources.extend(ip);
+
+        let alert_threshold = self.config.alert_threshold.max(1);
+        (failures.count % alert_threshold == 0).then(|| SecurityAlert {
+            address: address.to_owned(),
+            failures: failures.count,
+            sources: failures.sources.len(),
+        })
+    }
+
+    /// Forget the failures of an address after a successful login to it.
+    pub fn record_success(&self, address: &str) {
+        self.addresses.remove(address);
+    }
+
+    /// Addresses that received at least [`LockoutConfig::alert_threshold`] failed logins, most attacked first.
+    pub fn attacked_addresses(&self) -> Vec<AttackedAddress> {
+        self.prune();
+
+        let mut attacked: Vec<AttackedAddress> = self
+            .addresses
+            .iter()
+            .filter(|entry| entry.count >= self.config.alert_threshold)
+            .map(|entry| AttackedAddress {
+                address: entry.key().clone(),
+                failures: entry.count,
+                sources: entry.sources.len(),
+                last_failure: entry.last_failure.elapsed().as_secs(),
+                locked_for: entry.locked_for().map(|locked_for| locked_for.as_secs()),
+            })
+            .collect();
+        attacked.sort_by_key(|attacked| Reverse(attacked.failures));
+
+        attacked
+    }
+
+    fn prune(&self) {
+        let window = self.config.window;
+
+        self.ips.retain(|_, failures| !failures.is_stale(window));
+        self.addresses
+            .retain(|_, failures| !failures.is_stale(window));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locks_out_progressively() {
+        let tracker = FailedAuthTracker::new(LockoutConfig {
+            ip_threshold: 2,
+            address_threshold: 3,
+            alert_threshold: 3,
+            ..Default::default()
+        });
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+
+        assert_eq!(tracker.record_failure(Some(ip), Some("kaaaaaaaaa")), None);
+        assert_eq!(tracker.locked_for(Some(ip), None), None);
+
+        tracker.record_failure(Some(ip), Some("kaaaaaaaaa"));
+        let first = tracker.locked_for(Some(ip), None).unwrap();
+        assert!(first <= Duration::from_secs(30));
+        assert_eq!(tracker.locked_for(None, Some("kaaaaaaaaa")), None);
+
+        let alert = tracker.record_failure(Some(ip), Some("kaaaaaaaaa"));
+        assert_eq!(
+            alert,
+            Some(SecurityAlert {
+                address: "kaaaaaaaaa".to_owned(),
+                failures: 3,
+                sources: 1,
+            })
+        );
+        assert!(tracker.locked_for(Some(ip), None).unwrap() > first);
+        assert!(tracker.locked_for(None, Some("kaaaaaaaaa")).is_some());
+        assert_eq!(tracker.attacked_addresses()[0].failures, 3);
+
+        tracker.record_success("kaaaaaaaaa");
+        assert_eq!(tracker.locked_for(None, Some("kaaaaaaaaa")), None);
+        assert!(tracker.attacked_addresses().is_empty());
+        // The IP keeps its lockout until it decays.
+        assert!(tracker.locked_for(Some(ip), Some("kaaaaaaaaa")).is_some());
+
+        // Logging into its own wallet between guesses doesn't reset the count of an IP.
+        let other: IpAddr = "127.0.0.2".parse().unwrap();
+        tracker.record_failure(Some(other), Some("kbbbbbbbbb"));
+        tracker.record_success("kccccccccc");
+        assert_eq!(tracker.locked_for(Some(other), None), None);
+        tracker.record_failure(Some(other), Some("kbbbbbbbbb"));
+        tracker.record_success("kccccccccc");
+        assert!(tracker.locked_for(Some(other), None).is_some());
+        assert_eq!(tracker.locked_for(None, Some("kbbbbbbbbb")), None);
+    }
+}
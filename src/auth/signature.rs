@@ -0,0 +1,201 @@
+//! Challenge-response authentication with ed25519 keypairs.
+//!
+//! A wallet that registered a public key can request a single-use nonce and sign requests with its
+//! own private key instead of sending the Krist private key. Signed HTTP requests carry the
+//! `Kromer-Address`, `Kromer-Nonce` and `Kromer-Signature` (hex) headers, the signed message is
+//!
+//! ```text
+//! {address}\n{nonce}\n{METHOD}\n{path}\n{hex sha256 of the body}
+//! ```
+//!
+//! Websocket logins sign the same message with `WS` as the method, `login` as the path and an empty body.
+use std::future::Future;
+use std::pin::Pin;
+
+use actix_web::{dev::Payload, web, FromRequest, HttpMessage, HttpRequest};
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use surrealdb::{engine::any::Any, Surreal};
+
+use crate::database::models::auth_nonce::Model as AuthNonce;
+use crate::database::models::wallet::Model as Wallet;
+use crate::errors::krist::{address::AddressError, generic::GenericError, KristError};
+use crate::AppState;
+
+pub const ADDRESS_HEADER: &str = "Kromer-Address";
+pub const NONCE_HEADER: &str = "Kromer-Nonce";
+pub const SIGNATURE_HEADER: &str = "Kromer-Signature";
+
+/// The wallet that signed the current request, stored in the request extensions by [`Signed`].
+#[derive(Debug, Clone)]
+pub struct SignedBy(pub Wallet);
+
+/// A JSON body whose request may be signed by a wallet keypair.
+///
+/// Unsigned requests pass through untouched. When the signature headers are present the signature
+/// has to be valid, after which [`super::authenticate`] treats the signer as authenticated.
+#[derive(Debug)]
+pub struct Signed<T>(pub T);
+
+impl<T> Signed<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for Signed<T> {
+    type Error = KristError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let body = web::Bytes::from_request(&req, payload);
+
+        Box::pin(async move {
+            let body = body.await.map_err(|_| invalid_body())?;
+
+            if let Some((address, nonce, signature)) = signature_headers(&req) {
+                let state = req
+                    .app_data::<web::Data<AppState>>()
+                    .expect("AppState should be registered");
+                let message =
+                    signing_message(&address, &nonce, req.method().as_str(), req.path(), &body);
+                let wallet =
+                    verify_signed(&state.db, &address, &nonce, &message, &signature).await?;
+
+                req.extensions_mut().insert(SignedBy(wallet));
+            }
+
+            // Routes taking an optional body accept an empty one.
+            let body: &[u8] = if body.is_empty() { b"null" } else { &body };
+            let value = serde_json::from_slice(body).map_err(|_| invalid_body())?;
+
+            Ok(Signed(value))
+        })
+    }
+}
+
+fn invalid_body() -> KristError {
+    KristError::Generic(GenericError::InvalidParameter("body".to_string()))
+}
+
+fn signature_headers(req: &HttpRequest) -> Option<(String, String, String)> {
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+    };
+
+    Some((
+        header(ADDRESS_HEADER)?,
+        header(NONCE_HEADER)?,
+        header(SIGNATURE_HEADER)?,
+    ))
+}
+
+/// Build the message a wallet signs, see the module documentation.
+pub fn signing_message(
+    address: &str,
+    nonce: &str,
+    method: &str,
+    path: &str,
+    body: &[u8],
+) -> String {
+    let body_digest = hex::encode(Sha256::digest(body));
+
+    format!("{address}\n{nonce}\n{method}\n{path}\n{body_digest}")
+}
+
+/// Parse a hex encoded ed25519 public key.
+pub fn parse_public_key(public_key: &str) -> Option<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(public_key).ok()?.try_into().ok()?;
+
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+/// Check a hex encoded signature of a message against a hex encoded public key.
+pub fn verify_signature(public_key: &str, message: &str, signature: &str) -> bool {
+    let Some(public_key) = parse_public_key(public_key) else {
+        return false;
+    };
+    let Some(signature) = hex::decode(signature)
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+    else {
+        return false;
+    };
+
+    public_key
+        .verify_strict(message.as_bytes(), &signature)
+        .is_ok()
+}
+
+/// Verify a signed message, using up the nonce it was signed with.
+#[tracing::instrument(skip(db, message, signature))]
+pub async fn verify_signed(
+    db: &Surreal<Any>,
+    address: &str,
+    nonce: &str,
+    message: &str,
+    signature: &str,
+) -> Result<Wallet, KristError> {
+    // The nonce is used up even if the signature turns out to be invalid, so it can't be brute forced.
+    if !AuthNonce::consume(db, address, nonce).await? {
+        tracing::info!("Signed request with an unknown or expired nonce");
+        return Err(KristError::Address(AddressError::AuthFailed));
+    }
+
+    let wallet = Wallet::get_by_address(db, address.to_owned())
+        .await?
+        .filter(|wallet| !wallet.locked)
+        .ok_or(KristError::Address(AddressError::AuthFailed))?;
+
+    let verified = wallet
+        .public_key
+        .as_deref()
+        .is_some_and(|public_key| verify_signature(public_key, message, signature));
+    if !verified {
+        tracing::info!("Signed request with an invalid signature");
+        return Err(KristError::Address(AddressError::AuthFailed));
+    }
+    wallet.touch(db).await?;
+
+    Ok(wallet)
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    use super::*;
+
+    #[test]
+    fn verifies_signed_messages() {
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let public_key = hex::encode(signing_key.verifying_key().as_bytes());
+
+        let message = signing_message(
+            "kaaaaaaaaa",
+            "nonce",
+            "POST",
+            "/api/krist/transactions",
+            b"{}",
+        );
+        let signature = hex::encode(signing_key.sign(message.as_bytes()).to_bytes());
+
+        assert!(verify_signature(&public_key, &message, &signature));
+
+        let tampered = signing_message(
+            "kaaaaaaaaa",
+            "nonce",
+            "POST",
+            "/api/krist/transactions",
+            b"{\"amount\":1}",
+        );
+        assert!(!verify_signature(&public_key, &tampered, &signature));
+        assert!(!verify_signature(&public_key, &message, "not hex"));
+        assert!(!verify_signature("00", &message, &signature));
+    }
+}
@@ -0,0 +1,92 @@
+//! Batch transfers, sending from one wallet to many recipients at once.
+//!
+//! Every leg of a batch is validated before anything is sent, and the legs are committed in a
+//! single database transaction so a batch is either made in full or not at all.
+use rust_decimal::Decimal;
+use surrealdb::{engine::any::Any, Surreal, Uuid};
+
+use crate::database::models::transaction::{self, BatchLeg, BatchOutcome};
+use crate::errors::krist::{
+    address::AddressError, generic::GenericError, transaction::TransactionError, KristError,
+};
+use crate::invoices;
+use crate::models::websockets::{WebSocketEvent, WebSocketMessage};
+use crate::websockets::WebSocketServer;
+
+/// The most legs a single batch can have.
+pub const MAX_LEGS: usize = 100;
+
+/// The transactions made by a batch, one per leg.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Batch {
+    pub id: String,
+    pub transactions: Vec<transaction::Model>,
+}
+
+/// Check the legs of a batch, returning the total amount it sends.
+pub fn validate(legs: &[BatchLeg]) -> Result<Decimal, GenericError> {
+    if legs.is_empty() || legs.len() > MAX_LEGS {
+        return Err(GenericError::InvalidParameter("transfers".to_string()));
+    }
+    if legs.iter().any(|leg| leg.amount <= Decimal::ZERO) {
+        return Err(GenericError::InvalidParameter("amount".to_string()));
+    }
+
+    Ok(legs.iter().map(|leg| leg.amount).sum())
+}
+
+/// Send a validated batch, announcing every leg and linking it to the invoice it pays.
+pub async fn send(
+    db: &Surreal<Any>,
+    server: &WebSocketServer,
+    from: &str,
+    legs: &[BatchLeg],
+) -> Result<Batch, KristError> {
+    let id = Uuid::new_v4().simple().to_string();
+
+    let transactions = match transaction::Model::create_batch(db, from, &id, legs).await? {
+        BatchOutcome::Transferred { transactions } => transactions,
+        BatchOutcome::InsufficientFunds { .. } => {
+            return Err(KristError::Transaction(TransactionError::InsufficientFunds));
+        }
+        BatchOutcome::RecipientsMissing { addresses } => {
+            let address = addresses.into_iter().next().unwrap_or_default();
+            return Err(KristError::Address(AddressError::NotFound(address)));
+        }
+    };
+
+    for transaction in &transactions {
+        let event = WebSocketMessage::new_event(WebSocketEvent::Transaction {
+            transaction: transaction.clone().into(),
+        });
+        server.broadcast_event(event).await;
+        invoices::link_payment(db, server, transaction).await;
+    }
+
+    Ok(Batch { id, transactions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn leg(amount: Decimal) -> BatchLeg {
+        BatchLeg {
+            to: "kaaaaaaaaa".to_owned(),
+            amount,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(
+            validate(&[leg(dec!(1.5)), leg(dec!(2))]).unwrap(),
+            dec!(3.5)
+        );
+        assert!(validate(&[]).is_err());
+        assert!(validate(&[leg(dec!(1)), leg(dec!(0))]).is_err());
+        assert!(validate(&vec![leg(dec!(1)); MAX_LEGS + 1]).is_err());
+    }
+}
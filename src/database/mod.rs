@@ -1,2 +1,5 @@
 pub mod db;
 pub mod models;
+
+#[cfg(test)]
+pub(crate) mod testing;
@@ -0,0 +1,371 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use rust_decimal::Decimal;
+use surrealdb::{
+    engine::any::Any,
+    sql::{Datetime, Id, Thing},
+    Surreal,
+};
+
+use super::{serialize_record_opt, transaction, unexpected_response, CountResponse};
+use crate::routes::PaginationParams;
+
+/// The right of a spender to transfer up to `amount` from the wallet of its owner.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Model {
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_record_opt"
+    )]
+    pub id: Option<Thing>,
+    pub owner: String,
+    pub spender: String,
+    pub amount: Decimal,
+    /// Spent in the current period, or in total when there is no period.
+    pub spent: Decimal,
+    /// Seconds after which `spent` starts over, never when `None`.
+    pub period: Option<u64>,
+    pub period_start: Datetime,
+    /// The only addresses the spender may send to, any when `None`.
+    pub recipients: Option<Vec<String>>,
+    pub created_at: Datetime,
+    pub updated_at: Option<Datetime>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct AllowanceCreateData {
+    pub owner: String,
+    pub spender: String,
+    pub amount: Decimal,
+    pub period: Option<u64>,
+    pub recipients: Option<Vec<String>>,
+}
+
+/// Result of [`Model::spend`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpendOutcome {
+    Spent {
+        transaction: Box<transaction::Model>,
+    },
+    /// The owner did not grant the spender an allowance, or revoked it.
+    NoAllowance,
+    RecipientNotAllowed,
+    /// Only `remaining` can still be spent.
+    ExceedsAllowance {
+        remaining: Decimal,
+    },
+    InsufficientFunds,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SpendStatus {
+    Spent,
+    NoAllowance,
+    RecipientNotAllowed,
+    ExceedsAllowance,
+    InsufficientFunds,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+struct SpendResponse {
+    status: SpendStatus,
+    remaining: Option<Decimal>,
+    transaction: Option<transaction::Model>,
+}
+
+impl Model {
+    /// Grant an allowance, replacing the one the owner gave the spender before.
+    pub async fn create(
+        db: &Surreal<Any>,
+        data: AllowanceCreateData,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let q = r#"
+            BEGIN TRANSACTION;
+            DELETE allowance WHERE owner = $data.owner AND spender = $data.spender;
+            LET $allowance = (CREATE allowance CONTENT $data)[0];
+            RETURN $allowance;
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db.query(q).bind(("data", data)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Get an allowance from its unique ID, not including the table part
+    pub async fn get_partial<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        id: S,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let thing = Thing::from(("allowance", Id::from(id.as_ref())));
+        let q = "SELECT * FROM allowance WHERE id = $id;";
+
+        let mut response = db.query(q).bind(("id", thing)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Get the allowances an address granted or was granted, newest first. Also returns the total amount of them.
+    pub async fn involving<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        address: S,
+        pagination: &PaginationParams,
+    ) -> Result<(Vec<Model>, usize), surrealdb::Error> {
+        let limit = pagination.limit.unwrap_or(50).clamp(1, 1000);
+        let offset = pagination.offset.unwrap_or(0);
+
+        let q = r#"
+            SELECT * FROM allowance WHERE owner = $address OR spender = $address ORDER BY created_at DESC LIMIT $limit START $offset;
+            (SELECT count() FROM allowance WHERE owner = $address OR spender = $address GROUP ALL)[0] or { count: 0 };
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("address", address.as_ref().to_owned()))
+            .bind(("limit", limit))
+            .bind(("offset", offset))
+            .await?;
+        let models: Vec<Model> = response.take(0)?;
+        let count: Option<CountResponse> = response.take(1)?;
+
+        Ok((models, count.unwrap_or_default().count))
+    }
+
+    /// When the current period ends, `None` without a period.
+    pub fn period_end(&self) -> Option<DateTime<Utc>> {
+        let period = TimeDelta::seconds(self.period? as i64);
+        Some(self.period_start.0 + period)
+    }
+
+    /// What the spender can still spend at `now`, the full amount again once the period ended.
+    pub fn remaining_at(&self, now: DateTime<Utc>) -> Decimal {
+        if self.period_end().is_some_and(|end| end <= now) {
+            return self.amount;
+        }
+
+        (self.amount - self.spent).max(Decimal::ZERO)
+    }
+
+    /// Transfer from the owner's wallet on behalf of the spender, counting it against their allowance.
+    ///
+    /// The allowance is checked and decremented in the same database transaction as the transfer, so
+    /// concurrent spends can't exceed it and a revoked allowance can't be spent from.
+    pub async fn spend(
+        db: &Surreal<Any>,
+        owner: String,
+        spender: String,
+        to: String,
+        amount: Decimal,
+        metadata: Option<String>,
+    ) -> Result<SpendOutcome, surrealdb::Error> {
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $allowance = (SELECT * FROM allowance WHERE owner = $owner AND spender = $spender)[0];
+            LET $renewed = $allowance.period != NONE
+                AND $allowance.period_start + duration::from::secs($allowance.period) <= time::now();
+            LET $spent = IF $renewed { <decimal> 0 } ELSE { $allowance.spent };
+            LET $balance = (SELECT VALUE balance FROM wallet WHERE address = $owner)[0];
+            LET $status = IF $allowance = NONE {
+                'no_allowance'
+            } ELSE IF $allowance.recipients != NONE AND $to NOTINSIDE $allowance.recipients {
+                'recipient_not_allowed'
+            } ELSE IF $spent + $amount > $allowance.amount {
+                'exceeds_allowance'
+            } ELSE IF $balance = NONE OR $balance < $amount {
+                'insufficient_funds'
+            } ELSE {
+                'spent'
+            };
+            LET $transaction = IF $status = 'spent' {
+                UPDATE $allowance.id SET
+                    spent = $spent + $amount,
+                    period_start = IF $renewed { time::now() } ELSE { period_start },
+                    updated_at = time::now();
+                (CREATE transaction CONTENT {
+                    from: $owner,
+                    to: $to,
+                    amount: $amount,
+                    metadata: $metadata,
+                    transaction_type: 'transfer',
+                })[0]
+            };
+            RETURN {
+                status: $status,
+                remaining: IF $allowance != NONE { $allowance.amount - $spent },
+                transaction: $transaction,
+            };
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("owner", owner))
+            .bind(("spender", spender))
+            .bind(("to", to))
+            .bind(("amount", amount))
+            .bind(("metadata", metadata))
+            .await?;
+        let response: Option<SpendResponse> = response.take(0)?;
+        let response =
+            response.ok_or_else(|| unexpected_response("spending an allowance gave no status"))?;
+
+        let outcome = match response.status {
+            SpendStatus::Spent => SpendOutcome::Spent {
+                transaction: response.transaction.map(Box::new).ok_or_else(|| {
+                    unexpected_response("spending an allowance did not create a transaction")
+                })?,
+            },
+            SpendStatus::NoAllowance => SpendOutcome::NoAllowance,
+            SpendStatus::RecipientNotAllowed => SpendOutcome::RecipientNotAllowed,
+            SpendStatus::ExceedsAllowance => SpendOutcome::ExceedsAllowance {
+                remaining: response.remaining.unwrap_or_default().max(Decimal::ZERO),
+            },
+            SpendStatus::InsufficientFunds => SpendOutcome::InsufficientFunds,
+        };
+
+        Ok(outcome)
+    }
+
+    /// Revoke the allowance, nothing more can be spent from it afterwards.
+    ///
+    /// Returns `None` if it was already revoked.
+    pub async fn revoke(&self, db: &Surreal<Any>) -> Result<Option<Model>, surrealdb::Error> {
+        let q = "DELETE $id RETURN BEFORE;";
+
+        let mut response = db.query(q).bind(("id", self.id.clone())).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::database::testing;
+
+    async fn grant(db: &Surreal<Any>, amount: Decimal, recipients: Option<Vec<String>>) -> Model {
+        let data = AllowanceCreateData {
+            owner: "kaaaaaaaaa".to_owned(),
+            spender: "kbbbbbbbbb".to_owned(),
+            amount,
+            period: None,
+            recipients,
+        };
+
+        Model::create(db, data).await.unwrap().unwrap()
+    }
+
+    async fn spend(db: &Surreal<Any>, to: &str, amount: Decimal) -> SpendOutcome {
+        let owner = "kaaaaaaaaa".to_owned();
+        let spender = "kbbbbbbbbb".to_owned();
+
+        Model::spend(db, owner, spender, to.to_owned(), amount, None)
+            .await
+            .unwrap()
+    }
+
+    fn allowance(period: Option<u64>) -> Model {
+        Model {
+            id: None,
+            owner: "kaaaaaaaaa".to_owned(),
+            spender: "kbbbbbbbbb".to_owned(),
+            amount: dec!(100),
+            spent: dec!(30),
+            period,
+            period_start: Datetime::from(Utc::now() - TimeDelta::hours(2)),
+            recipients: None,
+            created_at: Datetime::default(),
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn remaining_without_period() {
+        let allowance = allowance(None);
+        assert_eq!(allowance.period_end(), None);
+        assert_eq!(allowance.remaining_at(Utc::now()), dec!(70));
+    }
+
+    #[test]
+    fn remaining_starts_over_after_period() {
+        // Two hours into a daily period.
+        assert_eq!(allowance(Some(86400)).remaining_at(Utc::now()), dec!(70));
+        // And past an hourly one.
+        assert_eq!(allowance(Some(3600)).remaining_at(Utc::now()), dec!(100));
+    }
+
+    #[actix_web::test]
+    async fn spends_no_more_than_allowed() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kaaaaaaaaa", "hash", 100).await;
+        testing::insert_wallet(&db, "kccccccccc", "hash", 0).await;
+        grant(&db, dec!(50), None).await;
+
+        let outcome = spend(&db, "kccccccccc", dec!(30)).await;
+        assert!(matches!(outcome, SpendOutcome::Spent { .. }));
+        let outcome = spend(&db, "kccccccccc", dec!(30)).await;
+        assert_eq!(
+            outcome,
+            SpendOutcome::ExceedsAllowance {
+                remaining: dec!(20)
+            }
+        );
+        let outcome = spend(&db, "kccccccccc", dec!(20)).await;
+        assert!(matches!(outcome, SpendOutcome::Spent { .. }));
+        let outcome = spend(&db, "kccccccccc", dec!(1)).await;
+        assert_eq!(
+            outcome,
+            SpendOutcome::ExceedsAllowance { remaining: dec!(0) }
+        );
+
+        assert_eq!(testing::balance(&db, "kaaaaaaaaa").await, dec!(50));
+        assert_eq!(testing::balance(&db, "kccccccccc").await, dec!(50));
+        let (allowances, _) = Model::involving(&db, "kbbbbbbbbb", &Default::default())
+            .await
+            .unwrap();
+        assert_eq!(allowances[0].spent, dec!(50));
+
+        // An allowance larger than the balance is still limited by it.
+        grant(&db, dec!(500), None).await;
+        let outcome = spend(&db, "kccccccccc", dec!(60)).await;
+        assert_eq!(outcome, SpendOutcome::InsufficientFunds);
+        assert_eq!(testing::balance(&db, "kaaaaaaaaa").await, dec!(50));
+    }
+
+    #[actix_web::test]
+    async fn spends_only_to_allowed_recipients() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kaaaaaaaaa", "hash", 100).await;
+        testing::insert_wallet(&db, "kccccccccc", "hash", 0).await;
+        testing::insert_wallet(&db, "kddddddddd", "hash", 0).await;
+        grant(&db, dec!(50), Some(vec!["kccccccccc".to_owned()])).await;
+
+        let outcome = spend(&db, "kddddddddd", dec!(10)).await;
+        assert_eq!(outcome, SpendOutcome::RecipientNotAllowed);
+        let outcome = spend(&db, "kccccccccc", dec!(10)).await;
+        assert!(matches!(outcome, SpendOutcome::Spent { .. }));
+
+        assert_eq!(testing::balance(&db, "kaaaaaaaaa").await, dec!(90));
+        assert_eq!(testing::balance(&db, "kccccccccc").await, dec!(10));
+        assert_eq!(testing::balance(&db, "kddddddddd").await, dec!(0));
+    }
+
+    #[actix_web::test]
+    async fn spends_nothing_once_revoked() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kaaaaaaaaa", "hash", 100).await;
+        testing::insert_wallet(&db, "kccccccccc", "hash", 0).await;
+        let allowance = grant(&db, dec!(50), None).await;
+
+        assert!(allowance.revoke(&db).await.unwrap().is_some());
+        assert_eq!(allowance.revoke(&db).await.unwrap(), None);
+        let outcome = spend(&db, "kccccccccc", dec!(10)).await;
+        assert_eq!(outcome, SpendOutcome::NoAllowance);
+        assert_eq!(testing::balance(&db, "kaaaaaaaaa").await, dec!(100));
+    }
+}
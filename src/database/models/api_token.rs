@@ -0,0 +1,188 @@
+use chrono::Utc;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use surrealdb::{
+    engine::any::Any,
+    sql::{Datetime, Id, Thing},
+    Surreal,
+};
+
+use super::serialize_record_opt;
+use crate::{models::tokens::TokenScope, utils};
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Model {
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_record_opt"
+    )]
+    pub id: Option<Thing>,
+    pub address: String,
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_hash: Option<String>, // Only needed when looking up a token.
+    pub scopes: Vec<TokenScope>,
+    pub daily_limit: Option<Decimal>,
+    pub spent: Decimal,
+    /// The UTC day `spent` was counted on, formatted as `YYYY-MM-DD`.
+    pub spent_day: Option<String>,
+    pub expires_at: Option<Datetime>,
+    pub last_used: Option<Datetime>,
+    pub created_at: Datetime,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ApiTokenCreateData {
+    pub address: String,
+    pub name: Option<String>,
+    pub token_hash: String,
+    pub scopes: Vec<TokenScope>,
+    pub daily_limit: Option<Decimal>,
+    pub expires_at: Option<Datetime>,
+}
+
+/// The current UTC day, used to reset the daily spend counter of tokens.
+fn today() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+impl Model {
+    /// Create a new token, the caller is responsible for hashing the secret.
+    pub async fn create(
+        db: &Surreal<Any>,
+        data: ApiTokenCreateData,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let q = "CREATE api_token CONTENT $data;";
+
+        let mut response = db.query(q).bind(("data", data)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model.map(|model| Model {
+            token_hash: None,
+            ..model
+        }))
+    }
+
+    /// Get a token from its secret.
+    pub async fn get_by_token<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        token: S,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let token_hash = utils::crypto::sha256(token.as_ref());
+        let q = "SELECT * FROM api_token WHERE token_hash = $token_hash;";
+
+        let mut response = db.query(q).bind(("token_hash", token_hash)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Get all tokens of an address, omitting their hash.
+    pub async fn get_by_address<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        address: S,
+    ) -> Result<Vec<Model>, surrealdb::Error> {
+        let address = address.as_ref().to_owned();
+        let q = "SELECT * OMIT token_hash FROM api_token WHERE address = $address ORDER BY created_at DESC;";
+
+        let mut response = db.query(q).bind(("address", address)).await?;
+        let models: Vec<Model> = response.take(0)?;
+
+        Ok(models)
+    }
+
+    /// Revoke a token of an address by its ID, not including the table part.
+    pub async fn revoke<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        address: S,
+        id: S,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let address = address.as_ref().to_owned();
+        let thing = Thing::from(("api_token", Id::from(id.as_ref())));
+        let q = "DELETE api_token WHERE id = $id AND address = $address RETURN BEFORE;";
+
+        let mut response = db
+            .query(q)
+            .bind(("id", thing))
+            .bind(("address", address))
+            .await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model.map(|model| Model {
+            token_hash: None,
+            ..model
+        }))
+    }
+
+    /// Mark the token as used right now.
+    pub async fn touch(&self, db: &Surreal<Any>) -> Result<(), surrealdb::Error> {
+        let q = "UPDATE $id SET last_used = time::now();";
+
+        db.query(q).bind(("id", self.id.clone())).await?.check()?;
+
+        Ok(())
+    }
+
+    /// Count an amount towards today's spending, returns `false` if that would exceed the daily limit.
+    ///
+    /// The check and the update happen in a single statement so concurrent requests can't overspend.
+    pub async fn reserve_spend(
+        &self,
+        db: &Surreal<Any>,
+        amount: Decimal,
+    ) -> Result<bool, surrealdb::Error> {
+        let q = r#"
+            UPDATE $id SET
+                spent = IF spent_day = $day THEN spent + $amount ELSE $amount END,
+                spent_day = $day
+            WHERE daily_limit = NONE
+                OR (IF spent_day = $day THEN spent ELSE 0 END) + $amount <= daily_limit;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("id", self.id.clone()))
+            .bind(("day", today()))
+            .bind(("amount", amount))
+            .await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model.is_some())
+    }
+
+    /// Give back an amount reserved with [`Model::reserve_spend`] when the spend did not go through.
+    pub async fn release_spend(
+        &self,
+        db: &Surreal<Any>,
+        amount: Decimal,
+    ) -> Result<(), surrealdb::Error> {
+        let q = "UPDATE $id SET spent = math::max([spent - $amount, 0]) WHERE spent_day = $day;";
+
+        db.query(q)
+            .bind(("id", self.id.clone()))
+            .bind(("day", today()))
+            .bind(("amount", amount))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .as_ref()
+            .is_some_and(|expires_at| expires_at.0 <= Utc::now())
+    }
+
+    pub fn has_scope(&self, scope: TokenScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+
+    /// Amount spent today, the stored counter belongs to an earlier day once it rolls over.
+    pub fn spent_today(&self) -> Decimal {
+        match &self.spent_day {
+            Some(day) if *day == today() => self.spent,
+            _ => dec!(0),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use surrealdb::{engine::any::Any, sql::Datetime, sql::Thing, Surreal};
+
+use super::{serialize_record_opt, CountResponse};
+
+/// Who made a change recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActorType {
+    /// A service using the internal API, `actor` is the name of its key.
+    InternalKey,
+    /// A wallet acting on its own behalf, `actor` is its address.
+    Wallet,
+    /// A background job, `actor` is the name of the job.
+    System,
+}
+
+/// A single entry of the audit log. Entries are append-only, the database refuses to update or delete them.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Model {
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_record_opt"
+    )]
+    pub id: Option<Thing>,
+    pub actor_type: ActorType,
+    pub actor: String,
+    pub action: String,
+    pub target: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub request_id: Option<String>,
+    pub ip: Option<String>,
+    pub timestamp: Datetime,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct AuditLogCreateData {
+    pub actor_type: ActorType,
+    pub actor: String,
+    pub action: String,
+    pub target: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub request_id: Option<String>,
+    pub ip: Option<String>,
+}
+
+/// Filters for searching the audit log, every filter that is set has to match.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct AuditLogQuery {
+    pub actor_type: Option<ActorType>,
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    pub target: Option<String>,
+    pub request_id: Option<String>,
+    /// Only entries made at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only entries made before this time.
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+impl AuditLogQuery {
+    fn condition(&self) -> String {
+        let filters = [
+            (self.actor_type.is_some(), "actor_type = $actor_type"),
+            (self.actor.is_some(), "actor = $actor"),
+            (self.action.is_some(), "action = $action"),
+            (self.target.is_some(), "target = $target"),
+            (self.request_id.is_some(), "request_id = $request_id"),
+            (self.since.is_some(), "timestamp >= $since"),
+            (self.until.is_some(), "timestamp < $until"),
+        ];
+        let filters: Vec<&str> = filters
+            .into_iter()
+            .filter_map(|(set, filter)| set.then_some(filter))
+            .collect();
+
+        if filters.is_empty() {
+            return String::new();
+        }
+
+        format!("WHERE {}", filters.join(" AND "))
+    }
+}
+
+impl Model {
+    /// Append an entry to the audit log.
+    pub async fn create(
+        db: &Surreal<Any>,
+        data: AuditLogCreateData,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let q = "CREATE audit_log CONTENT $data;";
+
+        let mut response = db.query(q).bind(("data", data)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Search the audit log, newest entries first. Also returns the total amount of matching entries.
+    pub async fn search(
+        db: &Surreal<Any>,
+        query: &AuditLogQuery,
+    ) -> Result<(Vec<Model>, usize), surrealdb::Error> {
+        let limit = query.limit.unwrap_or(50).clamp(1, 1000);
+        let offset = query.offset.unwrap_or(0);
+        let condition = query.condition();
+
+        let q = format!(
+            "SELECT * FROM audit_log {condition} ORDER BY timestamp DESC LIMIT $limit START $offset;
+            (SELECT count() FROM audit_log {condition} GROUP ALL)[0] or {{ count: 0 }};"
+        );
+
+        let mut response = db
+            .query(q)
+            .bind(("actor_type", query.actor_type))
+            .bind(("actor", query.actor.clone()))
+            .bind(("action", query.action.clone()))
+            .bind(("target", query.target.clone()))
+            .bind(("request_id", query.request_id.clone()))
+            .bind(("since", query.since.map(Datetime::from)))
+            .bind(("until", query.until.map(Datetime::from)))
+            .bind(("limit", limit))
+            .bind(("offset", offset))
+            .await?;
+        let models: Vec<Model> = response.take(0)?;
+        let count: Option<CountResponse> = response.take(1)?;
+
+        Ok((models, count.unwrap_or_default().count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_filters_on_set_fields() {
+        assert_eq!(AuditLogQuery::default().condition(), "");
+
+        let query = AuditLogQuery {
+            actor_type: Some(ActorType::InternalKey),
+            target: Some("kaaaaaaaaa".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            query.condition(),
+            "WHERE actor_type = $actor_type AND target = $target"
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{TimeDelta, Utc};
+use surrealdb::{
+    engine::any::Any,
+    sql::{Datetime, Thing},
+    Surreal,
+};
+
+use super::serialize_record_opt;
+use crate::utils;
+
+/// How long a nonce can be used to sign a request.
+pub const NONCE_LIFETIME: TimeDelta = TimeDelta::minutes(5);
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Model {
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_record_opt"
+    )]
+    pub id: Option<Thing>,
+    pub address: String,
+    pub nonce: String,
+    pub expires_at: Datetime,
+}
+
+impl Model {
+    /// Issue a new nonce for an address, clearing out expired ones while at it.
+    pub async fn issue<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        address: S,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let address = address.as_ref().to_owned();
+        let nonce = utils::crypto::generate_random_password();
+        let expires_at: Datetime = (Utc::now() + NONCE_LIFETIME).into();
+
+        let q = r#"
+            DELETE auth_nonce WHERE expires_at < time::now();
+            CREATE auth_nonce SET address = $address, nonce = $nonce, expires_at = $expires_at;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("address", address))
+            .bind(("nonce", nonce))
+            .bind(("expires_at", expires_at))
+            .await?;
+        let model: Option<Model> = response.take(1)?;
+
+        Ok(model)
+    }
+
+    /// Use up a nonce, returns `false` if it was never issued to the address, already used, or expired.
+    pub async fn consume<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        address: S,
+        nonce: S,
+    ) -> Result<bool, surrealdb::Error> {
+        let address = address.as_ref().to_owned();
+        let nonce = nonce.as_ref().to_owned();
+        let q = "DELETE auth_nonce WHERE nonce = $nonce AND address = $address RETURN BEFORE;";
+
+        let mut response = db
+            .query(q)
+            .bind(("address", address))
+            .bind(("nonce", nonce))
+            .await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model.is_some_and(|model| model.expires_at.0 > Utc::now()))
+    }
+}
@@ -0,0 +1,314 @@
+use rust_decimal::Decimal;
+use surrealdb::{
+    engine::any::Any,
+    sql::{Datetime, Id, Thing},
+    Surreal,
+};
+
+use super::{serialize_record_opt, transaction, CountResponse};
+use crate::routes::PaginationParams;
+
+/// The ledger address escrowed funds are sent to and paid out from. It is not a wallet, so it holds
+/// no balance of its own and can't be sent to through the transfer endpoints. `fn::transfer_balance`
+/// lets it go without one, funded escrows are counted in the supply instead.
+pub const ESCROW_ADDRESS: &str = "escrow";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscrowStatus {
+    /// The buyer's funds are held.
+    #[default]
+    Funded,
+    /// The funds were paid to the seller.
+    Released,
+    /// The funds were returned to the buyer by the arbiter or the seller.
+    Refunded,
+    /// The funds were returned to the buyer because the deadline passed.
+    Expired,
+}
+
+/// Funds of a buyer held until they are released to the seller or returned.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Model {
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_record_opt"
+    )]
+    pub id: Option<Thing>,
+    pub buyer: String,
+    pub seller: String,
+    pub arbiter: Option<String>,
+    pub amount: Decimal,
+    pub memo: Option<String>,
+    pub status: EscrowStatus,
+    pub deadline: Datetime,
+    /// The transaction moving the buyer's funds into escrow.
+    pub funding: Option<Thing>,
+    /// The transaction paying the funds out again.
+    pub settlement: Option<Thing>,
+    pub created_at: Datetime,
+    pub settled_at: Option<Datetime>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct EscrowCreateData {
+    pub buyer: String,
+    pub seller: String,
+    pub arbiter: Option<String>,
+    pub amount: Decimal,
+    pub memo: Option<String>,
+    pub deadline: Datetime,
+}
+
+/// An escrow together with the transaction that moved its funds.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize)]
+pub struct EscrowUpdate {
+    pub escrow: Model,
+    pub transaction: transaction::Model,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+struct EscrowUpdateResponse {
+    escrow: Option<Model>,
+    transaction: Option<transaction::Model>,
+}
+
+impl EscrowUpdateResponse {
+    fn into_update(self) -> Option<EscrowUpdate> {
+        Some(EscrowUpdate {
+            escrow: self.escrow?,
+            transaction: self.transaction?,
+        })
+    }
+}
+
+impl Model {
+    /// Move the buyer's funds into a new escrow.
+    ///
+    /// Returns `None` without changing anything when the buyer can't afford it.
+    pub async fn create(
+        db: &Surreal<Any>,
+        data: EscrowCreateData,
+    ) -> Result<Option<EscrowUpdate>, surrealdb::Error> {
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $balance = (SELECT VALUE balance FROM wallet WHERE address = $data.buyer)[0];
+            LET $response = IF $balance != NONE AND $balance >= $data.amount {
+                LET $escrow = (CREATE escrow CONTENT $data)[0];
+                LET $transaction = (CREATE transaction CONTENT {
+                    from: $data.buyer,
+                    to: $escrow_address,
+                    amount: $data.amount,
+                    metadata: 'escrow=' + <string> record::id($escrow.id),
+                    transaction_type: 'escrow',
+                })[0];
+                {
+                    escrow: (UPDATE $escrow.id SET funding = $transaction.id)[0],
+                    transaction: $transaction,
+                }
+            };
+            RETURN $response OR {};
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("data", data))
+            .bind(("escrow_address", ESCROW_ADDRESS))
+            .await?;
+        let response: Option<EscrowUpdateResponse> = response.take(0)?;
+
+        Ok(response.and_then(EscrowUpdateResponse::into_update))
+    }
+
+    /// Get an escrow from its unique ID, not including the table part
+    pub async fn get_partial<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        id: S,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let thing = Thing::from(("escrow", Id::from(id.as_ref())));
+        let q = "SELECT * FROM escrow WHERE id = $id;";
+
+        let mut response = db.query(q).bind(("id", thing)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Get the escrows an address takes part in, newest first. Also returns the total amount of them.
+    pub async fn involving<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        address: S,
+        status: Option<EscrowStatus>,
+        pagination: &PaginationParams,
+    ) -> Result<(Vec<Model>, usize), surrealdb::Error> {
+        let limit = pagination.limit.unwrap_or(50).clamp(1, 1000);
+        let offset = pagination.offset.unwrap_or(0);
+        let condition = match status {
+            Some(_) => {
+                "(buyer = $address OR seller = $address OR arbiter = $address) AND status = $status"
+            }
+            None => "buyer = $address OR seller = $address OR arbiter = $address",
+        };
+
+        let q = format!(
+            "SELECT * FROM escrow WHERE {condition} ORDER BY created_at DESC LIMIT $limit START $offset;
+            (SELECT count() FROM escrow WHERE {condition} GROUP ALL)[0] or {{ count: 0 }};"
+        );
+
+        let mut response = db
+            .query(q)
+            .bind(("address", address.as_ref().to_owned()))
+            .bind(("status", status))
+            .bind(("limit", limit))
+            .bind(("offset", offset))
+            .await?;
+        let models: Vec<Model> = response.take(0)?;
+        let count: Option<CountResponse> = response.take(1)?;
+
+        Ok((models, count.unwrap_or_default().count))
+    }
+
+    /// Pay the funds out to the seller.
+    pub async fn release(
+        &self,
+        db: &Surreal<Any>,
+    ) -> Result<Option<EscrowUpdate>, surrealdb::Error> {
+        self.settle(db, EscrowStatus::Released).await
+    }
+
+    /// Return the funds to the buyer, either `Refunded` or `Expired`.
+    pub async fn refund(
+        &self,
+        db: &Surreal<Any>,
+        status: EscrowStatus,
+    ) -> Result<Option<EscrowUpdate>, surrealdb::Error> {
+        self.settle(db, status).await
+    }
+
+    /// Move the funds out of escrow, to the seller once `Released` and the buyer otherwise. Returns
+    /// `None` if they were already paid out.
+    ///
+    /// Fails without changing anything when the wallet they are paid to no longer exists.
+    async fn settle(
+        &self,
+        db: &Surreal<Any>,
+        status: EscrowStatus,
+    ) -> Result<Option<EscrowUpdate>, surrealdb::Error> {
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $funded = (SELECT * FROM escrow WHERE id = $id AND status = 'funded')[0];
+            LET $to = IF $status = 'released' { $funded.seller } ELSE { $funded.buyer };
+            IF $funded != NONE AND count((SELECT id FROM wallet WHERE address = $to)) = 0 {
+                THROW "The wallet the escrow is paid to does not exist";
+            };
+            LET $escrow = (UPDATE $id SET status = $status, settled_at = time::now() WHERE status = 'funded')[0];
+            LET $response = IF $escrow != NONE {
+                LET $transaction = (CREATE transaction CONTENT {
+                    from: $escrow_address,
+                    to: $to,
+                    amount: $escrow.amount,
+                    metadata: 'escrow=' + <string> record::id($escrow.id),
+                    transaction_type: 'escrow',
+                    reference: $escrow.funding,
+                })[0];
+                {
+                    escrow: (UPDATE $id SET settlement = $transaction.id)[0],
+                    transaction: $transaction,
+                }
+            };
+            RETURN $response OR {};
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("id", self.id.clone()))
+            .bind(("status", status))
+            .bind(("escrow_address", ESCROW_ADDRESS))
+            .await?;
+        let response: Option<EscrowUpdateResponse> = response.take(0)?;
+
+        Ok(response.and_then(EscrowUpdateResponse::into_update))
+    }
+
+    /// Get the funded escrows whose deadline has passed.
+    pub async fn overdue(db: &Surreal<Any>) -> Result<Vec<Model>, surrealdb::Error> {
+        let q = "SELECT * FROM escrow WHERE status = 'funded' AND deadline <= time::now();";
+
+        let mut response = db.query(q).await?;
+        let models: Vec<Model> = response.take(0)?;
+
+        Ok(models)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::database::testing;
+
+    async fn create(db: &Surreal<Any>, amount: Decimal) -> Option<EscrowUpdate> {
+        let data = EscrowCreateData {
+            buyer: "kbuyer0001".to_owned(),
+            seller: "kseller001".to_owned(),
+            arbiter: None,
+            amount,
+            memo: None,
+            deadline: Datetime::default(),
+        };
+
+        Model::create(db, data).await.unwrap()
+    }
+
+    #[actix_web::test]
+    async fn settles_once() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kbuyer0001", "hash", 100).await;
+        testing::insert_wallet(&db, "kseller001", "hash", 0).await;
+
+        assert_eq!(create(&db, dec!(150)).await, None);
+        let escrow = create(&db, dec!(60)).await.unwrap().escrow;
+        assert_eq!(escrow.status, EscrowStatus::Funded);
+        assert_eq!(testing::balance(&db, "kbuyer0001").await, dec!(40));
+
+        let update = escrow.release(&db).await.unwrap().unwrap();
+        assert_eq!(update.escrow.status, EscrowStatus::Released);
+        assert_eq!(update.transaction.to, "kseller001");
+        assert_eq!(update.transaction.reference, escrow.funding);
+        assert_eq!(testing::balance(&db, "kseller001").await, dec!(60));
+
+        // The funds can't be paid out a second time.
+        assert_eq!(escrow.release(&db).await.unwrap(), None);
+        let refund = escrow.refund(&db, EscrowStatus::Refunded).await;
+        assert_eq!(refund.unwrap(), None);
+        assert_eq!(testing::balance(&db, "kbuyer0001").await, dec!(40));
+        assert_eq!(testing::balance(&db, "kseller001").await, dec!(60));
+    }
+
+    #[actix_web::test]
+    async fn keeps_the_funds_when_the_recipient_is_gone() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kbuyer0001", "hash", 100).await;
+        testing::insert_wallet(&db, "kseller001", "hash", 0).await;
+        let escrow = create(&db, dec!(60)).await.unwrap().escrow;
+
+        let q = "DELETE wallet WHERE address = 'kseller001';";
+        db.query(q).await.unwrap().check().unwrap();
+
+        assert!(escrow.release(&db).await.is_err());
+        let escrow = Model::get_partial(&db, escrow.id.as_ref().unwrap().id.to_raw())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(escrow.status, EscrowStatus::Funded);
+        assert_eq!(escrow.settlement, None);
+
+        // The buyer can still get them back.
+        let update = escrow.refund(&db, EscrowStatus::Refunded).await.unwrap();
+        assert_eq!(update.unwrap().transaction.to, "kbuyer0001");
+        assert_eq!(testing::balance(&db, "kbuyer0001").await, dec!(100));
+    }
+}
@@ -0,0 +1,394 @@
+use rust_decimal::Decimal;
+use surrealdb::{
+    engine::any::Any,
+    sql::{Datetime, Id, Thing},
+    Surreal,
+};
+
+use super::{serialize_record_opt, transaction, unexpected_response, CountResponse};
+use crate::routes::PaginationParams;
+use crate::utils::common_meta;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HoldStatus {
+    /// The merchant can still capture funds.
+    #[default]
+    Authorized,
+    /// The full amount was captured.
+    Captured,
+    /// The merchant released what was not captured.
+    Voided,
+    /// What was not captured was released once the hold expired.
+    Expired,
+}
+
+/// Funds of a payer reserved for a merchant, who can capture up to `amount` of them.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Model {
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_record_opt"
+    )]
+    pub id: Option<Thing>,
+    pub payer: String,
+    pub merchant: String,
+    pub amount: Decimal,
+    pub captured: Decimal,
+    pub memo: Option<String>,
+    pub status: HoldStatus,
+    /// The transactions made by captures.
+    pub transactions: Vec<Thing>,
+    pub expires_at: Datetime,
+    pub created_at: Datetime,
+    pub updated_at: Option<Datetime>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct HoldCreateData {
+    pub payer: String,
+    pub merchant: String,
+    pub amount: Decimal,
+    pub memo: Option<String>,
+    pub expires_at: Datetime,
+}
+
+/// Result of [`Model::capture`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum CaptureOutcome {
+    Captured {
+        hold: Box<Model>,
+        transaction: Box<transaction::Model>,
+    },
+    /// Only `remaining` can still be captured, or the amount was not positive.
+    ExceedsRemaining { remaining: Decimal },
+    /// The hold was captured in full, voided or expired.
+    NotAuthorized,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CaptureStatus {
+    Captured,
+    ExceedsRemaining,
+    NotAuthorized,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+struct CaptureResponse {
+    status: CaptureStatus,
+    remaining: Decimal,
+    hold: Option<Model>,
+    transaction: Option<transaction::Model>,
+}
+
+impl Model {
+    /// Move funds of the payer from their balance to their held balance and create the hold.
+    ///
+    /// Returns `None` without changing anything when the payer can't afford it.
+    pub async fn create(
+        db: &Surreal<Any>,
+        data: HoldCreateData,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $balance = (SELECT VALUE balance FROM wallet WHERE address = $data.payer)[0];
+            LET $hold = IF $balance != NONE AND $balance >= $data.amount {
+                UPDATE wallet SET balance -= $data.amount, held += $data.amount WHERE address = $data.payer;
+                (CREATE hold CONTENT $data)[0]
+            };
+            RETURN $hold;
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db.query(q).bind(("data", data)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Get a hold from its unique ID, not including the table part
+    pub async fn get_partial<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        id: S,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let thing = Thing::from(("hold", Id::from(id.as_ref())));
+        let q = "SELECT * FROM hold WHERE id = $id;";
+
+        let mut response = db.query(q).bind(("id", thing)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Get the holds an address authorized or was authorized, newest first. Also returns the total amount of them.
+    pub async fn involving<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        address: S,
+        status: Option<HoldStatus>,
+        pagination: &PaginationParams,
+    ) -> Result<(Vec<Model>, usize), surrealdb::Error> {
+        let limit = pagination.limit.unwrap_or(50).clamp(1, 1000);
+        let offset = pagination.offset.unwrap_or(0);
+        let condition = match status {
+            Some(_) => "(payer = $address OR merchant = $address) AND status = $status",
+            None => "payer = $address OR merchant = $address",
+        };
+
+        let q = format!(
+            "SELECT * FROM hold WHERE {condition} ORDER BY created_at DESC LIMIT $limit START $offset;
+            (SELECT count() FROM hold WHERE {condition} GROUP ALL)[0] or {{ count: 0 }};"
+        );
+
+        let mut response = db
+            .query(q)
+            .bind(("address", address.as_ref().to_owned()))
+            .bind(("status", status))
+            .bind(("limit", limit))
+            .bind(("offset", offset))
+            .await?;
+        let models: Vec<Model> = response.take(0)?;
+        let count: Option<CountResponse> = response.take(1)?;
+
+        Ok((models, count.unwrap_or_default().count))
+    }
+
+    /// The amount that can still be captured.
+    pub fn remaining(&self) -> Decimal {
+        (self.amount - self.captured).max(Decimal::ZERO)
+    }
+
+    /// The metadata of a capture, the merchant's own followed by `hold=<id>`.
+    pub fn capture_meta(&self, metadata: Option<&str>) -> String {
+        let id = self
+            .id
+            .as_ref()
+            .map(|id| id.id.to_raw())
+            .unwrap_or_default();
+
+        common_meta::join([metadata.unwrap_or_default(), &format!("hold={id}")])
+    }
+
+    /// Transfer `amount` of the held funds to the merchant, or everything that is left without one.
+    pub async fn capture(
+        &self,
+        db: &Surreal<Any>,
+        amount: Option<Decimal>,
+        metadata: String,
+    ) -> Result<CaptureOutcome, surrealdb::Error> {
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $hold = (SELECT * FROM hold WHERE id = $id)[0];
+            LET $remaining = $hold.amount - $hold.captured;
+            LET $amount = IF $requested = NONE { $remaining } ELSE { $requested };
+            LET $status = IF $hold.status != 'authorized' OR $hold.expires_at <= time::now() {
+                'not_authorized'
+            } ELSE IF $amount <= 0 OR $amount > $remaining {
+                'exceeds_remaining'
+            } ELSE {
+                'captured'
+            };
+            LET $transaction = IF $status = 'captured' {
+                -- Moved back to the balance first, the transfer takes it from there.
+                UPDATE wallet SET balance += $amount, held -= $amount WHERE address = $hold.payer;
+                (CREATE transaction CONTENT {
+                    from: $hold.payer,
+                    to: $hold.merchant,
+                    amount: $amount,
+                    metadata: $metadata,
+                    transaction_type: 'transfer',
+                })[0]
+            };
+            LET $hold = IF $status = 'captured' {
+                LET $captured = $hold.captured + $amount;
+                (UPDATE $id SET
+                    captured = $captured,
+                    transactions += $transaction.id,
+                    status = IF $captured >= $hold.amount { 'captured' } ELSE { 'authorized' },
+                    updated_at = time::now())[0]
+            } ELSE {
+                $hold
+            };
+            RETURN { status: $status, remaining: $remaining, hold: $hold, transaction: $transaction };
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("id", self.id.clone()))
+            .bind(("requested", amount))
+            .bind(("metadata", metadata))
+            .await?;
+        let response: Option<CaptureResponse> = response.take(0)?;
+        let response =
+            response.ok_or_else(|| unexpected_response("capturing a hold gave no status"))?;
+
+        let outcome = match response.status {
+            CaptureStatus::Captured => CaptureOutcome::Captured {
+                hold: response
+                    .hold
+                    .map(Box::new)
+                    .ok_or_else(|| unexpected_response("a captured hold no longer exists"))?,
+                transaction: response.transaction.map(Box::new).ok_or_else(|| {
+                    unexpected_response("capturing a hold did not create a transaction")
+                })?,
+            },
+            CaptureStatus::ExceedsRemaining => CaptureOutcome::ExceedsRemaining {
+                remaining: response.remaining,
+            },
+            CaptureStatus::NotAuthorized => CaptureOutcome::NotAuthorized,
+        };
+
+        Ok(outcome)
+    }
+
+    /// Give what was not captured back to the payer, either `Voided` or `Expired`.
+    ///
+    /// Returns `None` if the hold was no longer authorized.
+    pub async fn release(
+        &self,
+        db: &Surreal<Any>,
+        status: HoldStatus,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $hold = (UPDATE $id SET status = $status, updated_at = time::now() WHERE status = 'authorized')[0];
+            IF $hold != NONE {
+                LET $remaining = $hold.amount - $hold.captured;
+                UPDATE wallet SET balance += $remaining, held -= $remaining WHERE address = $hold.payer;
+            };
+            RETURN $hold;
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("id", self.id.clone()))
+            .bind(("status", status))
+            .await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Get the authorized holds that are past their expiry.
+    pub async fn overdue(db: &Surreal<Any>) -> Result<Vec<Model>, surrealdb::Error> {
+        let q = "SELECT * FROM hold WHERE status = 'authorized' AND expires_at <= time::now();";
+
+        let mut response = db.query(q).await?;
+        let models: Vec<Model> = response.take(0)?;
+
+        Ok(models)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, Utc};
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::database::testing;
+
+    async fn create(db: &Surreal<Any>, amount: Decimal) -> Option<Model> {
+        let data = HoldCreateData {
+            payer: "kpayer0000".to_owned(),
+            merchant: "kmerchant0".to_owned(),
+            amount,
+            memo: None,
+            expires_at: (Utc::now() + TimeDelta::hours(1)).into(),
+        };
+
+        Model::create(db, data).await.unwrap()
+    }
+
+    #[test]
+    fn capture_meta_tags_the_hold() {
+        let hold = Model {
+            id: Some(Thing::from(("hold", "abc"))),
+            payer: "kpayer0000".to_owned(),
+            merchant: "kmerchant0".to_owned(),
+            amount: dec!(10),
+            captured: Decimal::ZERO,
+            memo: None,
+            status: HoldStatus::Authorized,
+            transactions: Vec::new(),
+            expires_at: Datetime::default(),
+            created_at: Datetime::default(),
+            updated_at: None,
+        };
+
+        assert_eq!(hold.capture_meta(None), "hold=abc");
+        assert_eq!(hold.capture_meta(Some("order=12")), "order=12;hold=abc");
+    }
+
+    #[actix_web::test]
+    async fn captures_no_more_than_held() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kpayer0000", "hash", 100).await;
+        testing::insert_wallet(&db, "kmerchant0", "hash", 0).await;
+
+        assert_eq!(create(&db, dec!(150)).await, None);
+        let hold = create(&db, dec!(60)).await.unwrap();
+        let wallet = testing::wallet(&db, "kpayer0000").await;
+        assert_eq!((wallet.balance, wallet.held), (dec!(40), dec!(60)));
+
+        let outcome = hold.capture(&db, Some(dec!(25)), String::new()).await;
+        let CaptureOutcome::Captured { hold, transaction } = outcome.unwrap() else {
+            panic!("expected a capture");
+        };
+        assert_eq!(hold.status, HoldStatus::Authorized);
+        assert_eq!(transaction.amount, dec!(25));
+        let wallet = testing::wallet(&db, "kpayer0000").await;
+        assert_eq!((wallet.balance, wallet.held), (dec!(40), dec!(35)));
+        assert_eq!(testing::balance(&db, "kmerchant0").await, dec!(25));
+
+        for amount in [dec!(50), dec!(0), dec!(-5)] {
+            let outcome = hold.capture(&db, Some(amount), String::new()).await;
+            assert_eq!(
+                outcome.unwrap(),
+                CaptureOutcome::ExceedsRemaining {
+                    remaining: dec!(35)
+                }
+            );
+        }
+
+        // The same hold captured twice, the second time sees it was captured in full.
+        let outcome = hold.capture(&db, None, String::new()).await;
+        let CaptureOutcome::Captured { hold: captured, .. } = outcome.unwrap() else {
+            panic!("expected a capture");
+        };
+        assert_eq!(captured.status, HoldStatus::Captured);
+        assert_eq!(captured.transactions.len(), 2);
+        let outcome = hold.capture(&db, None, String::new()).await;
+        assert_eq!(outcome.unwrap(), CaptureOutcome::NotAuthorized);
+
+        let wallet = testing::wallet(&db, "kpayer0000").await;
+        assert_eq!((wallet.balance, wallet.held), (dec!(40), dec!(0)));
+        assert_eq!(testing::balance(&db, "kmerchant0").await, dec!(60));
+        assert_eq!(hold.release(&db, HoldStatus::Voided).await.unwrap(), None);
+    }
+
+    #[actix_web::test]
+    async fn releases_what_was_not_captured() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kpayer0000", "hash", 100).await;
+        testing::insert_wallet(&db, "kmerchant0", "hash", 0).await;
+
+        let hold = create(&db, dec!(60)).await.unwrap();
+        let outcome = hold.capture(&db, Some(dec!(10)), String::new()).await;
+        assert!(matches!(outcome, Ok(CaptureOutcome::Captured { .. })));
+
+        let released = hold.release(&db, HoldStatus::Voided).await.unwrap();
+        assert_eq!(released.unwrap().status, HoldStatus::Voided);
+        let wallet = testing::wallet(&db, "kpayer0000").await;
+        assert_eq!((wallet.balance, wallet.held), (dec!(90), dec!(0)));
+        assert_eq!(testing::balance(&db, "kmerchant0").await, dec!(10));
+
+        assert_eq!(hold.release(&db, HoldStatus::Expired).await.unwrap(), None);
+        let outcome = hold.capture(&db, None, String::new()).await;
+        assert_eq!(outcome.unwrap(), CaptureOutcome::NotAuthorized);
+        let wallet = testing::wallet(&db, "kpayer0000").await;
+        assert_eq!((wallet.balance, wallet.held), (dec!(90), dec!(0)));
+    }
+}
@@ -0,0 +1,206 @@
+use rust_decimal::Decimal;
+use surrealdb::{
+    engine::any::Any,
+    sql::{Datetime, Id, Thing},
+    Surreal,
+};
+
+use super::{serialize_record_opt, transaction, CountResponse};
+use crate::routes::PaginationParams;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    /// Waiting for (the rest of) the payment.
+    #[default]
+    Pending,
+    /// Exactly the requested amount was paid.
+    Paid,
+    /// Not fully paid before it expired.
+    Expired,
+    /// More than the requested amount was paid.
+    Overpaid,
+}
+
+/// A payment request of a merchant wallet.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Model {
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_record_opt"
+    )]
+    pub id: Option<Thing>,
+    /// The address that gets paid.
+    pub merchant: String,
+    pub amount: Decimal,
+    /// The sum of all payments linked to the invoice.
+    pub paid: Decimal,
+    pub memo: Option<String>,
+    /// Payments sent to `<metaname>@<name>.kst` of the merchant are linked to the invoice.
+    pub metaname: Option<String>,
+    pub status: InvoiceStatus,
+    pub transactions: Vec<Thing>,
+    pub expires_at: Datetime,
+    pub created_at: Datetime,
+    pub updated_at: Option<Datetime>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct InvoiceCreateData {
+    pub merchant: String,
+    pub amount: Decimal,
+    pub memo: Option<String>,
+    pub metaname: Option<String>,
+    pub expires_at: Datetime,
+}
+
+impl Model {
+    /// Create a new pending invoice.
+    pub async fn create(
+        db: &Surreal<Any>,
+        data: InvoiceCreateData,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let q = "CREATE invoice CONTENT $data;";
+
+        let mut response = db.query(q).bind(("data", data)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Get an invoice from its unique ID, not including the table part
+    pub async fn get_partial<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        id: S,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let thing = Thing::from(("invoice", Id::from(id.as_ref())));
+        let q = "SELECT * FROM invoice WHERE id = $id;";
+
+        let mut response = db.query(q).bind(("id", thing)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Get the invoices of a merchant, newest first. Also returns the total amount of matching invoices.
+    pub async fn by_merchant<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        merchant: S,
+        status: Option<InvoiceStatus>,
+        pagination: &PaginationParams,
+    ) -> Result<(Vec<Model>, usize), surrealdb::Error> {
+        let limit = pagination.limit.unwrap_or(50).clamp(1, 1000);
+        let offset = pagination.offset.unwrap_or(0);
+        let condition = match status {
+            Some(_) => "merchant = $merchant AND status = $status",
+            None => "merchant = $merchant",
+        };
+
+        let q = format!(
+            "SELECT * FROM invoice WHERE {condition} ORDER BY created_at DESC LIMIT $limit START $offset;
+            (SELECT count() FROM invoice WHERE {condition} GROUP ALL)[0] or {{ count: 0 }};"
+        );
+
+        let mut response = db
+            .query(q)
+            .bind(("merchant", merchant.as_ref().to_owned()))
+            .bind(("status", status))
+            .bind(("limit", limit))
+            .bind(("offset", offset))
+            .await?;
+        let models: Vec<Model> = response.take(0)?;
+        let count: Option<CountResponse> = response.take(1)?;
+
+        Ok((models, count.unwrap_or_default().count))
+    }
+
+    /// Whether the merchant ever used a deposit metaname, they can only be used by a single invoice.
+    pub async fn metaname_used<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        merchant: S,
+        metaname: S,
+    ) -> Result<bool, surrealdb::Error> {
+        let q = "RETURN count((SELECT id FROM invoice WHERE merchant = $merchant AND metaname = $metaname LIMIT 1)) > 0;";
+
+        let mut response = db
+            .query(q)
+            .bind(("merchant", merchant.as_ref().to_owned()))
+            .bind(("metaname", metaname.as_ref().to_owned()))
+            .await?;
+        let used: Option<bool> = response.take(0)?;
+
+        Ok(used.unwrap_or(false))
+    }
+
+    /// Get the invoice of a merchant that can still be paid through a deposit metaname.
+    pub async fn get_by_metaname<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        merchant: S,
+        metaname: S,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let q = "SELECT * FROM invoice WHERE merchant = $merchant AND metaname = $metaname AND status != 'expired' LIMIT 1;";
+
+        let mut response = db
+            .query(q)
+            .bind(("merchant", merchant.as_ref().to_owned()))
+            .bind(("metaname", metaname.as_ref().to_owned()))
+            .await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// The amount that still has to be paid.
+    pub fn outstanding(&self) -> Decimal {
+        (self.amount - self.paid).max(Decimal::ZERO)
+    }
+
+    /// Link a payment to the invoice, updating its status.
+    ///
+    /// Returns `None` when the invoice expired in the meantime, the payment is then not linked.
+    pub async fn record_payment(
+        &self,
+        db: &Surreal<Any>,
+        transaction: &transaction::Model,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $updated = (UPDATE $id SET
+                    paid += $amount,
+                    transactions += $transaction,
+                    updated_at = time::now()
+                WHERE status != 'expired' AND expires_at > time::now());
+            LET $updated = IF array::len($updated) > 0 {
+                (UPDATE $id SET status = IF paid > amount {
+                    'overpaid'
+                } ELSE IF paid = amount {
+                    'paid'
+                } ELSE {
+                    'pending'
+                })
+            };
+            RETURN $updated[0];
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("id", self.id.clone()))
+            .bind(("amount", transaction.amount))
+            .bind(("transaction", transaction.id.clone()))
+            .await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Expire every pending invoice that is past its expiry, returning them.
+    pub async fn expire_due(db: &Surreal<Any>) -> Result<Vec<Model>, surrealdb::Error> {
+        let q = "UPDATE invoice SET status = 'expired', updated_at = time::now() WHERE status = 'pending' AND expires_at <= time::now();";
+
+        let mut response = db.query(q).await?;
+        let models: Vec<Model> = response.take(0)?;
+
+        Ok(models)
+    }
+}
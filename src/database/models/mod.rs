@@ -1,5 +1,14 @@
+pub mod allowance;
+pub mod api_token;
+pub mod audit_log;
+pub mod auth_nonce;
+pub mod escrow;
+pub mod hold;
+pub mod invoice;
 pub mod name;
 pub mod player;
+pub mod scheduled_transfer;
+pub mod standing_order;
 pub mod transaction;
 pub mod wallet;
 
@@ -11,6 +20,11 @@ pub struct CountResponse {
     pub count: usize,
 }
 
+/// Error for a query response missing something it should always contain, e.g. the status of an atomic query.
+pub fn unexpected_response(message: &str) -> surrealdb::Error {
+    surrealdb::Error::Api(surrealdb::error::Api::InternalError(message.to_owned()))
+}
+
 /// Serde serializer function that converts a record ID from a Thing to a raw string in the format `table:id`.
 pub fn serialize_record<S>(record: &Thing, s: S) -> Result<S::Ok, S::Error>
 where
@@ -8,8 +8,7 @@ use surrealdb::{
 use super::{serialize_record_opt, CountResponse};
 use crate::{
     database::models::wallet::Model as Wallet,
-    errors::krist::{address::AddressError, generic::GenericError, name::NameError, KristError},
-    models::names::NameDataUpdateBody,
+    errors::krist::{generic::GenericError, name::NameError, KristError},
     routes::PaginationParams,
     utils,
 };
@@ -201,14 +200,14 @@ impl Model {
         Ok(result.is_some())
     }
 
-    /// Modify the data for a given name with more checks
+    /// Modify the data for a given name with more checks, `owner` must already be authenticated.
     pub async fn ctrl_modify_data(
         db: &Surreal<Any>,
         name: String,
-        body: NameDataUpdateBody,
+        a_record: Option<String>,
+        owner: &Wallet,
     ) -> Result<Model, KristError> {
         // I have this code so much, i hate you, krist.
-        let a_record = body.a;
         if a_record.is_none() {
             return Err(KristError::Generic(GenericError::MissingParameter(
                 "a".to_owned(),
@@ -230,18 +229,12 @@ impl Model {
 
         let name = name.trim().to_lowercase();
 
-        let wallet = Wallet::verify_address(db, body.private_key).await?;
-        if !wallet.authed {
-            tracing::info!("Auth failed on name update");
-            return Err(KristError::Address(AddressError::AuthFailed));
-        }
-
         // I dont like this, please stop borrow checker :sob:
         let model = Model::get_by_name(db, name.clone())
             .await?
             .ok_or_else(|| KristError::Name(NameError::NameNotFound(name.clone())))?;
 
-        if model.owner != wallet.address.address {
+        if model.owner != owner.address {
             return Err(KristError::Name(NameError::NotNameOwner(name)));
         }
 
@@ -0,0 +1,355 @@
+use rust_decimal::Decimal;
+use surrealdb::{
+    engine::any::Any,
+    sql::{Datetime, Id, Thing},
+    Surreal,
+};
+
+use super::{serialize_record_opt, transaction, CountResponse};
+use crate::routes::PaginationParams;
+use crate::utils::common_meta;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduledTransferStatus {
+    /// Its funds are reserved until it executes.
+    #[default]
+    Pending,
+    /// The transfer was made.
+    Executed,
+    /// Cancelled by the sender, the funds went back to them.
+    Cancelled,
+    /// The recipient no longer existed or was locked when it was due, the funds went back to the sender.
+    Failed,
+}
+
+/// A transfer made once `execute_at` passed, its funds are reserved from the sender until then.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Model {
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_record_opt"
+    )]
+    pub id: Option<Thing>,
+    pub from: String,
+    pub to: String,
+    pub amount: Decimal,
+    pub metadata: Option<String>,
+    pub execute_at: Datetime,
+    pub status: ScheduledTransferStatus,
+    /// The transaction made when it executed.
+    pub transaction: Option<Thing>,
+    pub created_at: Datetime,
+    pub updated_at: Option<Datetime>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ScheduledTransferCreateData {
+    pub from: String,
+    pub to: String,
+    pub amount: Decimal,
+    pub metadata: Option<String>,
+    pub execute_at: Datetime,
+}
+
+/// A scheduled transfer that executed, together with the transaction it made if it succeeded.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize)]
+pub struct ExecutionUpdate {
+    pub transfer: Model,
+    pub transaction: Option<transaction::Model>,
+}
+
+impl Model {
+    /// Reserve the funds of the sender and schedule the transfer.
+    ///
+    /// Returns `None` without changing anything when the sender can't afford it.
+    pub async fn create(
+        db: &Surreal<Any>,
+        data: ScheduledTransferCreateData,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $balance = (SELECT VALUE balance FROM wallet WHERE address = $data.from)[0];
+            LET $transfer = IF $balance != NONE AND $balance >= $data.amount {
+                UPDATE wallet SET balance -= $data.amount, pending_out += $data.amount WHERE address = $data.from;
+                UPDATE wallet SET pending_in += $data.amount WHERE address = $data.to;
+                (CREATE scheduled_transfer CONTENT $data)[0]
+            };
+            RETURN $transfer;
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db.query(q).bind(("data", data)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Get a scheduled transfer from its unique ID, not including the table part
+    pub async fn get_partial<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        id: S,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let thing = Thing::from(("scheduled_transfer", Id::from(id.as_ref())));
+        let q = "SELECT * FROM scheduled_transfer WHERE id = $id;";
+
+        let mut response = db.query(q).bind(("id", thing)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Get the scheduled transfers from or to an address, soonest first. Also returns the total amount of them.
+    pub async fn involving<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        address: S,
+        status: Option<ScheduledTransferStatus>,
+        pagination: &PaginationParams,
+    ) -> Result<(Vec<Model>, usize), surrealdb::Error> {
+        let limit = pagination.limit.unwrap_or(50).clamp(1, 1000);
+        let offset = pagination.offset.unwrap_or(0);
+        let condition = match status {
+            Some(_) => "(from = $address OR to = $address) AND status = $status",
+            None => "from = $address OR to = $address",
+        };
+
+        let q = format!(
+            "SELECT * FROM scheduled_transfer WHERE {condition} ORDER BY execute_at ASC LIMIT $limit START $offset;
+            (SELECT count() FROM scheduled_transfer WHERE {condition} GROUP ALL)[0] or {{ count: 0 }};"
+        );
+
+        let mut response = db
+            .query(q)
+            .bind(("address", address.as_ref().to_owned()))
+            .bind(("status", status))
+            .bind(("limit", limit))
+            .bind(("offset", offset))
+            .await?;
+        let models: Vec<Model> = response.take(0)?;
+        let count: Option<CountResponse> = response.take(1)?;
+
+        Ok((models, count.unwrap_or_default().count))
+    }
+
+    /// The metadata of the transaction made when it executes, the sender's own followed by
+    /// `scheduled_transfer=<id>`.
+    pub fn execution_meta(&self) -> String {
+        let id = self
+            .id
+            .as_ref()
+            .map(|id| id.id.to_raw())
+            .unwrap_or_default();
+
+        common_meta::join([
+            self.metadata.clone().unwrap_or_default(),
+            format!("scheduled_transfer={id}"),
+        ])
+    }
+
+    /// Get the pending scheduled transfers that are due.
+    pub async fn due(db: &Surreal<Any>) -> Result<Vec<Model>, surrealdb::Error> {
+        let q = "SELECT * FROM scheduled_transfer WHERE status = 'pending' AND execute_at <= time::now();";
+
+        let mut response = db.query(q).await?;
+        let models: Vec<Model> = response.take(0)?;
+
+        Ok(models)
+    }
+
+    /// Make the transfer in a single database transaction, releasing the reserved funds into it.
+    ///
+    /// Returns `None` if it is not due or no longer pending.
+    pub async fn execute(
+        &self,
+        db: &Surreal<Any>,
+    ) -> Result<Option<ExecutionUpdate>, surrealdb::Error> {
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $recipient = (SELECT VALUE address FROM wallet WHERE address = $to AND locked = false)[0];
+            LET $transfer = (UPDATE $id SET
+                    status = IF $recipient = NONE { 'failed' } ELSE { 'executed' },
+                    updated_at = time::now()
+                WHERE status = 'pending' AND execute_at <= time::now())[0];
+            IF $transfer != NONE {
+                UPDATE wallet SET balance += $transfer.amount, pending_out -= $transfer.amount WHERE address = $transfer.from;
+                UPDATE wallet SET pending_in -= $transfer.amount WHERE address = $transfer.to;
+            };
+            -- The transfer takes the released funds from the sender's balance again.
+            LET $transaction = IF $transfer.status = 'executed' {
+                (CREATE transaction CONTENT {
+                    from: $transfer.from,
+                    to: $transfer.to,
+                    amount: $transfer.amount,
+                    metadata: $metadata,
+                    transaction_type: 'transfer',
+                })[0]
+            };
+            LET $transfer = IF $transaction != NONE {
+                (UPDATE $id SET transaction = $transaction.id)[0]
+            } ELSE {
+                $transfer
+            };
+            RETURN IF $transfer != NONE { { transfer: $transfer, transaction: $transaction } };
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("id", self.id.clone()))
+            .bind(("to", self.to.clone()))
+            .bind(("metadata", self.execution_meta()))
+            .await?;
+        let update: Option<ExecutionUpdate> = response.take(0)?;
+
+        Ok(update)
+    }
+
+    /// Cancel the transfer, giving the reserved funds back to the sender.
+    ///
+    /// Returns `None` if it was no longer pending.
+    pub async fn cancel(&self, db: &Surreal<Any>) -> Result<Option<Model>, surrealdb::Error> {
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $transfer = (UPDATE $id SET status = 'cancelled', updated_at = time::now() WHERE status = 'pending')[0];
+            IF $transfer != NONE {
+                UPDATE wallet SET balance += $transfer.amount, pending_out -= $transfer.amount WHERE address = $transfer.from;
+                UPDATE wallet SET pending_in -= $transfer.amount WHERE address = $transfer.to;
+            };
+            RETURN $transfer;
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db.query(q).bind(("id", self.id.clone())).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::testing;
+    use chrono::{TimeDelta, Utc};
+    use rust_decimal_macros::dec;
+
+    async fn schedule(db: &Surreal<Any>, to: &str, amount: Decimal, due: bool) -> Option<Model> {
+        let execute_at = match due {
+            true => Utc::now() - TimeDelta::seconds(1),
+            false => Utc::now() + TimeDelta::hours(1),
+        };
+        let data = ScheduledTransferCreateData {
+            from: "kaaaaaaaaa".to_owned(),
+            to: to.to_owned(),
+            amount,
+            metadata: None,
+            execute_at: execute_at.into(),
+        };
+
+        Model::create(db, data).await.unwrap()
+    }
+
+    #[test]
+    fn tags_executions_with_the_transfer() {
+        let mut transfer = Model {
+            id: Some(Thing::from(("scheduled_transfer", "abc123"))),
+            from: "kaaaaaaaaa".to_owned(),
+            to: "kbbbbbbbbb".to_owned(),
+            amount: dec!(250),
+            metadata: None,
+            execute_at: Datetime::default(),
+            status: ScheduledTransferStatus::Pending,
+            transaction: None,
+            created_at: Datetime::default(),
+            updated_at: None,
+        };
+        assert_eq!(transfer.execution_meta(), "scheduled_transfer=abc123");
+
+        transfer.metadata = Some("event=winter".to_owned());
+        assert_eq!(
+            transfer.execution_meta(),
+            "event=winter;scheduled_transfer=abc123"
+        );
+    }
+
+    #[actix_web::test]
+    async fn reserves_funds_until_executed_or_cancelled() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kaaaaaaaaa", "hash", 100).await;
+        testing::insert_wallet(&db, "kbbbbbbbbb", "hash", 0).await;
+
+        assert_eq!(schedule(&db, "kbbbbbbbbb", dec!(150), true).await, None);
+        let due = schedule(&db, "kbbbbbbbbb", dec!(40), true).await.unwrap();
+        let later = schedule(&db, "kbbbbbbbbb", dec!(25), false).await.unwrap();
+        let wallet = testing::wallet(&db, "kaaaaaaaaa").await;
+        assert_eq!(
+            (wallet.balance, wallet.pending_in, wallet.pending_out),
+            (dec!(35), dec!(0), dec!(65))
+        );
+        let wallet = testing::wallet(&db, "kbbbbbbbbb").await;
+        assert_eq!(
+            (wallet.balance, wallet.pending_in, wallet.pending_out),
+            (dec!(0), dec!(65), dec!(0))
+        );
+
+        assert_eq!(later.execute(&db).await.unwrap(), None);
+        let update = due.execute(&db).await.unwrap().unwrap();
+        assert_eq!(update.transfer.status, ScheduledTransferStatus::Executed);
+        assert_eq!(update.transaction.unwrap().amount, dec!(40));
+        assert_eq!(due.execute(&db).await.unwrap(), None);
+        let wallet = testing::wallet(&db, "kaaaaaaaaa").await;
+        assert_eq!(
+            (wallet.balance, wallet.pending_in, wallet.pending_out),
+            (dec!(35), dec!(0), dec!(25))
+        );
+        let wallet = testing::wallet(&db, "kbbbbbbbbb").await;
+        assert_eq!(
+            (wallet.balance, wallet.pending_in, wallet.pending_out),
+            (dec!(40), dec!(25), dec!(0))
+        );
+
+        let cancelled = later.cancel(&db).await.unwrap().unwrap();
+        assert_eq!(cancelled.status, ScheduledTransferStatus::Cancelled);
+        assert_eq!(later.cancel(&db).await.unwrap(), None);
+        assert_eq!(due.cancel(&db).await.unwrap(), None);
+        let wallet = testing::wallet(&db, "kaaaaaaaaa").await;
+        assert_eq!(
+            (wallet.balance, wallet.pending_in, wallet.pending_out),
+            (dec!(60), dec!(0), dec!(0))
+        );
+        let wallet = testing::wallet(&db, "kbbbbbbbbb").await;
+        assert_eq!(
+            (wallet.balance, wallet.pending_in, wallet.pending_out),
+            (dec!(40), dec!(0), dec!(0))
+        );
+    }
+
+    #[actix_web::test]
+    async fn fails_when_the_recipient_is_gone() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kaaaaaaaaa", "hash", 100).await;
+        testing::insert_wallet(&db, "klocked001", "hash", 0).await;
+
+        let missing = schedule(&db, "kmissing01", dec!(30), true).await.unwrap();
+        let locked = schedule(&db, "klocked001", dec!(20), true).await.unwrap();
+        let q = "UPDATE wallet SET locked = true WHERE address = 'klocked001';";
+        db.query(q).await.unwrap().check().unwrap();
+
+        for transfer in [missing, locked] {
+            let update = transfer.execute(&db).await.unwrap().unwrap();
+            assert_eq!(update.transfer.status, ScheduledTransferStatus::Failed);
+            assert_eq!(update.transaction, None);
+        }
+
+        let wallet = testing::wallet(&db, "kaaaaaaaaa").await;
+        assert_eq!(
+            (wallet.balance, wallet.pending_in, wallet.pending_out),
+            (dec!(100), dec!(0), dec!(0))
+        );
+        let wallet = testing::wallet(&db, "klocked001").await;
+        assert_eq!(
+            (wallet.balance, wallet.pending_in, wallet.pending_out),
+            (dec!(0), dec!(0), dec!(0))
+        );
+    }
+}
@@ -0,0 +1,463 @@
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use surrealdb::{
+    engine::any::Any,
+    sql::{Datetime, Id, Thing},
+    Surreal,
+};
+
+use super::{serialize_record_opt, transaction, unexpected_response, CountResponse};
+use crate::routes::PaginationParams;
+use crate::utils::common_meta;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StandingOrderStatus {
+    /// Executed whenever it is due.
+    #[default]
+    Active,
+    /// Paused by one of its parties.
+    Paused,
+    /// Paused after failing too often because the payer lacked the funds.
+    Suspended,
+    /// Cancelled by one of its parties, or because its recipient no longer exists.
+    Cancelled,
+    /// Reached its end date or amount of runs.
+    Completed,
+}
+
+/// A transfer repeated every `interval` seconds.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Model {
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_record_opt"
+    )]
+    pub id: Option<Thing>,
+    pub from: String,
+    pub to: String,
+    pub amount: Decimal,
+    pub metadata: Option<String>,
+    pub interval: u64,
+    /// When the next transfer is due.
+    pub next_run: Datetime,
+    /// When a transfer that failed for a lack of funds is tried again.
+    pub retry_at: Option<Datetime>,
+    pub ends_at: Option<Datetime>,
+    pub max_runs: Option<u64>,
+    pub runs: u64,
+    /// Failed attempts since the last successful transfer.
+    pub failures: u32,
+    pub status: StandingOrderStatus,
+    pub last_transaction: Option<Thing>,
+    pub created_at: Datetime,
+    pub updated_at: Option<Datetime>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct StandingOrderCreateData {
+    pub from: String,
+    pub to: String,
+    pub amount: Decimal,
+    pub metadata: Option<String>,
+    pub interval: u64,
+    pub next_run: Datetime,
+    pub ends_at: Option<Datetime>,
+    pub max_runs: Option<u64>,
+}
+
+/// Result of [`Model::execute`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExecutionOutcome {
+    Executed {
+        order: Box<Model>,
+        transaction: Box<transaction::Model>,
+    },
+    /// The payer lacked the funds, the order is either retried later or suspended.
+    InsufficientFunds { order: Box<Model> },
+    /// The recipient no longer exists, the order was cancelled.
+    RecipientMissing { order: Box<Model> },
+    /// The order was no longer due, e.g. because it was paused in the meantime.
+    Skipped,
+}
+
+#[derive(Clone, Copy, Debug, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ExecutionStatus {
+    Executed,
+    InsufficientFunds,
+    RecipientMissing,
+    Skipped,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+struct ExecutionResponse {
+    status: ExecutionStatus,
+    order: Option<Model>,
+    transaction: Option<transaction::Model>,
+}
+
+impl Model {
+    pub async fn create(
+        db: &Surreal<Any>,
+        data: StandingOrderCreateData,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let q = "CREATE standing_order CONTENT $data;";
+
+        let mut response = db.query(q).bind(("data", data)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Get a standing order from its unique ID, not including the table part
+    pub async fn get_partial<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        id: S,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let thing = Thing::from(("standing_order", Id::from(id.as_ref())));
+        let q = "SELECT * FROM standing_order WHERE id = $id;";
+
+        let mut response = db.query(q).bind(("id", thing)).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Get the standing orders an address pays or is paid by, newest first. Also returns the total amount of them.
+    pub async fn involving<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        address: S,
+        status: Option<StandingOrderStatus>,
+        pagination: &PaginationParams,
+    ) -> Result<(Vec<Model>, usize), surrealdb::Error> {
+        let limit = pagination.limit.unwrap_or(50).clamp(1, 1000);
+        let offset = pagination.offset.unwrap_or(0);
+        let condition = match status {
+            Some(_) => "(from = $address OR to = $address) AND status = $status",
+            None => "from = $address OR to = $address",
+        };
+
+        let q = format!(
+            "SELECT * FROM standing_order WHERE {condition} ORDER BY created_at DESC LIMIT $limit START $offset;
+            (SELECT count() FROM standing_order WHERE {condition} GROUP ALL)[0] or {{ count: 0 }};"
+        );
+
+        let mut response = db
+            .query(q)
+            .bind(("address", address.as_ref().to_owned()))
+            .bind(("status", status))
+            .bind(("limit", limit))
+            .bind(("offset", offset))
+            .await?;
+        let models: Vec<Model> = response.take(0)?;
+        let count: Option<CountResponse> = response.take(1)?;
+
+        Ok((models, count.unwrap_or_default().count))
+    }
+
+    /// The metadata of the transfers made by the order, its own metadata tagged with `standing_order=<id>`.
+    pub fn execution_meta(&self) -> String {
+        let id = self
+            .id
+            .as_ref()
+            .map(|id| id.id.to_raw())
+            .unwrap_or_default();
+
+        // Kept behind the order's own metadata, which may start with a `meta@name.kst` entry.
+        common_meta::join([
+            self.metadata.clone().unwrap_or_default(),
+            format!("standing_order={id}"),
+        ])
+    }
+
+    /// Get the active standing orders that are due, including retries.
+    pub async fn due(db: &Surreal<Any>) -> Result<Vec<Model>, surrealdb::Error> {
+        let q = "SELECT * FROM standing_order WHERE status = 'active' AND (retry_at OR next_run) <= time::now();";
+
+        let mut response = db.query(q).await?;
+        let models: Vec<Model> = response.take(0)?;
+
+        Ok(models)
+    }
+
+    /// Make the transfer of a due standing order in a single database transaction.
+    ///
+    /// When the payer lacks the funds it is tried again after `retry_delay`, and suspended once it
+    /// failed `max_failures` times in a row. Runs missed while the server was down are skipped.
+    pub async fn execute(
+        &self,
+        db: &Surreal<Any>,
+        retry_delay: Duration,
+        max_failures: u32,
+    ) -> Result<ExecutionOutcome, surrealdb::Error> {
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $now = time::now();
+            LET $order = (SELECT * FROM standing_order WHERE id = $id)[0];
+            LET $balance = <decimal> ((SELECT VALUE balance FROM wallet WHERE address = $order.from)[0] OR 0);
+            LET $recipient = (SELECT VALUE address FROM wallet WHERE address = $order.to)[0];
+            LET $status = IF $order = NONE OR $order.status != 'active' OR ($order.retry_at OR $order.next_run) > $now {
+                'skipped'
+            } ELSE IF $recipient = NONE {
+                'recipient_missing'
+            } ELSE IF $balance < $order.amount {
+                'insufficient_funds'
+            } ELSE {
+                'executed'
+            };
+            LET $transaction = IF $status = 'executed' {
+                (CREATE transaction CONTENT {
+                    from: $order.from,
+                    to: $order.to,
+                    amount: $order.amount,
+                    metadata: $metadata,
+                    transaction_type: 'transfer',
+                })[0]
+            };
+            LET $order = IF $status = 'executed' {
+                LET $interval = duration::from::secs($order.interval);
+                LET $runs = $order.runs + 1;
+                LET $next = $order.next_run + $interval;
+                LET $next = IF $next < $now { $now + $interval } ELSE { $next };
+                LET $completed = ($order.max_runs != NONE AND $runs >= $order.max_runs)
+                    OR ($order.ends_at != NONE AND $next > $order.ends_at);
+                (UPDATE $id SET
+                    runs = $runs,
+                    failures = 0,
+                    next_run = $next,
+                    retry_at = NONE,
+                    last_transaction = $transaction.id,
+                    status = IF $completed { 'completed' } ELSE { 'active' },
+                    updated_at = $now)[0]
+            } ELSE IF $status = 'insufficient_funds' {
+                LET $failures = $order.failures + 1;
+                LET $suspended = $failures >= $max_failures;
+                (UPDATE $id SET
+                    failures = $failures,
+                    retry_at = IF $suspended { NONE } ELSE { $now + duration::from::secs($retry_delay) },
+                    status = IF $suspended { 'suspended' } ELSE { 'active' },
+                    updated_at = $now)[0]
+            } ELSE IF $status = 'recipient_missing' {
+                (UPDATE $id SET status = 'cancelled', retry_at = NONE, updated_at = $now)[0]
+            } ELSE {
+                $order
+            };
+            RETURN { status: $status, order: $order, transaction: $transaction };
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("id", self.id.clone()))
+            .bind(("metadata", self.execution_meta()))
+            .bind(("retry_delay", retry_delay.as_secs()))
+            .bind(("max_failures", max_failures))
+            .await?;
+        let response: Option<ExecutionResponse> = response.take(0)?;
+        let response = response
+            .ok_or_else(|| unexpected_response("executing a standing order gave no status"))?;
+
+        let order = response.order.map(Box::new);
+        let outcome = match (response.status, order) {
+            (ExecutionStatus::Executed, Some(order)) => ExecutionOutcome::Executed {
+                order,
+                transaction: response.transaction.map(Box::new).ok_or_else(|| {
+                    unexpected_response("executing a standing order did not create a transaction")
+                })?,
+            },
+            (ExecutionStatus::InsufficientFunds, Some(order)) => {
+                ExecutionOutcome::InsufficientFunds { order }
+            }
+            (ExecutionStatus::RecipientMissing, Some(order)) => {
+                ExecutionOutcome::RecipientMissing { order }
+            }
+            _ => ExecutionOutcome::Skipped,
+        };
+
+        Ok(outcome)
+    }
+
+    /// Stop executing the order until it is resumed. Returns `None` if it was not active.
+    pub async fn pause(&self, db: &Surreal<Any>) -> Result<Option<Model>, surrealdb::Error> {
+        let q =
+            "UPDATE $id SET status = 'paused', updated_at = time::now() WHERE status = 'active';";
+
+        let mut response = db.query(q).bind(("id", self.id.clone())).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Resume a paused or suspended order, runs that were missed in the meantime are skipped.
+    ///
+    /// Returns `None` if it was neither paused nor suspended.
+    pub async fn resume(&self, db: &Surreal<Any>) -> Result<Option<Model>, surrealdb::Error> {
+        let q = r#"
+            UPDATE $id SET
+                status = 'active',
+                failures = 0,
+                retry_at = NONE,
+                next_run = IF next_run < time::now() { time::now() } ELSE { next_run },
+                updated_at = time::now()
+            WHERE status IN ['paused', 'suspended'];
+        "#;
+
+        let mut response = db.query(q).bind(("id", self.id.clone())).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+
+    /// Stop the order for good. Returns `None` if it already ended.
+    pub async fn cancel(&self, db: &Surreal<Any>) -> Result<Option<Model>, surrealdb::Error> {
+        let q = r#"
+            UPDATE $id SET status = 'cancelled', retry_at = NONE, updated_at = time::now()
+            WHERE status IN ['active', 'paused', 'suspended'];
+        "#;
+
+        let mut response = db.query(q).bind(("id", self.id.clone())).await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::testing;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn tags_executions_with_the_order() {
+        let mut order = Model {
+            id: Some(Thing::from(("standing_order", "abc123"))),
+            from: "kaaaaaaaaa".to_owned(),
+            to: "kbbbbbbbbb".to_owned(),
+            amount: dec!(5),
+            metadata: None,
+            interval: 604800,
+            next_run: Datetime::default(),
+            retry_at: None,
+            ends_at: None,
+            max_runs: None,
+            runs: 0,
+            failures: 0,
+            status: StandingOrderStatus::Active,
+            last_transaction: None,
+            created_at: Datetime::default(),
+            updated_at: None,
+        };
+        assert_eq!(order.execution_meta(), "standing_order=abc123");
+
+        order.metadata = Some("rent@town.kst".to_owned());
+        assert_eq!(
+            order.execution_meta(),
+            "rent@town.kst;standing_order=abc123"
+        );
+    }
+
+    const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+    async fn create(db: &Surreal<Any>, amount: Decimal, max_runs: Option<u64>) -> Model {
+        let data = StandingOrderCreateData {
+            from: "kpayer0001".to_owned(),
+            to: "kpayee0001".to_owned(),
+            amount,
+            metadata: None,
+            interval: 3600,
+            next_run: Datetime::default(),
+            ends_at: None,
+            max_runs,
+        };
+
+        Model::create(db, data).await.unwrap().unwrap()
+    }
+
+    #[actix_web::test]
+    async fn executes_when_due() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kpayer0001", "hash", 100).await;
+        testing::insert_wallet(&db, "kpayee0001", "hash", 0).await;
+        let order = create(&db, dec!(30), Some(2)).await;
+
+        let outcome = order.execute(&db, RETRY_DELAY, 3).await.unwrap();
+        let ExecutionOutcome::Executed {
+            order: executed, ..
+        } = outcome
+        else {
+            panic!("expected an execution, got {outcome:?}");
+        };
+        assert_eq!(executed.runs, 1);
+        assert_eq!(executed.status, StandingOrderStatus::Active);
+        assert_eq!(testing::balance(&db, "kpayee0001").await, dec!(30));
+
+        // Missed runs are skipped, so the next one is no longer due.
+        assert_eq!(
+            order.execute(&db, RETRY_DELAY, 3).await.unwrap(),
+            ExecutionOutcome::Skipped
+        );
+        assert_eq!(testing::balance(&db, "kpayee0001").await, dec!(30));
+
+        let q = "UPDATE standing_order SET next_run = time::now();";
+        db.query(q).await.unwrap().check().unwrap();
+        let outcome = order.execute(&db, RETRY_DELAY, 3).await.unwrap();
+        let ExecutionOutcome::Executed {
+            order: executed, ..
+        } = outcome
+        else {
+            panic!("expected an execution, got {outcome:?}");
+        };
+        assert_eq!(executed.status, StandingOrderStatus::Completed);
+        assert_eq!(testing::balance(&db, "kpayer0001").await, dec!(40));
+        assert_eq!(testing::balance(&db, "kpayee0001").await, dec!(60));
+    }
+
+    #[actix_web::test]
+    async fn suspends_after_failing_repeatedly() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kpayer0001", "hash", 10).await;
+        testing::insert_wallet(&db, "kpayee0001", "hash", 0).await;
+        let order = create(&db, dec!(30), None).await;
+
+        let outcome = order.execute(&db, RETRY_DELAY, 2).await.unwrap();
+        let ExecutionOutcome::InsufficientFunds { order: failed } = outcome else {
+            panic!("expected a failure, got {outcome:?}");
+        };
+        assert_eq!(failed.failures, 1);
+        assert_eq!(failed.status, StandingOrderStatus::Active);
+        assert!(failed.retry_at.is_some());
+
+        // Not retried before the delay passed.
+        assert_eq!(
+            order.execute(&db, RETRY_DELAY, 2).await.unwrap(),
+            ExecutionOutcome::Skipped
+        );
+
+        let q = "UPDATE standing_order SET retry_at = time::now();";
+        db.query(q).await.unwrap().check().unwrap();
+        let outcome = order.execute(&db, RETRY_DELAY, 2).await.unwrap();
+        let ExecutionOutcome::InsufficientFunds { order: failed } = outcome else {
+            panic!("expected a failure, got {outcome:?}");
+        };
+        assert_eq!(failed.status, StandingOrderStatus::Suspended);
+        assert_eq!(testing::balance(&db, "kpayer0001").await, dec!(10));
+        assert_eq!(transaction::Model::count(&db).await.unwrap(), 0);
+    }
+
+    #[actix_web::test]
+    async fn cancels_when_the_recipient_is_gone() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kpayer0001", "hash", 100).await;
+        let order = create(&db, dec!(30), None).await;
+
+        let outcome = order.execute(&db, RETRY_DELAY, 3).await.unwrap();
+        let ExecutionOutcome::RecipientMissing { order: cancelled } = outcome else {
+            panic!("expected a cancellation, got {outcome:?}");
+        };
+        assert_eq!(cancelled.status, StandingOrderStatus::Cancelled);
+        assert_eq!(testing::balance(&db, "kpayer0001").await, dec!(100));
+    }
+}
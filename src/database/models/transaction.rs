@@ -8,7 +8,7 @@ use surrealdb::{
 
 use rust_decimal::Decimal;
 
-use super::{serialize_record_opt, CountResponse};
+use super::{serialize_record_opt, unexpected_response, CountResponse};
 use crate::{models::transactions::TransactionType, routes::PaginationParams};
 
 static KST_REGEX: Lazy<Regex> =
@@ -28,6 +28,15 @@ pub struct Model {
     pub timestamp: Datetime,
     pub to: String,
     pub transaction_type: TransactionType,
+    /// The transaction this one reverses or refunds, or the funding of the escrow it settles.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<Thing>,
+    /// Shared by the transactions of a batch transfer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<String>,
+    /// What the sender of a forced reversal could not cover, their balance went negative by it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debt: Option<Decimal>,
 }
 
 #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
@@ -39,6 +48,81 @@ pub struct TransactionCreateData {
     pub transaction_type: TransactionType,
 }
 
+/// Result of [`Model::reverse`] and [`Model::refund`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReturnOutcome {
+    /// `remaining` is the amount that can still be returned afterwards.
+    Returned {
+        transaction: Box<Model>,
+        remaining: Decimal,
+    },
+    /// Only `remaining` of the original amount has not been returned yet.
+    ExceedsRemaining { remaining: Decimal },
+    /// The recipient of the original transaction only holds `balance`.
+    InsufficientFunds { balance: Decimal },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ReturnStatus {
+    Returned,
+    ExceedsRemaining,
+    InsufficientFunds,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+struct ReturnResponse {
+    status: ReturnStatus,
+    remaining: Decimal,
+    balance: Decimal,
+    transaction: Option<Model>,
+}
+
+/// A single transfer of a batch, see [`Model::create_batch`].
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct BatchLeg {
+    pub to: String,
+    pub amount: Decimal,
+    pub metadata: Option<String>,
+}
+
+/// Result of [`Model::create_batch`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchOutcome {
+    /// One transaction per leg, in the order of the legs.
+    Transferred { transactions: Vec<Model> },
+    /// The sender only holds `balance`.
+    InsufficientFunds { balance: Decimal },
+    /// These recipients don't exist or are locked.
+    RecipientsMissing { addresses: Vec<String> },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum BatchStatus {
+    Transferred,
+    InsufficientFunds,
+    RecipientsMissing,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+struct BatchResponse {
+    status: BatchStatus,
+    balance: Decimal,
+    missing: Vec<String>,
+    transactions: Vec<Model>,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+struct BatchLegData {
+    from: String,
+    to: String,
+    amount: Decimal,
+    metadata: Option<String>,
+    transaction_type: TransactionType,
+    batch: String,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize)]
 pub struct TransactionNameData {
     pub meta: Option<String>,
@@ -131,6 +215,206 @@ impl Model {
 
         Ok(models)
     }
+
+    /// The CommonMeta entry pointing at this transaction, e.g. `ref=abc`.
+    pub fn reference_meta(&self) -> String {
+        let id = self
+            .id
+            .as_ref()
+            .map(|id| id.id.to_raw())
+            .unwrap_or_default();
+
+        format!("ref={id}")
+    }
+
+    /// Send every leg of a batch from `from` in a single database transaction, either all of them
+    /// are made or none are. The transactions share the `batch` ID.
+    pub async fn create_batch(
+        db: &Surreal<Any>,
+        from: &str,
+        batch: &str,
+        legs: &[BatchLeg],
+    ) -> Result<BatchOutcome, surrealdb::Error> {
+        let rows: Vec<BatchLegData> = legs
+            .iter()
+            .map(|leg| BatchLegData {
+                from: from.to_owned(),
+                to: leg.to.clone(),
+                amount: leg.amount,
+                metadata: leg.metadata.clone(),
+                transaction_type: TransactionType::Transfer,
+                batch: batch.to_owned(),
+            })
+            .collect();
+        let total: Decimal = legs.iter().map(|leg| leg.amount).sum();
+
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $balance = <decimal> ((SELECT VALUE balance FROM wallet WHERE address = $from)[0] OR 0);
+            LET $recipients = array::distinct($rows.to);
+            LET $missing = array::complement($recipients, (SELECT VALUE address FROM wallet WHERE address IN $recipients AND locked = false));
+            LET $status = IF array::len($missing) > 0 {
+                'recipients_missing'
+            } ELSE IF $balance < $total {
+                'insufficient_funds'
+            } ELSE {
+                'transferred'
+            };
+            LET $transactions = IF $status = 'transferred' { (INSERT INTO transaction $rows) } ELSE { [] };
+            RETURN { status: $status, balance: $balance, missing: $missing, transactions: $transactions };
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("from", from.to_owned()))
+            .bind(("rows", rows))
+            .bind(("total", total))
+            .await?;
+        let response: Option<BatchResponse> = response.take(0)?;
+        let response =
+            response.ok_or_else(|| unexpected_response("a batch transfer gave no status"))?;
+
+        let outcome = match response.status {
+            BatchStatus::Transferred => BatchOutcome::Transferred {
+                transactions: response.transactions,
+            },
+            BatchStatus::InsufficientFunds => BatchOutcome::InsufficientFunds {
+                balance: response.balance,
+            },
+            BatchStatus::RecipientsMissing => BatchOutcome::RecipientsMissing {
+                addresses: response.missing,
+            },
+        };
+
+        Ok(outcome)
+    }
+
+    /// Move `amount` of a transaction back from its recipient to its sender, recorded as a `reversal`
+    /// transaction referencing it. Without an amount, everything not returned yet is moved back.
+    ///
+    /// With `force` the recipient's balance may go negative, leaving them in debt. The part of the
+    /// amount they could not cover is recorded as the `debt` of the reversal.
+    pub async fn reverse(
+        &self,
+        db: &Surreal<Any>,
+        amount: Option<Decimal>,
+        force: bool,
+    ) -> Result<ReturnOutcome, surrealdb::Error> {
+        let metadata = self.reference_meta();
+
+        self.return_funds(
+            db,
+            &self.from,
+            amount,
+            metadata,
+            TransactionType::Reversal,
+            force,
+        )
+        .await
+    }
+
+    /// Send `amount` of a transaction from its recipient to `to`, usually its sender, recorded as a
+    /// transfer referencing it. Without an amount, everything not returned yet is sent.
+    pub async fn refund(
+        &self,
+        db: &Surreal<Any>,
+        to: &str,
+        amount: Option<Decimal>,
+        metadata: String,
+    ) -> Result<ReturnOutcome, surrealdb::Error> {
+        self.return_funds(db, to, amount, metadata, TransactionType::Transfer, false)
+            .await
+    }
+
+    /// Send funds of this transaction from its recipient to `to` in a single database transaction.
+    ///
+    /// The reversals and refunds of a transaction never add up to more than its amount, once it was
+    /// returned in full every further return exceeds the remaining amount.
+    async fn return_funds(
+        &self,
+        db: &Surreal<Any>,
+        to: &str,
+        amount: Option<Decimal>,
+        metadata: String,
+        transaction_type: TransactionType,
+        force: bool,
+    ) -> Result<ReturnOutcome, surrealdb::Error> {
+        let id = self
+            .id
+            .clone()
+            .ok_or_else(|| unexpected_response("returned transaction has no ID"))?;
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $original = (SELECT * FROM transaction WHERE id = $id)[0];
+            IF $original = NONE { THROW "Transaction not found" };
+            LET $returned = <decimal> math::sum((SELECT VALUE amount FROM transaction WHERE reference = $id));
+            LET $remaining = $original.amount - $returned;
+            LET $amount = IF $amount = NONE { $remaining } ELSE { $amount };
+            LET $balance = <decimal> ((SELECT VALUE balance FROM wallet WHERE address = $original.to)[0] OR 0);
+            LET $status = IF $amount <= 0 OR $amount > $remaining {
+                'exceeds_remaining'
+            } ELSE IF !$force AND $balance < $amount {
+                'insufficient_funds'
+            } ELSE {
+                'returned'
+            };
+            LET $debt = IF $balance >= $amount {
+                NONE
+            } ELSE IF $balance > 0 {
+                $amount - $balance
+            } ELSE {
+                $amount
+            };
+            LET $transaction = IF $status = 'returned' {
+                (CREATE transaction CONTENT {
+                    from: $original.to,
+                    to: $to,
+                    amount: $amount,
+                    metadata: $metadata,
+                    transaction_type: $transaction_type,
+                    reference: $id,
+                    debt: $debt,
+                })[0]
+            };
+            RETURN { status: $status, remaining: $remaining, balance: $balance, transaction: $transaction };
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("id", id))
+            .bind(("to", to.to_owned()))
+            .bind(("amount", amount))
+            .bind(("force", force))
+            .bind(("metadata", metadata))
+            .bind(("transaction_type", transaction_type))
+            .await?;
+        let response: Option<ReturnResponse> = response.take(0)?;
+        let response =
+            response.ok_or_else(|| unexpected_response("returning funds gave no status"))?;
+
+        let outcome = match response.status {
+            ReturnStatus::Returned => {
+                let transaction = response.transaction.map(Box::new).ok_or_else(|| {
+                    unexpected_response("returning funds did not create a transaction")
+                })?;
+
+                ReturnOutcome::Returned {
+                    remaining: response.remaining - transaction.amount,
+                    transaction,
+                }
+            }
+            ReturnStatus::ExceedsRemaining => ReturnOutcome::ExceedsRemaining {
+                remaining: response.remaining,
+            },
+            ReturnStatus::InsufficientFunds => ReturnOutcome::InsufficientFunds {
+                balance: response.balance,
+            },
+        };
+
+        Ok(outcome)
+    }
 }
 
 impl TransactionNameData {
@@ -142,6 +426,7 @@ impl TransactionNameData {
     ///
     /// # Examples
     /// ```
+    /// # use kromer::database::models::transaction::TransactionNameData;
     /// let data = TransactionNameData::parse("meta@name.kst");
     /// assert_eq!(data.meta, Some("meta".to_string()));
     /// assert_eq!(data.name, Some("name".to_string()));
@@ -172,6 +457,7 @@ impl TransactionNameData {
     ///
     /// # Examples
     /// ```
+    /// # use kromer::database::models::transaction::TransactionNameData;
     /// let data = TransactionNameData::parse_opt(Some("meta@name.kst"));
     /// assert_eq!(data.meta, Some("meta".to_string()));
     /// assert_eq!(data.name, Some("name".to_string()));
@@ -194,6 +480,7 @@ impl TransactionNameData {
     ///
     /// # Examples
     /// ```
+    /// # use kromer::database::models::transaction::TransactionNameData;
     /// let input = Some("meta@name.kst".to_string());
     /// let data = TransactionNameData::parse_opt_ref(&input);
     /// assert_eq!(data.meta, Some("meta".to_string()));
@@ -211,3 +498,298 @@ impl TransactionNameData {
         Self::parse(input)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::database::testing;
+
+    #[actix_web::test]
+    async fn refuses_empty_transactions() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kaaaaaaaaa", "hash", 10).await;
+        testing::insert_wallet(&db, "kbbbbbbbbb", "hash", 0).await;
+
+        assert!(testing::transfer(&db, "kaaaaaaaaa", "kbbbbbbbbb", 0)
+            .await
+            .is_err());
+        assert!(testing::transfer(&db, "kaaaaaaaaa", "kbbbbbbbbb", -5)
+            .await
+            .is_err());
+        assert_eq!(testing::balance(&db, "kaaaaaaaaa").await, dec!(10));
+        assert_eq!(Model::count(&db).await.unwrap(), 0);
+    }
+
+    #[actix_web::test]
+    async fn refuses_transactions_without_wallets() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kaaaaaaaaa", "hash", 10).await;
+
+        assert!(testing::transfer(&db, "kaaaaaaaaa", "kmissing01", 3)
+            .await
+            .is_err());
+        assert!(testing::transfer(&db, "kmissing01", "kaaaaaaaaa", 3)
+            .await
+            .is_err());
+        assert_eq!(testing::balance(&db, "kaaaaaaaaa").await, dec!(10));
+        assert_eq!(Model::count(&db).await.unwrap(), 0);
+
+        // Name purchases are burned.
+        testing::transfer(&db, "kaaaaaaaaa", "name", 3)
+            .await
+            .unwrap();
+        assert_eq!(testing::balance(&db, "kaaaaaaaaa").await, dec!(7));
+    }
+
+    #[actix_web::test]
+    async fn refunds_no_more_than_the_amount() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kaaaaaaaaa", "hash", 100).await;
+        testing::insert_wallet(&db, "kbbbbbbbbb", "hash", 0).await;
+        let transaction = testing::transfer(&db, "kaaaaaaaaa", "kbbbbbbbbb", 50)
+            .await
+            .unwrap();
+        let refund = |amount| transaction.refund(&db, "kaaaaaaaaa", amount, String::new());
+
+        let outcome = refund(Some(dec!(20))).await.unwrap();
+        let ReturnOutcome::Returned { remaining, .. } = outcome else {
+            panic!("expected a refund, got {outcome:?}");
+        };
+        assert_eq!(remaining, dec!(30));
+
+        let outcome = refund(Some(dec!(40))).await.unwrap();
+        assert_eq!(
+            outcome,
+            ReturnOutcome::ExceedsRemaining {
+                remaining: dec!(30)
+            }
+        );
+
+        // Without an amount the rest is refunded.
+        let outcome = refund(None).await.unwrap();
+        let ReturnOutcome::Returned {
+            transaction: returned,
+            remaining,
+        } = outcome
+        else {
+            panic!("expected a refund, got {outcome:?}");
+        };
+        assert_eq!(returned.amount, dec!(30));
+        assert_eq!(returned.reference, transaction.id);
+        assert_eq!(remaining, dec!(0));
+
+        // Nothing is left to refund after a full refund.
+        let outcome = refund(None).await.unwrap();
+        assert_eq!(
+            outcome,
+            ReturnOutcome::ExceedsRemaining { remaining: dec!(0) }
+        );
+        let outcome = refund(Some(dec!(1))).await.unwrap();
+        assert_eq!(
+            outcome,
+            ReturnOutcome::ExceedsRemaining { remaining: dec!(0) }
+        );
+
+        assert_eq!(testing::balance(&db, "kaaaaaaaaa").await, dec!(100));
+        assert_eq!(testing::balance(&db, "kbbbbbbbbb").await, dec!(0));
+        assert_eq!(Model::count(&db).await.unwrap(), 3);
+    }
+
+    #[actix_web::test]
+    async fn reverses_in_parts() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kaaaaaaaaa", "hash", 100).await;
+        testing::insert_wallet(&db, "kbbbbbbbbb", "hash", 0).await;
+        let transaction = testing::transfer(&db, "kaaaaaaaaa", "kbbbbbbbbb", 50)
+            .await
+            .unwrap();
+
+        let outcome = transaction
+            .reverse(&db, Some(dec!(20)), false)
+            .await
+            .unwrap();
+        let ReturnOutcome::Returned {
+            transaction: reversal,
+            remaining,
+        } = outcome
+        else {
+            panic!("expected a reversal, got {outcome:?}");
+        };
+        assert_eq!(reversal.transaction_type, TransactionType::Reversal);
+        assert_eq!(reversal.from, "kbbbbbbbbb");
+        assert_eq!(reversal.to, "kaaaaaaaaa");
+        assert_eq!(reversal.debt, None);
+        assert_eq!(remaining, dec!(30));
+
+        // A zero amount doesn't stand for everything that is left.
+        let outcome = transaction
+            .reverse(&db, Some(dec!(0)), false)
+            .await
+            .unwrap();
+        assert_eq!(
+            outcome,
+            ReturnOutcome::ExceedsRemaining {
+                remaining: dec!(30)
+            }
+        );
+
+        let outcome = transaction.reverse(&db, None, false).await.unwrap();
+        let ReturnOutcome::Returned { remaining, .. } = outcome else {
+            panic!("expected a reversal, got {outcome:?}");
+        };
+        assert_eq!(remaining, dec!(0));
+
+        // Repeating a reversal once everything was moved back does nothing.
+        let outcome = transaction.reverse(&db, None, false).await.unwrap();
+        assert_eq!(
+            outcome,
+            ReturnOutcome::ExceedsRemaining { remaining: dec!(0) }
+        );
+        let outcome = transaction.reverse(&db, None, true).await.unwrap();
+        assert_eq!(
+            outcome,
+            ReturnOutcome::ExceedsRemaining { remaining: dec!(0) }
+        );
+
+        assert_eq!(testing::balance(&db, "kaaaaaaaaa").await, dec!(100));
+        assert_eq!(testing::balance(&db, "kbbbbbbbbb").await, dec!(0));
+        assert_eq!(Model::count(&db).await.unwrap(), 3);
+    }
+
+    #[actix_web::test]
+    async fn records_the_debt_of_forced_reversals() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kaaaaaaaaa", "hash", 100).await;
+        testing::insert_wallet(&db, "kbbbbbbbbb", "hash", 0).await;
+        testing::insert_wallet(&db, "kccccccccc", "hash", 0).await;
+        let transaction = testing::transfer(&db, "kaaaaaaaaa", "kbbbbbbbbb", 50)
+            .await
+            .unwrap();
+        testing::transfer(&db, "kbbbbbbbbb", "kccccccccc", 40)
+            .await
+            .unwrap();
+
+        let outcome = transaction.reverse(&db, None, false).await.unwrap();
+        assert_eq!(
+            outcome,
+            ReturnOutcome::InsufficientFunds { balance: dec!(10) }
+        );
+
+        let outcome = transaction
+            .reverse(&db, Some(dec!(30)), true)
+            .await
+            .unwrap();
+        let ReturnOutcome::Returned {
+            transaction: reversal,
+            remaining,
+        } = outcome
+        else {
+            panic!("expected a reversal, got {outcome:?}");
+        };
+        assert_eq!(reversal.debt, Some(dec!(20)));
+        assert_eq!(remaining, dec!(20));
+        assert_eq!(testing::balance(&db, "kbbbbbbbbb").await, dec!(-20));
+
+        // Once in debt, all of a further forced reversal is owed.
+        let outcome = transaction.reverse(&db, None, true).await.unwrap();
+        let ReturnOutcome::Returned {
+            transaction: reversal,
+            ..
+        } = outcome
+        else {
+            panic!("expected a reversal, got {outcome:?}");
+        };
+        assert_eq!(reversal.debt, Some(dec!(20)));
+        assert_eq!(testing::balance(&db, "kbbbbbbbbb").await, dec!(-40));
+        assert_eq!(testing::balance(&db, "kaaaaaaaaa").await, dec!(100));
+    }
+
+    fn leg(to: &str, amount: Decimal) -> BatchLeg {
+        BatchLeg {
+            to: to.to_owned(),
+            amount,
+            metadata: None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn sends_batches_in_full_or_not_at_all() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kaaaaaaaaa", "hash", 100).await;
+        testing::insert_wallet(&db, "kbbbbbbbbb", "hash", 0).await;
+        testing::insert_wallet(&db, "kccccccccc", "hash", 0).await;
+        testing::insert_wallet(&db, "klocked001", "hash", 0).await;
+        let q = "UPDATE wallet SET locked = true WHERE address = 'klocked001';";
+        db.query(q).await.unwrap().check().unwrap();
+
+        let legs = [leg("kbbbbbbbbb", dec!(60)), leg("kccccccccc", dec!(50))];
+        let outcome = Model::create_batch(&db, "kaaaaaaaaa", "batch1", &legs).await;
+        assert_eq!(
+            outcome.unwrap(),
+            BatchOutcome::InsufficientFunds { balance: dec!(100) }
+        );
+
+        let legs = [
+            leg("kbbbbbbbbb", dec!(10)),
+            leg("kmissing01", dec!(10)),
+            leg("klocked001", dec!(10)),
+        ];
+        let outcome = Model::create_batch(&db, "kaaaaaaaaa", "batch2", &legs).await;
+        let BatchOutcome::RecipientsMissing { mut addresses } = outcome.unwrap() else {
+            panic!("expected missing recipients");
+        };
+        addresses.sort();
+        assert_eq!(addresses, vec!["klocked001", "kmissing01"]);
+        assert_eq!(testing::balance(&db, "kaaaaaaaaa").await, dec!(100));
+        assert_eq!(Model::count(&db).await.unwrap(), 0);
+
+        let legs = [
+            leg("kbbbbbbbbb", dec!(60)),
+            leg("kccccccccc", dec!(15)),
+            leg("kbbbbbbbbb", dec!(25)),
+        ];
+        let outcome = Model::create_batch(&db, "kaaaaaaaaa", "batch3", &legs).await;
+        let BatchOutcome::Transferred { transactions } = outcome.unwrap() else {
+            panic!("expected a transfer");
+        };
+        assert_eq!(transactions.len(), 3);
+        assert!(transactions
+            .iter()
+            .all(|transaction| transaction.batch.as_deref() == Some("batch3")));
+        assert_eq!(testing::balance(&db, "kaaaaaaaaa").await, dec!(0));
+        assert_eq!(testing::balance(&db, "kbbbbbbbbb").await, dec!(85));
+        assert_eq!(testing::balance(&db, "kccccccccc").await, dec!(15));
+
+        // The balance was spent by the first batch, a second one is refused.
+        let legs = [leg("kbbbbbbbbb", dec!(1))];
+        let outcome = Model::create_batch(&db, "kaaaaaaaaa", "batch4", &legs).await;
+        assert_eq!(
+            outcome.unwrap(),
+            BatchOutcome::InsufficientFunds { balance: dec!(0) }
+        );
+    }
+
+    #[actix_web::test]
+    async fn refuses_double_spends() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kaaaaaaaaa", "hash", 100).await;
+        testing::insert_wallet(&db, "kbbbbbbbbb", "hash", 0).await;
+        testing::insert_wallet(&db, "kccccccccc", "hash", 0).await;
+
+        let legs = [leg("kbbbbbbbbb", dec!(70))];
+        let outcome = Model::create_batch(&db, "kaaaaaaaaa", "batch1", &legs).await;
+        assert!(matches!(outcome, Ok(BatchOutcome::Transferred { .. })));
+
+        let legs = [leg("kccccccccc", dec!(70))];
+        let outcome = Model::create_batch(&db, "kaaaaaaaaa", "batch2", &legs).await;
+        assert_eq!(
+            outcome.unwrap(),
+            BatchOutcome::InsufficientFunds { balance: dec!(30) }
+        );
+        assert_eq!(testing::balance(&db, "kaaaaaaaaa").await, dec!(30));
+        assert_eq!(testing::balance(&db, "kccccccccc").await, dec!(0));
+        assert_eq!(Model::count(&db).await.unwrap(), 1);
+    }
+}
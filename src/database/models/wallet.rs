@@ -1,3 +1,6 @@
+use std::env;
+
+use once_cell::sync::Lazy;
 use rust_decimal_macros::dec;
 use surrealdb::{
     engine::any::Any,
@@ -8,8 +11,13 @@ use surrealdb::{
 use rust_decimal::Decimal;
 
 use super::{name, transaction};
-use super::{serialize_record_opt, CountResponse};
-use crate::{models::transactions::AddressTransactionQuery, routes::PaginationParams, utils};
+use super::{serialize_record_opt, unexpected_response, CountResponse};
+use crate::{
+    models::transactions::AddressTransactionQuery,
+    routes::PaginationParams,
+    utils::{self, crypto::WalletHashVersion},
+    websockets::types::common::SessionCredential,
+};
 
 #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct Model {
@@ -20,6 +28,15 @@ pub struct Model {
     pub id: Option<Thing>,
     pub address: String,
     pub balance: Decimal,
+    /// Funds reserved by holds, they are not part of `balance` until the hold is released.
+    #[serde(default)]
+    pub held: Decimal,
+    /// Funds of scheduled transfers to the wallet, it only receives them once they execute.
+    #[serde(default)]
+    pub pending_in: Decimal,
+    /// Funds reserved by scheduled transfers from the wallet, they are not part of `balance`.
+    #[serde(default)]
+    pub pending_out: Decimal,
     pub created_at: Datetime,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hash: Option<String>, // We dont want to retrieve the hash all the time.
@@ -27,6 +44,62 @@ pub struct Model {
     pub total_in: Decimal,
     pub total_out: Decimal,
     pub locked: bool,
+    /// Hex encoded ed25519 public key, set when the wallet opted into signed requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// Last time the owner authenticated, `None` if they never did since this was tracked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<Datetime>,
+}
+
+/// Whether v1 wallets without a password fingerprint can still log in, see [`Model::verify`].
+///
+/// Once every legacy wallet has a fingerprint this can be turned off with `V1_LEGACY_PASSWORD_SCAN=false`.
+static LEGACY_PASSWORD_SCAN: Lazy<bool> = Lazy::new(|| {
+    env::var("V1_LEGACY_PASSWORD_SCAN")
+        .map(|value| value != "false")
+        .unwrap_or(true)
+});
+
+/// Whether an unknown private key gets a new, empty wallet when it is used, see [`Model::verify_address`].
+///
+/// Disable with `WALLET_AUTO_CREATE=false`, wallets are then only created through the internal API.
+static AUTO_CREATE: Lazy<bool> = Lazy::new(|| utils::env::env_or("WALLET_AUTO_CREATE", true));
+
+/// Wallets matching this condition never held any Krist, and can be purged once they are old enough.
+///
+/// `$cutoff` is the datetime before which the wallet must have been created and last seen. The `name`
+/// wallet receiving name purchases is never purged, even before the first name was bought, and neither
+/// are wallets that open escrows, holds, standing orders or scheduled transfers still pay out to.
+const DORMANT_WALLET_CONDITION: &str =
+    "address != 'name' AND balance = 0 AND total_in = 0 AND total_out = 0 AND pending_in = 0 \
+    AND is_shared = false AND locked = false AND public_key = NONE \
+    AND created_at < $cutoff AND (last_seen = NONE OR last_seen < $cutoff) \
+    AND count(<-owns) = 0 AND count((SELECT id FROM name WHERE owner = $parent.address)) = 0 \
+    AND count((SELECT id FROM escrow WHERE status = 'funded' \
+        AND $parent.address IN [buyer, seller, arbiter])) = 0 \
+    AND count((SELECT id FROM hold WHERE status = 'authorized' \
+        AND $parent.address IN [payer, merchant])) = 0 \
+    AND count((SELECT id FROM standing_order WHERE status NOT IN ['cancelled', 'completed'] \
+        AND $parent.address IN [from, to])) = 0 \
+    AND count((SELECT id FROM scheduled_transfer WHERE status = 'pending' \
+        AND $parent.address IN [from, to])) = 0";
+
+async fn argon2_compare(
+    db: &Surreal<Any>,
+    hash: String,
+    secret: String,
+) -> Result<bool, surrealdb::Error> {
+    let q = "RETURN crypto::argon2::compare($hash, $secret);";
+
+    let mut response = db
+        .query(q)
+        .bind(("hash", hash))
+        .bind(("secret", secret))
+        .await?;
+    let valid: Option<bool> = response.take(0)?;
+
+    Ok(valid.unwrap_or(false))
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -35,6 +108,21 @@ pub struct VerifyResponse {
     pub address: Model,
 }
 
+impl VerifyResponse {
+    /// The wallet, if the key it was verified with is correct.
+    pub fn into_authed(self) -> Option<Model> {
+        self.authed.then_some(self.address)
+    }
+}
+
+/// The new wallet of a recovered one, see [`Model::recover_to_key`].
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct Recovery {
+    pub wallet: Model,
+    /// The `recovery` transaction moving the balance, `None` if there was no balance.
+    pub transaction: Option<transaction::Model>,
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct LookupResponse {
     #[serde(flatten)]
@@ -123,6 +211,19 @@ impl Model {
         Ok(model)
     }
 
+    /// Create a new wallet owned by a private key.
+    pub async fn create_with_key(
+        db: &Surreal<Any>,
+        private_key: &str,
+        initial_bal: Decimal,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let address = utils::crypto::make_v2_address(private_key, "k");
+        let digest = utils::crypto::make_wallet_digest(&address, private_key);
+        let hash = Model::hash_digest(db, &digest).await?;
+
+        Model::create(db, address, hash, Some(initial_bal)).await
+    }
+
     /// Get all wallets, omitting hash and id.
     pub async fn all(
         db: &Surreal<Any>,
@@ -145,15 +246,77 @@ impl Model {
     }
 
     /// Verify the password of a wallet, returning the given wallet if it exists.
+    ///
+    /// The password is either a Krist private key, which derives the address, or a v1 password found
+    /// through its keyed fingerprint. Either way exactly one argon2 check is done. Legacy v1 wallets
+    /// without a fingerprint are found with a scan, and get their fingerprint once they log in.
     pub async fn verify(
         db: &Surreal<Any>,
         password: String,
     ) -> Result<Option<Model>, surrealdb::Error> {
-        let q = "SELECT * FROM wallet WHERE crypto::argon2::compare(hash, $password);";
+        let fingerprint = utils::crypto::password_fingerprint(&password);
+
+        Model::verify_password(db, password, fingerprint, *LEGACY_PASSWORD_SCAN).await
+    }
+
+    /// [`Model::verify`] with the fingerprint of the password and whether legacy wallets are scanned passed in.
+    async fn verify_password(
+        db: &Surreal<Any>,
+        password: String,
+        fingerprint: Option<String>,
+        legacy_scan: bool,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let address = utils::crypto::make_v2_address(&password, "k");
+        if let Some(wallet) = Model::get_by_address(db, address.clone()).await? {
+            let digest = utils::crypto::make_wallet_digest(&address, &password);
+            if !wallet.check_digest(db, &digest).await? {
+                return Ok(None);
+            }
+            wallet.touch(db).await?;
+
+            return Ok(Some(wallet));
+        }
+
+        if let Some(fingerprint) = &fingerprint {
+            let q = "SELECT * FROM wallet WHERE password_fingerprint = $fingerprint LIMIT 1;";
+
+            let mut response = db
+                .query(q)
+                .bind(("fingerprint", fingerprint.clone()))
+                .await?;
+            let model: Option<Model> = response.take(0)?;
+
+            if let Some(wallet) = model {
+                let hash = wallet.hash.clone().unwrap_or_default();
+                if wallet.locked || !argon2_compare(db, hash, password).await? {
+                    return Ok(None);
+                }
+                wallet.touch(db).await?;
+
+                return Ok(Some(wallet));
+            }
+        }
+
+        if !legacy_scan {
+            return Ok(None);
+        }
+
+        let q = "SELECT * FROM wallet WHERE legacy_password = true AND locked = false AND crypto::argon2::compare(hash, $password) LIMIT 1;";
 
         let mut response = db.query(q).bind(("password", password)).await?;
         let model: Option<Model> = response.take(0)?;
 
+        if let (Some(wallet), Some(fingerprint)) = (&model, fingerprint) {
+            let q = "UPDATE $id SET password_fingerprint = $fingerprint, legacy_password = false, last_seen = time::now();";
+
+            db.query(q)
+                .bind(("id", wallet.id.clone()))
+                .bind(("fingerprint", fingerprint))
+                .await?
+                .check()?;
+            tracing::debug!("Migrated legacy v1 wallet {}", wallet.address);
+        }
+
         Ok(model)
     }
 
@@ -191,9 +354,9 @@ impl Model {
         Ok(count.count)
     }
 
-    /// Get the total amount of kromer held by wallets
+    /// Get the total amount of kromer held by wallets, including the funds they put in escrow or on hold
     pub async fn supply(db: &Surreal<Any>) -> Result<Decimal, surrealdb::Error> {
-        let q = "RETURN math::sum((SELECT balance FROM wallet).balance);";
+        let q = "RETURN math::sum((SELECT balance FROM wallet).balance) + math::sum((SELECT held FROM wallet).held) + math::sum((SELECT pending_out FROM wallet).pending_out) + math::sum((SELECT VALUE amount FROM escrow WHERE status = 'funded'));";
 
         let mut response = db.query(q).await?;
         let supply: Option<Decimal> = response.take(0)?;
@@ -202,45 +365,327 @@ impl Model {
         Ok(supply)
     }
 
-    #[tracing::instrument(skip(db))]
-    pub async fn verify_address<S: AsRef<str> + std::fmt::Debug>(
+    /// Verify a private key against the wallet of its address.
+    ///
+    /// Unknown keys get a new, empty wallet unless auto-creation is disabled, in which case `None` is returned.
+    #[tracing::instrument(skip_all)]
+    pub async fn verify_address<S: AsRef<str>>(
         db: &Surreal<Any>,
         private_key: S,
-    ) -> Result<VerifyResponse, surrealdb::Error> {
-        let private_key = private_key.as_ref();
+    ) -> Result<Option<VerifyResponse>, surrealdb::Error> {
+        Model::verify_key(db, private_key.as_ref(), *AUTO_CREATE).await
+    }
 
+    /// [`Model::verify_address`] with whether unknown keys get a wallet passed in.
+    async fn verify_key(
+        db: &Surreal<Any>,
+        private_key: &str,
+        auto_create: bool,
+    ) -> Result<Option<VerifyResponse>, surrealdb::Error> {
         let address = utils::crypto::make_v2_address(private_key, "k");
-        let guh = format!("{address}{private_key}"); // SO CURSED I LOVE IT
 
         tracing::info!("Authentication attempt on address {address}");
 
         // TODO: Fix the fucking api definition so it doesnt require an owned copy.
-        let result = Model::get_by_address(db, address.clone()).await?;
-        let hash = utils::crypto::sha256(&guh);
+        let Some(wallet) = Model::get_by_address(db, address.clone()).await? else {
+            if !auto_create {
+                tracing::info!("Not creating a wallet for unknown address {address}");
+                return Ok(None);
+            }
 
-        if result.is_none() {
-            let model = Model::create(db, address, hash, Some(dec!(0))).await?;
+            let model = Model::create_with_key(db, private_key, dec!(0)).await?;
             let model = model.expect("for some fucking reason, model is none."); // TODO: Figure out if it actually errors or not.
+            model.touch(db).await?;
             tracing::debug!("Created a new wallet with an initial balance of 0");
 
-            return Ok(VerifyResponse {
+            return Ok(Some(VerifyResponse {
                 authed: true,
                 address: model,
-            });
-        }
+            }));
+        };
 
-        let wallet = result.unwrap(); // It's fine to unwrap here, see above if statement, we checked if it exists or not.
-        let pkey = &wallet.hash;
-        let authed = *pkey == Some(hash); // Cursed i know, it makes the borrow checker happy.
+        let digest = utils::crypto::make_wallet_digest(&address, private_key);
+        let authed = wallet.check_digest(db, &digest).await?;
 
-        if !authed {
+        if authed {
+            wallet.touch(db).await?;
+        } else {
             tracing::info!("Someone tried to login to an address they do not own");
         }
 
-        return Ok(VerifyResponse {
+        Ok(Some(VerifyResponse {
             authed,
             address: wallet,
-        });
+        }))
+    }
+
+    /// Authenticate a legacy v1 address, these are never created anymore.
+    pub async fn verify_v1_address(
+        db: &Surreal<Any>,
+        private_key: &str,
+        address: &str,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        if utils::crypto::make_v1_address(private_key) != address {
+            return Ok(None);
+        }
+
+        let Some(wallet) = Model::get_by_address(db, address.to_owned()).await? else {
+            return Ok(None);
+        };
+        let digest = utils::crypto::make_wallet_digest(address, private_key);
+        if !wallet.check_digest(db, &digest).await? {
+            return Ok(None);
+        }
+        wallet.touch(db).await?;
+
+        Ok(Some(wallet))
+    }
+
+    /// Re-verify a session credential against the credentials currently stored for an address.
+    ///
+    /// This fails once the wallet's credentials have changed, even if the session was valid when it was created.
+    pub async fn verify_credential<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        address: S,
+        credential: &SessionCredential,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let Some(wallet) = Model::get_by_address(db, address.as_ref().to_owned()).await? else {
+            return Ok(None);
+        };
+        if wallet.locked {
+            return Ok(None);
+        }
+
+        let valid = match credential {
+            SessionCredential::Digest(digest) => wallet.check_digest(db, digest).await?,
+            SessionCredential::PublicKey(public_key) => {
+                wallet.public_key.as_deref() == Some(public_key.as_str())
+            }
+        };
+
+        Ok(valid.then_some(wallet))
+    }
+
+    /// Hash a wallet digest (see [`utils::crypto::make_wallet_digest`]) for storage.
+    pub async fn hash_digest(db: &Surreal<Any>, digest: &str) -> Result<String, surrealdb::Error> {
+        let q = "RETURN crypto::argon2::generate($digest);";
+
+        let mut response = db.query(q).bind(("digest", digest.to_owned())).await?;
+        let hash: Option<String> = response.take(0)?;
+
+        Ok(hash.expect("argon2 should always return a hash"))
+    }
+
+    /// Check a wallet digest against the stored hash, this always fails for locked wallets.
+    ///
+    /// Wallets still on a legacy hash are upgraded to argon2 once they successfully authenticate.
+    pub async fn check_digest(
+        &self,
+        db: &Surreal<Any>,
+        digest: &str,
+    ) -> Result<bool, surrealdb::Error> {
+        let Some(hash) = self.hash.as_deref().filter(|_| !self.locked) else {
+            return Ok(false);
+        };
+
+        match WalletHashVersion::of(hash) {
+            WalletHashVersion::Argon2 => {
+                argon2_compare(db, hash.to_owned(), digest.to_owned()).await
+            }
+            WalletHashVersion::LegacySha256 => {
+                if hash != digest {
+                    return Ok(false);
+                }
+
+                // Only replace the hash we checked against, a concurrent login may have upgraded it already.
+                let q = "UPDATE wallet SET hash = crypto::argon2::generate($digest) WHERE address = $address AND hash = $hash;";
+                db.query(q)
+                    .bind(("address", self.address.clone()))
+                    .bind(("hash", hash.to_owned()))
+                    .bind(("digest", digest.to_owned()))
+                    .await?
+                    .check()?;
+                tracing::debug!("Upgraded legacy wallet hash of {}", self.address);
+
+                Ok(true)
+            }
+        }
+    }
+
+    /// Record that the owner of the wallet was just seen.
+    pub async fn touch(&self, db: &Surreal<Any>) -> Result<(), surrealdb::Error> {
+        let q = "UPDATE wallet SET last_seen = time::now() WHERE address = $address;";
+
+        db.query(q)
+            .bind(("address", self.address.clone()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    /// Delete wallets that never held any Krist and were not seen for the given amount of days,
+    /// returning their addresses. With `dry_run` they are only listed.
+    ///
+    /// API tokens and nonces of purged wallets are removed along with them.
+    pub async fn purge_dormant(
+        db: &Surreal<Any>,
+        days: u64,
+        dry_run: bool,
+    ) -> Result<Vec<String>, surrealdb::Error> {
+        let (q, index) = if dry_run {
+            let q = format!(
+                "LET $cutoff = time::now() - duration::from::days($days);
+                RETURN (SELECT VALUE address FROM wallet WHERE {DORMANT_WALLET_CONDITION});"
+            );
+            (q, 1)
+        } else {
+            let q = format!(
+                "LET $cutoff = time::now() - duration::from::days($days);
+                LET $purged = (DELETE wallet WHERE {DORMANT_WALLET_CONDITION} RETURN BEFORE).address;
+                DELETE api_token WHERE address IN $purged;
+                DELETE auth_nonce WHERE address IN $purged;
+                RETURN $purged;"
+            );
+            (q, 4)
+        };
+
+        let mut response = db.query(q).bind(("days", days)).await?;
+        let addresses: Vec<String> = response.take(index)?;
+
+        Ok(addresses)
+    }
+
+    /// Get the wallets a player owns through the `owns` relation.
+    pub async fn owned_by(
+        db: &Surreal<Any>,
+        player: Thing,
+    ) -> Result<Vec<Model>, surrealdb::Error> {
+        let q = "SELECT * FROM $player->owns->wallet;";
+
+        let mut response = db.query(q).bind(("player", player)).await?;
+        let models: Vec<Model> = response.take(0)?;
+
+        Ok(models)
+    }
+
+    /// Replace the password of a v1 wallet, invalidating its public key and API tokens.
+    ///
+    /// Returns `false` without changing anything when the wallet's address is derived from its key,
+    /// those can only be recovered with [`Model::recover_to_key`].
+    pub async fn reset_password(
+        &self,
+        db: &Surreal<Any>,
+        password: &str,
+    ) -> Result<bool, surrealdb::Error> {
+        let fingerprint = utils::crypto::password_fingerprint(password);
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $updated = (UPDATE wallet SET
+                    hash = crypto::argon2::generate($password),
+                    password_fingerprint = $fingerprint,
+                    legacy_password = ($fingerprint = NONE),
+                    public_key = NONE
+                WHERE address = $address AND (legacy_password = true OR password_fingerprint != NONE));
+            IF array::len($updated) > 0 {
+                DELETE api_token WHERE address = $address;
+                DELETE auth_nonce WHERE address = $address;
+            };
+            RETURN array::len($updated) > 0;
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("address", self.address.clone()))
+            .bind(("password", password.to_owned()))
+            .bind(("fingerprint", fingerprint))
+            .await?;
+        let reset: Option<bool> = response.take(0)?;
+
+        Ok(reset.unwrap_or(false))
+    }
+
+    /// Move the balance, names and owners of a wallet to a new wallet owned by `private_key`, the
+    /// balance is recorded as a `recovery` transaction. The new wallet is created in the same database
+    /// transaction, so nothing is left behind when the recovery fails.
+    ///
+    /// Open holds, escrows, scheduled transfers, standing orders and invoices move along with the funds
+    /// they reserve, allowances from and to the wallet are revoked. The old wallet is locked, and loses
+    /// its public key and API tokens.
+    pub async fn recover_to_key(
+        &self,
+        db: &Surreal<Any>,
+        private_key: &str,
+    ) -> Result<Recovery, surrealdb::Error> {
+        let to = utils::crypto::make_v2_address(private_key, "k");
+        let digest = utils::crypto::make_wallet_digest(&to, private_key);
+        let hash = Model::hash_digest(db, &digest).await?;
+
+        let q = r#"
+            BEGIN TRANSACTION;
+            LET $old = (SELECT id, balance, held, pending_in, pending_out FROM wallet WHERE address = $from)[0];
+            IF $old = NONE { THROW "Wallet not found" };
+            LET $new = fn::create_wallet_ext($to, $hash, 0)[0].id;
+            LET $transaction = IF $old.balance > 0 {
+                (CREATE transaction CONTENT {
+                    from: $from,
+                    to: $to,
+                    amount: $old.balance,
+                    transaction_type: 'recovery',
+                })[0]
+            };
+            UPDATE name SET owner = $to, transfered = time::now(), last_transfered = time::now() WHERE owner = $from;
+            LET $players = (SELECT VALUE in FROM owns WHERE out = $old.id);
+            DELETE owns WHERE out = $old.id;
+            FOR $player IN $players { RELATE $player->owns->$new; };
+            UPDATE $new SET held += $old.held, pending_in += $old.pending_in, pending_out += $old.pending_out;
+            UPDATE $old.id SET held = 0, pending_in = 0, pending_out = 0, locked = true, public_key = NONE;
+            UPDATE hold SET payer = $to, updated_at = time::now() WHERE payer = $from AND status = 'authorized';
+            UPDATE hold SET merchant = $to, updated_at = time::now() WHERE merchant = $from AND status = 'authorized';
+            UPDATE escrow SET buyer = $to WHERE buyer = $from AND status = 'funded';
+            UPDATE escrow SET seller = $to WHERE seller = $from AND status = 'funded';
+            UPDATE escrow SET arbiter = $to WHERE arbiter = $from AND status = 'funded';
+            UPDATE scheduled_transfer SET from = $to, updated_at = time::now() WHERE from = $from AND status = 'pending';
+            UPDATE scheduled_transfer SET to = $to, updated_at = time::now() WHERE to = $from AND status = 'pending';
+            UPDATE standing_order SET from = $to, updated_at = time::now() WHERE from = $from AND status NOT IN ['cancelled', 'completed'];
+            UPDATE standing_order SET to = $to, updated_at = time::now() WHERE to = $from AND status NOT IN ['cancelled', 'completed'];
+            UPDATE invoice SET merchant = $to, updated_at = time::now() WHERE merchant = $from AND status = 'pending';
+            DELETE allowance WHERE owner = $from OR spender = $from;
+            DELETE api_token WHERE address = $from;
+            DELETE auth_nonce WHERE address = $from;
+            RETURN { wallet: (SELECT * FROM $new)[0], transaction: $transaction };
+            COMMIT TRANSACTION;
+        "#;
+
+        let mut response = db
+            .query(q)
+            .bind(("from", self.address.clone()))
+            .bind(("to", to))
+            .bind(("hash", hash))
+            .await?;
+        let recovery: Option<Recovery> = response.take(0)?;
+
+        recovery.ok_or_else(|| unexpected_response("recovering a wallet gave no new wallet"))
+    }
+
+    /// Register the ed25519 public key of a wallet, or remove it with `None`.
+    pub async fn set_public_key<S: AsRef<str>>(
+        db: &Surreal<Any>,
+        address: S,
+        public_key: Option<String>,
+    ) -> Result<Option<Model>, surrealdb::Error> {
+        let address = address.as_ref().to_owned();
+        let q = "UPDATE wallet SET public_key = $public_key WHERE address = $address;";
+
+        let mut response = db
+            .query(q)
+            .bind(("address", address))
+            .bind(("public_key", public_key))
+            .await?;
+        let model: Option<Model> = response.take(0)?;
+
+        Ok(model)
     }
 
     /// Get all transaction made by an address or send by an address
@@ -306,3 +751,405 @@ impl Model {
         Ok(models)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::testing;
+
+    #[actix_web::test]
+    async fn upgrades_legacy_hashes() {
+        let db = testing::connect().await;
+        let address = utils::crypto::make_v2_address("hunter2", "k");
+        let digest = utils::crypto::make_wallet_digest(&address, "hunter2");
+        testing::insert_wallet(&db, &address, &digest, 10).await;
+
+        let legacy = Model::get_by_address(&db, address.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!legacy.check_digest(&db, "wrong").await.unwrap());
+        assert!(legacy.check_digest(&db, &digest).await.unwrap());
+
+        let upgraded = Model::get_by_address(&db, address.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        let hash = upgraded.hash.clone().unwrap();
+        assert_eq!(WalletHashVersion::of(&hash), WalletHashVersion::Argon2);
+        assert_ne!(hash, digest);
+
+        assert!(upgraded.check_digest(&db, &digest).await.unwrap());
+        assert!(!upgraded.check_digest(&db, "wrong").await.unwrap());
+
+        let verified = Model::verify_address(&db, "hunter2")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(verified.authed);
+    }
+
+    async fn legacy_addresses(db: &Surreal<Any>) -> Vec<String> {
+        let q = "SELECT VALUE address FROM wallet WHERE legacy_password = true ORDER BY address;";
+
+        db.query(q).await.unwrap().take(0).unwrap()
+    }
+
+    /// Flag v1 wallets the way the `v1_password_lookup` migration does.
+    async fn flag_legacy_wallets(db: &Surreal<Any>) {
+        let q = include_str!(
+            "../../../surrealdb-migrations/migrations/20261019_140000_v1_password_lookup.surql"
+        );
+
+        db.query(q).await.unwrap().check().unwrap();
+    }
+
+    #[actix_web::test]
+    async fn flags_only_v1_wallets_for_the_legacy_scan() {
+        let db = testing::connect().await;
+
+        let hash = Model::hash_digest(&db, "v1-password").await.unwrap();
+        testing::insert_wallet(&db, "x1y2z3w4v5", &hash, 10).await;
+        // Random v1 addresses can look like Krist ones.
+        let hash = Model::hash_digest(&db, "k-password").await.unwrap();
+        testing::insert_wallet(&db, "kabc123xyz", &hash, 10).await;
+
+        let legacy = utils::crypto::make_v2_address("legacy-key", "k");
+        let digest = utils::crypto::make_wallet_digest(&legacy, "legacy-key");
+        testing::insert_wallet(&db, &legacy, &digest, 10).await;
+
+        flag_legacy_wallets(&db).await;
+
+        // Krist-style wallets only get an argon2 hash after the migration, once they log in.
+        let wallet = Model::get_by_address(&db, legacy.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(wallet.check_digest(&db, &digest).await.unwrap());
+
+        assert_eq!(
+            legacy_addresses(&db).await,
+            vec!["kabc123xyz", "x1y2z3w4v5"]
+        );
+        let found = Model::verify_password(&db, "k-password".to_owned(), None, true)
+            .await
+            .unwrap();
+        assert_eq!(found.unwrap().address, "kabc123xyz");
+    }
+
+    #[actix_web::test]
+    async fn finds_v1_wallets_by_fingerprint() {
+        let db = testing::connect().await;
+        let hash = Model::hash_digest(&db, "v1-password").await.unwrap();
+        testing::insert_wallet(&db, "x1y2z3w4v5", &hash, 10).await;
+        flag_legacy_wallets(&db).await;
+
+        let password = || "v1-password".to_owned();
+        let fingerprint = || Some("fingerprint".to_owned());
+
+        // Without the scan, a wallet without a fingerprint can't be found.
+        let found = Model::verify_password(&db, password(), fingerprint(), false).await;
+        assert_eq!(found.unwrap(), None);
+        let found = Model::verify_password(&db, "wrong".to_owned(), fingerprint(), true).await;
+        assert_eq!(found.unwrap(), None);
+
+        // The first login through the scan stores the fingerprint and clears the flag.
+        let found = Model::verify_password(&db, password(), fingerprint(), true).await;
+        assert_eq!(found.unwrap().unwrap().address, "x1y2z3w4v5");
+        assert!(legacy_addresses(&db).await.is_empty());
+
+        let found = Model::verify_password(&db, password(), fingerprint(), false).await;
+        assert_eq!(found.unwrap().unwrap().address, "x1y2z3w4v5");
+
+        // The fingerprint only finds the wallet, the password still has to match.
+        let found = Model::verify_password(&db, "wrong".to_owned(), fingerprint(), false).await;
+        assert_eq!(found.unwrap(), None);
+    }
+
+    #[actix_web::test]
+    async fn creates_wallets_for_unknown_keys_unless_disabled() {
+        let db = testing::connect().await;
+        let address = utils::crypto::make_v2_address("new-key", "k");
+
+        let verified = Model::verify_key(&db, "new-key", false).await.unwrap();
+        assert_eq!(verified, None);
+        assert_eq!(
+            Model::get_by_address(&db, address.clone()).await.unwrap(),
+            None
+        );
+
+        let verified = Model::verify_key(&db, "new-key", true)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(verified.authed);
+        assert_eq!(verified.address.address, address);
+        assert_eq!(verified.address.balance, dec!(0));
+
+        // Known wallets are verified either way.
+        let verified = Model::verify_key(&db, "new-key", false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(verified.authed);
+    }
+
+    #[actix_web::test]
+    async fn purges_only_dormant_wallets() {
+        let db = testing::connect().await;
+        for address in [
+            "kdormant01",
+            "kdormant02",
+            "kfunded001",
+            "kspent0001",
+            "knamed0001",
+        ] {
+            testing::insert_wallet(&db, address, "hash", 0).await;
+        }
+        for address in ["kseen00001", "klocked001", "kshared001", "ksigned001"] {
+            testing::insert_wallet(&db, address, "hash", 0).await;
+        }
+        for address in ["kescrow001", "khold00001", "korder0001", "ksched0001"] {
+            testing::insert_wallet(&db, address, "hash", 0).await;
+        }
+
+        let q = r#"
+            UPDATE wallet SET balance = 5 WHERE address = "kfunded001";
+            UPDATE wallet SET total_in = 5, total_out = 5 WHERE address = "kspent0001";
+            UPDATE wallet SET last_seen = time::now() + 1h WHERE address = "kseen00001";
+            UPDATE wallet SET locked = true WHERE address = "klocked001";
+            UPDATE wallet SET is_shared = true WHERE address = "kshared001";
+            UPDATE wallet SET public_key = "key" WHERE address = "ksigned001";
+            CREATE name CONTENT { name: "named", owner: "knamed0001", unpaid: 0 };
+            CREATE api_token CONTENT { address: "kdormant01", token_hash: "token", scopes: [] };
+            CREATE escrow CONTENT { buyer: "kfunded001", seller: "kescrow001", amount: 1, deadline: time::now() + 1h };
+            CREATE hold CONTENT { payer: "kfunded001", merchant: "khold00001", amount: 1, expires_at: time::now() + 1h };
+            CREATE standing_order CONTENT { from: "kfunded001", to: "korder0001", amount: 1, interval: 3600, next_run: time::now() + 1h };
+            CREATE scheduled_transfer CONTENT { from: "kfunded001", to: "ksched0001", amount: 1, execute_at: time::now() + 1h };
+            CREATE escrow CONTENT { buyer: "kdormant02", seller: "kfunded001", amount: 1, deadline: time::now(), status: 'expired' };
+        "#;
+        db.query(q).await.unwrap().check().unwrap();
+
+        // Nothing is old enough yet.
+        assert!(Model::purge_dormant(&db, 1, true).await.unwrap().is_empty());
+
+        let mut listed = Model::purge_dormant(&db, 0, true).await.unwrap();
+        listed.sort();
+        assert_eq!(listed, vec!["kdormant01", "kdormant02"]);
+        assert_eq!(Model::count(&db).await.unwrap(), 14);
+
+        let mut purged = Model::purge_dormant(&db, 0, false).await.unwrap();
+        purged.sort();
+        assert_eq!(purged, listed);
+        assert_eq!(Model::count(&db).await.unwrap(), 12);
+
+        let q = "SELECT VALUE address FROM api_token;";
+        let tokens: Vec<String> = db.query(q).await.unwrap().take(0).unwrap();
+        assert!(tokens.is_empty());
+    }
+
+    /// Insert a wallet with an argon2 hash of the digest of the given private key, returning its address.
+    async fn insert_keyed_wallet(db: &Surreal<Any>, private_key: &str, balance: i64) -> String {
+        let address = utils::crypto::make_v2_address(private_key, "k");
+        let digest = utils::crypto::make_wallet_digest(&address, private_key);
+        let hash = Model::hash_digest(db, &digest).await.unwrap();
+        testing::insert_wallet(db, &address, &hash, balance).await;
+
+        address
+    }
+
+    #[actix_web::test]
+    async fn refuses_locked_wallets() {
+        let db = testing::connect().await;
+        let address = insert_keyed_wallet(&db, "locked-key", 10).await;
+        testing::insert_wallet(&db, "kother0001", "hash", 10).await;
+
+        let wallet = Model::get_by_address(&db, address.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        let digest = utils::crypto::make_wallet_digest(&address, "locked-key");
+        let credential = SessionCredential::Digest(digest.clone());
+        assert!(wallet.check_digest(&db, &digest).await.unwrap());
+        assert!(testing::transfer(&db, &address, "kother0001", 1)
+            .await
+            .is_ok());
+
+        let q = "UPDATE wallet SET locked = true, public_key = 'key' WHERE address = $address;";
+        db.query(q)
+            .bind(("address", address.clone()))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        let wallet = Model::get_by_address(&db, address.clone())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(!wallet.check_digest(&db, &digest).await.unwrap());
+        let verified = Model::verify_key(&db, "locked-key", true)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!verified.authed);
+        let verified = Model::verify_credential(&db, &address, &credential).await;
+        assert_eq!(verified.unwrap(), None);
+        let credential = SessionCredential::PublicKey("key".to_owned());
+        let verified = Model::verify_credential(&db, &address, &credential).await;
+        assert_eq!(verified.unwrap(), None);
+
+        assert!(testing::transfer(&db, &address, "kother0001", 1)
+            .await
+            .is_err());
+        assert!(testing::transfer(&db, "kother0001", &address, 1)
+            .await
+            .is_err());
+        let wallet = Model::get_by_address(&db, address).await.unwrap().unwrap();
+        assert_eq!(wallet.balance, dec!(9));
+    }
+
+    #[actix_web::test]
+    async fn resets_only_v1_passwords() {
+        let db = testing::connect().await;
+        let hash = Model::hash_digest(&db, "v1-password").await.unwrap();
+        testing::insert_wallet(&db, "x1y2z3w4v5", &hash, 10).await;
+        flag_legacy_wallets(&db).await;
+        let address = insert_keyed_wallet(&db, "derived-key", 10).await;
+
+        let q = r#"
+            UPDATE wallet SET public_key = "key";
+            CREATE api_token CONTENT { address: "x1y2z3w4v5", token_hash: "token", scopes: [] };
+        "#;
+        db.query(q).await.unwrap().check().unwrap();
+
+        let derived = Model::get_by_address(&db, address.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!derived.reset_password(&db, "new-password").await.unwrap());
+        let derived = Model::get_by_address(&db, address).await.unwrap().unwrap();
+        assert_eq!(derived.public_key.as_deref(), Some("key"));
+
+        let wallet = Model::get_by_address(&db, "x1y2z3w4v5".to_owned())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(wallet.reset_password(&db, "new-password").await.unwrap());
+
+        let found = Model::verify(&db, "v1-password".to_owned()).await.unwrap();
+        assert_eq!(found, None);
+        let found = Model::verify(&db, "new-password".to_owned()).await.unwrap();
+        let found = found.unwrap();
+        assert_eq!(found.address, "x1y2z3w4v5");
+        assert_eq!(found.balance, dec!(10));
+        assert_eq!(found.public_key, None);
+
+        let q = "SELECT VALUE address FROM api_token;";
+        let tokens: Vec<String> = db.query(q).await.unwrap().take(0).unwrap();
+        assert!(tokens.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn recovers_funds_and_commitments_to_a_new_wallet() {
+        let db = testing::connect().await;
+        let old = insert_keyed_wallet(&db, "lost-key", 100).await;
+        testing::insert_wallet(&db, "kother0001", "hash", 100).await;
+
+        // The old wallet has 10 on hold for kother0001, and 20 scheduled for and from it.
+        let q = r#"
+            UPDATE wallet SET balance -= 10, held += 10, pending_in += 20 WHERE address = $old;
+            UPDATE wallet SET balance -= 20, pending_out += 20 WHERE address = "kother0001";
+            CREATE hold CONTENT { payer: $old, merchant: "kother0001", amount: 10, expires_at: time::now() + 1h };
+            CREATE scheduled_transfer CONTENT { from: "kother0001", to: $old, amount: 20, execute_at: time::now() + 1h };
+            CREATE escrow CONTENT { buyer: "kother0001", seller: $old, amount: 5, deadline: time::now() + 1h };
+            CREATE allowance CONTENT { owner: $old, spender: "kother0001", amount: 50 };
+            CREATE name CONTENT { name: "lost", owner: $old, unpaid: 0 };
+        "#;
+        db.query(q)
+            .bind(("old", old.clone()))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+
+        let wallet = Model::get_by_address(&db, old.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        let recovery = wallet.recover_to_key(&db, "new-key").await.unwrap();
+        let new = recovery.wallet.address;
+        assert_eq!(new, utils::crypto::make_v2_address("new-key", "k"));
+        assert_eq!(recovery.transaction.unwrap().amount, dec!(90));
+
+        let old_wallet = Model::get_by_address(&db, old.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(old_wallet.locked);
+        assert_eq!(old_wallet.balance, dec!(0));
+        assert_eq!(old_wallet.held, dec!(0));
+        assert_eq!(old_wallet.pending_in, dec!(0));
+        let verified = Model::verify_key(&db, "lost-key", true)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!verified.authed);
+
+        let new_wallet = Model::get_by_address(&db, new.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(new_wallet.balance, dec!(90));
+        assert_eq!(new_wallet.held, dec!(10));
+        assert_eq!(new_wallet.pending_in, dec!(20));
+
+        let q = r#"
+            RETURN [
+                (SELECT VALUE payer FROM hold)[0],
+                (SELECT VALUE to FROM scheduled_transfer)[0],
+                (SELECT VALUE seller FROM escrow)[0],
+                (SELECT VALUE owner FROM name)[0],
+            ];
+            SELECT VALUE owner FROM allowance;
+        "#;
+        let mut response = db.query(q).await.unwrap();
+        let moved: Vec<String> = response.take(0).unwrap();
+        assert_eq!(moved, vec![new.clone(), new.clone(), new.clone(), new]);
+        let allowances: Vec<String> = response.take(1).unwrap();
+        assert!(allowances.is_empty());
+
+        // Recovering a wallet without balance moves no funds.
+        let wallet = Model::get_by_address(&db, "kother0001".to_owned())
+            .await
+            .unwrap()
+            .unwrap();
+        let q = "UPDATE wallet SET balance = 0 WHERE address = 'kother0001';";
+        db.query(q).await.unwrap().check().unwrap();
+        let recovery = wallet.recover_to_key(&db, "empty-key").await.unwrap();
+        assert_eq!(recovery.transaction, None);
+    }
+
+    #[actix_web::test]
+    async fn leaves_no_wallet_behind_when_recovery_fails() {
+        let db = testing::connect().await;
+        let old = insert_keyed_wallet(&db, "lost-key", 100).await;
+        let wallet = Model::get_by_address(&db, old.clone())
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Moving the balance of a locked wallet is refused, along with the whole recovery.
+        let q = "UPDATE wallet SET locked = true WHERE address = $old;";
+        db.query(q)
+            .bind(("old", old))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        assert!(wallet.recover_to_key(&db, "new-key").await.is_err());
+
+        let new = utils::crypto::make_v2_address("new-key", "k");
+        assert_eq!(Model::get_by_address(&db, new).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,80 @@
+//! In-memory database for tests, set up with the same schemas and migrations as a real deployment.
+use rust_decimal::Decimal;
+use surrealdb::{
+    engine::any::{self, Any},
+    Surreal,
+};
+use surrealdb_migrations::MigrationRunner;
+
+use super::models::{transaction, wallet};
+
+/// Points the migration runner at `surrealdb-migrations`, the same as the `.surrealdb` of a deployment.
+const MIGRATIONS_CONFIG: &str = ".surrealdb.example";
+
+/// Connect to a fresh in-memory database with every migration applied.
+pub async fn connect() -> Surreal<Any> {
+    let db = any::connect("mem://")
+        .await
+        .expect("Failed to start the in-memory database");
+    db.use_ns("kromer")
+        .use_db("kromer")
+        .await
+        .expect("Failed to select the test database");
+
+    MigrationRunner::new(&db)
+        .use_config_file(&MIGRATIONS_CONFIG)
+        .up()
+        .await
+        .expect("Failed to apply SurrealDB Migrations");
+
+    db
+}
+
+/// Insert a wallet with the given balance and raw hash, bypassing `fn::create_wallet_ext`.
+pub async fn insert_wallet(db: &Surreal<Any>, address: &str, hash: &str, balance: i64) {
+    let q =
+        "CREATE wallet CONTENT { address: $address, hash: $hash, balance: <decimal> $balance };";
+
+    db.query(q)
+        .bind(("address", address.to_owned()))
+        .bind(("hash", hash.to_owned()))
+        .bind(("balance", balance))
+        .await
+        .expect("Failed to create the wallet")
+        .check()
+        .expect("Failed to create the wallet");
+}
+
+/// Get the wallet of an address, which must exist.
+pub async fn wallet(db: &Surreal<Any>, address: &str) -> wallet::Model {
+    wallet::Model::get_by_address(db, address.to_owned())
+        .await
+        .expect("Failed to get the wallet")
+        .expect("The wallet does not exist")
+}
+
+/// The balance of the wallet of an address, see [`wallet`].
+pub async fn balance(db: &Surreal<Any>, address: &str) -> Decimal {
+    wallet(db, address).await.balance
+}
+
+/// Create a transfer, moving the funds through the transaction event like every other transfer.
+pub async fn transfer(
+    db: &Surreal<Any>,
+    from: &str,
+    to: &str,
+    amount: i64,
+) -> Result<transaction::Model, surrealdb::Error> {
+    let q = "CREATE transaction CONTENT { from: $from, to: $to, amount: <decimal> $amount, transaction_type: 'transfer' };";
+
+    let mut response = db
+        .query(q)
+        .bind(("from", from.to_owned()))
+        .bind(("to", to.to_owned()))
+        .bind(("amount", amount))
+        .await?
+        .check()?;
+    let model: Option<transaction::Model> = response.take(0)?;
+
+    Ok(model.expect("creating a transaction should return it"))
+}
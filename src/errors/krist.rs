@@ -1,7 +1,14 @@
 //! Responses and error types for the krist api routes
 pub mod address;
+pub mod allowance;
+pub mod escrow;
 pub mod generic;
+pub mod hold;
+pub mod invoice;
 pub mod name;
+pub mod scheduled_transfer;
+pub mod standing_order;
+pub mod token;
 pub mod transaction;
 pub mod websockets;
 
@@ -22,12 +29,33 @@ pub enum KristError {
     #[error(transparent)]
     Address(#[from] address::AddressError),
 
+    #[error(transparent)]
+    Allowance(#[from] allowance::AllowanceError),
+
+    #[error(transparent)]
+    Escrow(#[from] escrow::EscrowError),
+
     #[error(transparent)]
     Generic(#[from] generic::GenericError),
 
+    #[error(transparent)]
+    Hold(#[from] hold::HoldError),
+
+    #[error(transparent)]
+    Invoice(#[from] invoice::InvoiceError),
+
     #[error(transparent)]
     Name(#[from] name::NameError),
 
+    #[error(transparent)]
+    ScheduledTransfer(#[from] scheduled_transfer::ScheduledTransferError),
+
+    #[error(transparent)]
+    StandingOrder(#[from] standing_order::StandingOrderError),
+
+    #[error(transparent)]
+    Token(#[from] token::TokenError),
+
     #[error(transparent)]
     Transaction(#[from] transaction::TransactionError),
 
@@ -50,8 +78,15 @@ impl KristErrorExt for KristError {
     fn error_type(&self) -> &'static str {
         match self {
             KristError::Address(e) => e.error_type(),
+            KristError::Allowance(e) => e.error_type(),
+            KristError::Escrow(e) => e.error_type(),
             KristError::Generic(e) => e.error_type(),
+            KristError::Hold(e) => e.error_type(),
+            KristError::Invoice(e) => e.error_type(),
             KristError::Name(e) => e.error_type(),
+            KristError::ScheduledTransfer(e) => e.error_type(),
+            KristError::StandingOrder(e) => e.error_type(),
+            KristError::Token(e) => e.error_type(),
             KristError::Transaction(e) => e.error_type(),
             KristError::WebSocket(e) => e.error_type(),
             KristError::Database(_) => "internal_server_error",
@@ -67,8 +102,15 @@ impl error::ResponseError for KristError {
         //       For some reason, that bug was never fixed and is just set there for forever, pretty stupid if you ask me.
         match self {
             KristError::Address(e) => e.status_code(),
+            KristError::Allowance(e) => e.status_code(),
+            KristError::Escrow(e) => e.status_code(),
             KristError::Generic(e) => e.status_code(),
+            KristError::Hold(e) => e.status_code(),
+            KristError::Invoice(e) => e.status_code(),
             KristError::Name(e) => e.status_code(),
+            KristError::ScheduledTransfer(e) => e.status_code(),
+            KristError::StandingOrder(e) => e.status_code(),
+            KristError::Token(e) => e.status_code(),
             KristError::Transaction(e) => e.status_code(),
             KristError::WebSocket(e) => e.status_code(),
             KristError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
@@ -79,8 +121,15 @@ impl error::ResponseError for KristError {
     fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
         match self {
             KristError::Address(e) => e.error_response(),
+            KristError::Allowance(e) => e.error_response(),
+            KristError::Escrow(e) => e.error_response(),
             KristError::Generic(e) => e.error_response(),
+            KristError::Hold(e) => e.error_response(),
+            KristError::Invoice(e) => e.error_response(),
             KristError::Name(e) => e.error_response(),
+            KristError::ScheduledTransfer(e) => e.error_response(),
+            KristError::StandingOrder(e) => e.error_response(),
+            KristError::Token(e) => e.error_response(),
             KristError::Transaction(e) => e.error_response(),
             KristError::WebSocket(e) => e.error_response(),
             KristError::Database(_) => {
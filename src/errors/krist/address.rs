@@ -10,6 +10,15 @@ pub enum AddressError {
 
     #[error("Authentication failed")]
     AuthFailed,
+
+    #[error("Address {0} has no public key registered")]
+    NoPublicKey(String),
+
+    #[error("Too many failed login attempts, try again in {0} seconds")]
+    TooManyAttempts(u64),
+
+    #[error("Address {0} is locked")]
+    Locked(String),
 }
 
 impl KristErrorExt for AddressError {
@@ -17,6 +26,9 @@ impl KristErrorExt for AddressError {
         match self {
             AddressError::NotFound(_) => "address_not_found",
             AddressError::AuthFailed => "auth_failed",
+            AddressError::NoPublicKey(_) => "no_public_key",
+            AddressError::TooManyAttempts(_) => "too_many_attempts",
+            AddressError::Locked(_) => "address_locked",
         }
     }
 }
@@ -29,6 +41,9 @@ impl error::ResponseError for AddressError {
         match self {
             AddressError::NotFound(_) => StatusCode::NOT_FOUND,
             AddressError::AuthFailed => StatusCode::UNAUTHORIZED,
+            AddressError::NoPublicKey(_) => StatusCode::BAD_REQUEST,
+            AddressError::TooManyAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
+            AddressError::Locked(_) => StatusCode::FORBIDDEN,
         }
     }
 
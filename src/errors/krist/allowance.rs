@@ -0,0 +1,58 @@
+use actix_web::{error, http::StatusCode, HttpResponse};
+use rust_decimal::Decimal;
+use thiserror::Error;
+
+use super::{KristErrorExt, KristErrorResponse};
+
+#[derive(Error, Debug)]
+pub enum AllowanceError {
+    #[error("Allowance {0} not found")]
+    NotFound(String),
+
+    #[error("{0} has not granted you an allowance")]
+    NotGranted(String),
+
+    #[error("Only the owner or spender of the allowance can do this")]
+    Forbidden,
+
+    #[error("The allowance does not cover transfers to {0}")]
+    RecipientNotAllowed(String),
+
+    #[error("Only {0} of the allowance can still be spent")]
+    Exceeded(Decimal),
+}
+
+impl error::ResponseError for AllowanceError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AllowanceError::NotFound(_) => StatusCode::NOT_FOUND,
+            AllowanceError::NotGranted(_) => StatusCode::FORBIDDEN,
+            AllowanceError::Forbidden => StatusCode::FORBIDDEN,
+            AllowanceError::RecipientNotAllowed(_) => StatusCode::FORBIDDEN,
+            AllowanceError::Exceeded(_) => StatusCode::FORBIDDEN,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let error = KristErrorResponse {
+            ok: false,
+            error: self.error_type(),
+            message: self.to_string(),
+            info: None,
+        };
+
+        HttpResponse::build(self.status_code()).json(error)
+    }
+}
+
+impl KristErrorExt for AllowanceError {
+    fn error_type(&self) -> &'static str {
+        match self {
+            AllowanceError::NotFound(_) => "allowance_not_found",
+            AllowanceError::NotGranted(_) => "allowance_not_granted",
+            AllowanceError::Forbidden => "allowance_forbidden",
+            AllowanceError::RecipientNotAllowed(_) => "allowance_recipient_not_allowed",
+            AllowanceError::Exceeded(_) => "allowance_exceeded",
+        }
+    }
+}
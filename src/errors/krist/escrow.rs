@@ -0,0 +1,52 @@
+use actix_web::{error, http::StatusCode, HttpResponse};
+use thiserror::Error;
+
+use super::{KristErrorExt, KristErrorResponse};
+
+#[derive(Error, Debug)]
+pub enum EscrowError {
+    #[error("Escrow {0} not found")]
+    NotFound(String),
+
+    #[error("Escrow {0} was already settled")]
+    AlreadySettled(String),
+
+    #[error("Only the {0} of the escrow can do this")]
+    Forbidden(&'static str),
+
+    #[error("The arbiter can't be the buyer or the seller")]
+    InvalidArbiter,
+}
+
+impl error::ResponseError for EscrowError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            EscrowError::NotFound(_) => StatusCode::NOT_FOUND,
+            EscrowError::AlreadySettled(_) => StatusCode::CONFLICT,
+            EscrowError::Forbidden(_) => StatusCode::FORBIDDEN,
+            EscrowError::InvalidArbiter => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let error = KristErrorResponse {
+            ok: false,
+            error: self.error_type(),
+            message: self.to_string(),
+            info: None,
+        };
+
+        HttpResponse::build(self.status_code()).json(error)
+    }
+}
+
+impl KristErrorExt for EscrowError {
+    fn error_type(&self) -> &'static str {
+        match self {
+            EscrowError::NotFound(_) => "escrow_not_found",
+            EscrowError::AlreadySettled(_) => "escrow_settled",
+            EscrowError::Forbidden(_) => "escrow_forbidden",
+            EscrowError::InvalidArbiter => "escrow_invalid_arbiter",
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use actix_web::{error, http::StatusCode, HttpResponse};
+use rust_decimal::Decimal;
+use thiserror::Error;
+
+use super::{KristErrorExt, KristErrorResponse};
+
+#[derive(Error, Debug)]
+pub enum HoldError {
+    #[error("Hold {0} not found")]
+    NotFound(String),
+
+    #[error("Only the merchant of the hold can do this")]
+    Forbidden,
+
+    #[error("Hold {0} is no longer authorized")]
+    NotAuthorized(String),
+
+    #[error("Only {0} of the hold can still be captured")]
+    ExceedsRemaining(Decimal),
+}
+
+impl error::ResponseError for HoldError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            HoldError::NotFound(_) => StatusCode::NOT_FOUND,
+            HoldError::Forbidden => StatusCode::FORBIDDEN,
+            HoldError::NotAuthorized(_) => StatusCode::CONFLICT,
+            HoldError::ExceedsRemaining(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let error = KristErrorResponse {
+            ok: false,
+            error: self.error_type(),
+            message: self.to_string(),
+            info: None,
+        };
+
+        HttpResponse::build(self.status_code()).json(error)
+    }
+}
+
+impl KristErrorExt for HoldError {
+    fn error_type(&self) -> &'static str {
+        match self {
+            HoldError::NotFound(_) => "hold_not_found",
+            HoldError::Forbidden => "hold_forbidden",
+            HoldError::NotAuthorized(_) => "hold_not_authorized",
+            HoldError::ExceedsRemaining(_) => "hold_exceeds_remaining",
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use actix_web::{error, http::StatusCode, HttpResponse};
+use thiserror::Error;
+
+use super::{KristErrorExt, KristErrorResponse};
+
+#[derive(Error, Debug)]
+pub enum InvoiceError {
+    #[error("Invoice {0} not found")]
+    NotFound(String),
+
+    #[error("Invoice {0} has expired")]
+    Expired(String),
+
+    #[error("Invoice {0} is payable to {1}")]
+    WrongRecipient(String, String),
+
+    #[error("Metaname {0} was already used by another invoice")]
+    MetanameUsed(String),
+}
+
+impl error::ResponseError for InvoiceError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            InvoiceError::NotFound(_) => StatusCode::NOT_FOUND,
+            InvoiceError::Expired(_) => StatusCode::GONE,
+            InvoiceError::WrongRecipient(..) => StatusCode::BAD_REQUEST,
+            InvoiceError::MetanameUsed(_) => StatusCode::CONFLICT,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let error = KristErrorResponse {
+            ok: false,
+            error: self.error_type(),
+            message: self.to_string(),
+            info: None,
+        };
+
+        HttpResponse::build(self.status_code()).json(error)
+    }
+}
+
+impl KristErrorExt for InvoiceError {
+    fn error_type(&self) -> &'static str {
+        match self {
+            InvoiceError::NotFound(_) => "invoice_not_found",
+            InvoiceError::Expired(_) => "invoice_expired",
+            InvoiceError::WrongRecipient(..) => "invoice_wrong_recipient",
+            InvoiceError::MetanameUsed(_) => "invoice_metaname_used",
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use actix_web::{error, http::StatusCode, HttpResponse};
+use thiserror::Error;
+
+use super::{KristErrorExt, KristErrorResponse};
+
+#[derive(Error, Debug)]
+pub enum ScheduledTransferError {
+    #[error("Scheduled transfer {0} not found")]
+    NotFound(String),
+
+    #[error("Only the sender of the scheduled transfer can do this")]
+    Forbidden,
+
+    #[error("Scheduled transfer {0} is no longer pending")]
+    NotPending(String),
+}
+
+impl error::ResponseError for ScheduledTransferError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ScheduledTransferError::NotFound(_) => StatusCode::NOT_FOUND,
+            ScheduledTransferError::Forbidden => StatusCode::FORBIDDEN,
+            ScheduledTransferError::NotPending(_) => StatusCode::CONFLICT,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let error = KristErrorResponse {
+            ok: false,
+            error: self.error_type(),
+            message: self.to_string(),
+            info: None,
+        };
+
+        HttpResponse::build(self.status_code()).json(error)
+    }
+}
+
+impl KristErrorExt for ScheduledTransferError {
+    fn error_type(&self) -> &'static str {
+        match self {
+            ScheduledTransferError::NotFound(_) => "scheduled_transfer_not_found",
+            ScheduledTransferError::Forbidden => "scheduled_transfer_forbidden",
+            ScheduledTransferError::NotPending(_) => "scheduled_transfer_not_pending",
+        }
+    }
+}
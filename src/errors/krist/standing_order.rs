@@ -0,0 +1,47 @@
+use actix_web::{error, http::StatusCode, HttpResponse};
+use thiserror::Error;
+
+use super::{KristErrorExt, KristErrorResponse};
+
+#[derive(Error, Debug)]
+pub enum StandingOrderError {
+    #[error("Standing order {0} not found")]
+    NotFound(String),
+
+    #[error("Only the {0} of the standing order can do this")]
+    Forbidden(&'static str),
+
+    #[error("Standing order {0} is {1}")]
+    InvalidStatus(String, &'static str),
+}
+
+impl error::ResponseError for StandingOrderError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            StandingOrderError::NotFound(_) => StatusCode::NOT_FOUND,
+            StandingOrderError::Forbidden(_) => StatusCode::FORBIDDEN,
+            StandingOrderError::InvalidStatus(..) => StatusCode::CONFLICT,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let error = KristErrorResponse {
+            ok: false,
+            error: self.error_type(),
+            message: self.to_string(),
+            info: None,
+        };
+
+        HttpResponse::build(self.status_code()).json(error)
+    }
+}
+
+impl KristErrorExt for StandingOrderError {
+    fn error_type(&self) -> &'static str {
+        match self {
+            StandingOrderError::NotFound(_) => "standing_order_not_found",
+            StandingOrderError::Forbidden(_) => "standing_order_forbidden",
+            StandingOrderError::InvalidStatus(..) => "standing_order_invalid_status",
+        }
+    }
+}
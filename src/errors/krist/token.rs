@@ -0,0 +1,58 @@
+use actix_web::{error, http::StatusCode, HttpResponse};
+use thiserror::Error;
+
+use super::{KristErrorExt, KristErrorResponse};
+use crate::models::tokens::TokenScope;
+
+#[derive(Error, Debug)]
+pub enum TokenError {
+    #[error("Invalid API token")]
+    InvalidToken,
+
+    #[error("API token has expired")]
+    Expired,
+
+    #[error("API token {0} not found")]
+    NotFound(String),
+
+    #[error("API token is missing the {0} scope")]
+    MissingScope(TokenScope),
+
+    #[error("API token daily spend limit exceeded")]
+    SpendLimitExceeded,
+}
+
+impl error::ResponseError for TokenError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            TokenError::InvalidToken => StatusCode::UNAUTHORIZED,
+            TokenError::Expired => StatusCode::UNAUTHORIZED,
+            TokenError::NotFound(_) => StatusCode::NOT_FOUND,
+            TokenError::MissingScope(_) => StatusCode::FORBIDDEN,
+            TokenError::SpendLimitExceeded => StatusCode::FORBIDDEN,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let error = KristErrorResponse {
+            ok: false,
+            error: self.error_type(),
+            message: self.to_string(),
+            info: None,
+        };
+
+        HttpResponse::build(self.status_code()).json(error)
+    }
+}
+
+impl KristErrorExt for TokenError {
+    fn error_type(&self) -> &'static str {
+        match self {
+            TokenError::InvalidToken => "invalid_token",
+            TokenError::Expired => "token_expired",
+            TokenError::NotFound(_) => "token_not_found",
+            TokenError::MissingScope(_) => "missing_scope",
+            TokenError::SpendLimitExceeded => "spend_limit_exceeded",
+        }
+    }
+}
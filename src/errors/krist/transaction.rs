@@ -16,6 +16,15 @@ pub enum TransactionError {
 
     #[error("Transaction conflict for parameter {0}")]
     Conflict(String),
+
+    #[error("Only the recipient of a transaction can refund it")]
+    NotRecipient,
+
+    #[error("Transactions of this type can not be refunded")]
+    NotRefundable,
+
+    #[error("Only {0} of the transaction can still be refunded")]
+    RefundExceedsAmount(rust_decimal::Decimal),
 }
 
 impl KristErrorExt for TransactionError {
@@ -25,6 +34,9 @@ impl KristErrorExt for TransactionError {
             TransactionError::NotFound => "transaction_not_found",
             TransactionError::Disabled => "transactions_disabled",
             TransactionError::Conflict(_) => "transaction_conflict",
+            TransactionError::NotRecipient => "not_transaction_recipient",
+            TransactionError::NotRefundable => "transaction_not_refundable",
+            TransactionError::RefundExceedsAmount(_) => "refund_exceeds_amount",
         }
     }
 }
@@ -36,6 +48,9 @@ impl error::ResponseError for TransactionError {
             TransactionError::NotFound => StatusCode::NOT_FOUND,
             TransactionError::Disabled => StatusCode::LOCKED,
             TransactionError::Conflict(_) => StatusCode::CONFLICT,
+            TransactionError::NotRecipient => StatusCode::FORBIDDEN,
+            TransactionError::NotRefundable => StatusCode::BAD_REQUEST,
+            TransactionError::RefundExceedsAmount(_) => StatusCode::BAD_REQUEST,
         }
     }
 
@@ -10,11 +10,17 @@ pub enum WebSocketError {
 
     #[error("Failed to create a WebSocket handshake")]
     HandshakeError,
+
+    #[error("Too many sessions")]
+    TooManySessions,
 }
 
 impl error::ResponseError for WebSocketError {
     fn status_code(&self) -> actix_web::http::StatusCode {
-        actix_web::http::StatusCode::FORBIDDEN
+        match self {
+            WebSocketError::TooManySessions => actix_web::http::StatusCode::TOO_MANY_REQUESTS,
+            _ => actix_web::http::StatusCode::FORBIDDEN,
+        }
     }
 
     fn error_response(&self) -> actix_web::HttpResponse<actix_web::body::BoxBody> {
@@ -34,6 +40,7 @@ impl KristErrorExt for WebSocketError {
         match self {
             WebSocketError::InvalidWebsocketToken => "invalid_websocket_token",
             WebSocketError::HandshakeError => "handshake_error",
+            WebSocketError::TooManySessions => "too_many_sessions",
         }
     }
 }
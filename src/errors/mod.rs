@@ -30,6 +30,9 @@ pub enum KromerError {
     #[error("WebSocket error: {0}")]
     WebSocket(#[from] websocket::WebSocketError),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Something went wrong: {0}")]
     Internal(&'static str),
 
@@ -47,6 +50,7 @@ impl error::ResponseError for KromerError {
     fn status_code(&self) -> StatusCode {
         match self {
             KromerError::NotFound => StatusCode::NOT_FOUND,
+            KromerError::Forbidden(..) => StatusCode::FORBIDDEN,
             KromerError::Database(..) => StatusCode::INTERNAL_SERVER_ERROR,
             KromerError::Wallet(e) => e.status_code(),
             KromerError::Transaction(e) => e.status_code(),
@@ -59,6 +63,7 @@ impl error::ResponseError for KromerError {
         let response = ApiResponse {
             message: match self {
                 KromerError::NotFound => "not_found",
+                KromerError::Forbidden(..) => "forbidden",
                 KromerError::Database(..) => "database",
                 KromerError::Wallet(..) => "wallet",
                 KromerError::Transaction(..) => "transaction",
@@ -13,6 +13,12 @@ pub enum TransactionError {
 
     #[error("Sender has insufficient funds")]
     InsufficientFunds,
+
+    #[error("Only {0} of the transaction can still be reversed")]
+    ExceedsRemaining(rust_decimal::Decimal),
+
+    #[error("Transactions of this type can not be reversed")]
+    NotReversible,
 }
 
 impl error::ResponseError for TransactionError {
@@ -22,6 +28,8 @@ impl error::ResponseError for TransactionError {
             TransactionError::InvalidAmount => StatusCode::BAD_REQUEST,
             TransactionError::FailedCreate => StatusCode::INTERNAL_SERVER_ERROR,
             TransactionError::InsufficientFunds => StatusCode::BAD_REQUEST,
+            TransactionError::ExceedsRemaining(_) => StatusCode::BAD_REQUEST,
+            TransactionError::NotReversible => StatusCode::BAD_REQUEST,
         }
     }
 }
@@ -13,6 +13,12 @@ pub enum WalletError {
 
     #[error("Invalid password")]
     InvalidPassword,
+
+    #[error("Too many failed login attempts, try again in {0} seconds")]
+    TooManyAttempts(u64),
+
+    #[error("Wallet is locked")]
+    Locked,
 }
 
 impl error::ResponseError for WalletError {
@@ -22,6 +28,8 @@ impl error::ResponseError for WalletError {
             WalletError::FailedCreate => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
             WalletError::InvalidPassword => actix_web::http::StatusCode::BAD_REQUEST,
             WalletError::FailedTransfer => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+            WalletError::TooManyAttempts(_) => actix_web::http::StatusCode::TOO_MANY_REQUESTS,
+            WalletError::Locked => actix_web::http::StatusCode::FORBIDDEN,
         }
     }
 }
@@ -0,0 +1,21 @@
+//! Escrow for player-to-player trades.
+//!
+//! Funding an escrow sends the buyer's funds to the `escrow` ledger address, so they stop counting
+//! towards the buyer's spendable balance. Settling it sends them on to the seller, or back to the
+//! buyer, with a transaction referencing the funding one. Both carry `escrow=<id>` in their metadata.
+use crate::database::models::escrow::EscrowUpdate;
+use crate::models::websockets::{WebSocketEvent, WebSocketMessage};
+use crate::websockets::WebSocketServer;
+
+/// Announce the transaction that moved the funds of an escrow, and the escrow's new state.
+pub async fn notify(server: &WebSocketServer, update: EscrowUpdate) {
+    let transaction = WebSocketMessage::new_event(WebSocketEvent::Transaction {
+        transaction: update.transaction.into(),
+    });
+    server.broadcast_event(transaction).await;
+
+    let escrow = WebSocketMessage::new_event(WebSocketEvent::Escrow {
+        escrow: update.escrow.into(),
+    });
+    server.broadcast_event(escrow).await;
+}
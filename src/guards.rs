@@ -1,11 +1,241 @@
+//! Authentication of the internal API.
+//!
+//! Every service talking to the internal API (the Minecraft plugin, the Discord bot, ...) gets its own
+//! named key, limited to the scopes it needs and optionally to a set of IPs. Keys are read from the JSON
+//! file in `INTERNAL_KEYS_FILE`:
+//!
+//! ```json
+//! [{ "name": "discord-bot", "key": "...", "scopes": ["read_only"], "allowed_ips": ["10.0.0.2"] }]
+//! ```
+//!
+//! The single `INTERNAL_KEY` of older setups is still accepted as a key named `default` with every scope.
+use std::future::{ready, Ready};
+use std::net::IpAddr;
+
+use actix_web::dev::{Payload, RequestHead};
 use actix_web::guard::GuardContext;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::errors::KromerError;
+use crate::utils::net;
+use crate::websockets::WebSocketServer;
 
 pub const INTERNAL_KEY_HEADER: &str = "Kromer-Key";
 
+/// Name of the key configured through `INTERNAL_KEY`.
+const DEFAULT_KEY_NAME: &str = "default";
+
+static INTERNAL_KEYS: Lazy<Vec<InternalKeyEntry>> = Lazy::new(load_internal_keys);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InternalScope {
+    /// Read anything exposed by the internal API. Implied by every other scope.
+    ReadOnly,
+    /// Create, fund and recover wallets.
+    WalletAdmin,
+    /// Kick sessions, send notices and change subscriptions.
+    WebsocketAdmin,
+    /// Reverse transactions.
+    TransactionAdmin,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct InternalKeyConfig {
+    name: String,
+    key: String,
+    scopes: Vec<InternalScope>,
+    /// Any IP may use the key when empty.
+    #[serde(default)]
+    allowed_ips: Vec<IpAddr>,
+}
+
+#[derive(Debug)]
+struct InternalKeyEntry {
+    key_hash: [u8; 32],
+    allowed_ips: Vec<IpAddr>,
+    caller: InternalKey,
+}
+
+/// The internal key a request was made with, see [`internal_key_guard`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InternalKey {
+    pub name: String,
+    pub scopes: Vec<InternalScope>,
+}
+
+impl InternalKey {
+    pub fn has_scope(&self, scope: InternalScope) -> bool {
+        self.scopes.contains(&scope)
+            || (scope == InternalScope::ReadOnly && !self.scopes.is_empty())
+    }
+
+    /// Fail with [`KromerError::Forbidden`] unless the key was granted `scope`.
+    #[allow(clippy::result_large_err)]
+    pub fn require(&self, scope: InternalScope) -> Result<(), KromerError> {
+        if self.has_scope(scope) {
+            return Ok(());
+        }
+
+        tracing::warn!(key = %self.name, "Internal key is missing the {scope:?} scope");
+        Err(KromerError::Forbidden(format!(
+            "Internal key '{}' is missing the {scope:?} scope",
+            self.name
+        )))
+    }
+}
+
+impl FromRequest for InternalKey {
+    type Error = KromerError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        // Only missing when a handler is mounted outside of the guarded scope.
+        let key = req
+            .extensions()
+            .get::<InternalKey>()
+            .cloned()
+            .ok_or(KromerError::Internal(
+                "Request was not made with an internal key",
+            ));
+
+        ready(key)
+    }
+}
+
+fn hash_key(key: &[u8]) -> [u8; 32] {
+    Sha256::digest(key).into()
+}
+
+fn load_internal_keys() -> Vec<InternalKeyEntry> {
+    let mut configs = Vec::new();
+
+    if let Ok(path) = std::env::var("INTERNAL_KEYS_FILE") {
+        let contents = std::fs::read_to_string(&path).expect("Failed to read INTERNAL_KEYS_FILE");
+        let keys: Vec<InternalKeyConfig> = serde_json::from_str(&contents)
+            .expect("INTERNAL_KEYS_FILE is not a valid list of keys");
+        configs.extend(keys);
+    }
+
+    if let Ok(key) = std::env::var("INTERNAL_KEY") {
+        configs.push(InternalKeyConfig {
+            name: DEFAULT_KEY_NAME.to_owned(),
+            key,
+            scopes: vec![
+                InternalScope::ReadOnly,
+                InternalScope::WalletAdmin,
+                InternalScope::WebsocketAdmin,
+                InternalScope::TransactionAdmin,
+            ],
+            allowed_ips: Vec::new(),
+        });
+    }
+
+    if configs.is_empty() {
+        tracing::warn!(
+            "Neither INTERNAL_KEY nor INTERNAL_KEYS_FILE is set, the internal API is disabled"
+        );
+    }
+
+    configs
+        .into_iter()
+        .filter(|config| !config.key.is_empty())
+        .map(|config| InternalKeyEntry {
+            key_hash: hash_key(config.key.as_bytes()),
+            allowed_ips: config.allowed_ips,
+            caller: InternalKey {
+                name: config.name,
+                scopes: config.scopes,
+            },
+        })
+        .collect()
+}
+
+/// Find the key matching `given`, comparing against every key in constant time.
+fn find_key<'a>(keys: &'a [InternalKeyEntry], given: &[u8]) -> Option<&'a InternalKeyEntry> {
+    let given = hash_key(given);
+
+    keys.iter().fold(None, |found, entry| {
+        if bool::from(entry.key_hash.ct_eq(&given)) {
+            Some(entry)
+        } else {
+            found
+        }
+    })
+}
+
+fn head_ip(ctx: &GuardContext, head: &RequestHead) -> Option<IpAddr> {
+    let trust_forwarded_for = ctx
+        .app_data::<web::Data<WebSocketServer>>()
+        .is_some_and(|server| server.config.trust_forwarded_for);
+
+    net::head_ip(head, trust_forwarded_for)
+}
+
+/// Only let requests made with a known internal key, from an IP it is allowed to be used from, through.
+///
+/// The matching key is stored in the request, handlers take an [`InternalKey`] to check its scopes.
 pub fn internal_key_guard(ctx: &GuardContext) -> bool {
-    let capy_password = std::env::var("INTERNAL_KEY").expect("No INTERNAL_KEY set in .env file");
-    ctx.head()
-        .headers()
-        .get(INTERNAL_KEY_HEADER)
-        .is_some_and(|it| it.as_bytes() == capy_password.as_bytes())
+    let head = ctx.head();
+    let Some(given) = head.headers().get(INTERNAL_KEY_HEADER) else {
+        return false;
+    };
+    let Some(entry) = find_key(&INTERNAL_KEYS, given.as_bytes()) else {
+        return false;
+    };
+    let name = &entry.caller.name;
+
+    if !entry.allowed_ips.is_empty() {
+        let ip = head_ip(ctx, head);
+        if !ip.is_some_and(|ip| entry.allowed_ips.contains(&ip)) {
+            tracing::warn!(key = %name, ?ip, "Internal key used from an IP that is not allowed");
+            return false;
+        }
+    }
+
+    tracing::info!(key = %name, "Internal request: {} {}", head.method, head.uri);
+    ctx.req_data_mut().insert(entry.caller.clone());
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, key: &str, scopes: Vec<InternalScope>) -> InternalKeyEntry {
+        InternalKeyEntry {
+            key_hash: hash_key(key.as_bytes()),
+            allowed_ips: Vec::new(),
+            caller: InternalKey {
+                name: name.to_owned(),
+                scopes,
+            },
+        }
+    }
+
+    #[test]
+    fn matches_named_keys_and_scopes() {
+        let keys = vec![
+            entry("minecraft", "mc-secret", vec![InternalScope::WalletAdmin]),
+            entry("discord", "bot-secret", vec![InternalScope::ReadOnly]),
+        ];
+
+        let minecraft = &find_key(&keys, b"mc-secret").unwrap().caller;
+        assert_eq!(minecraft.name, "minecraft");
+        assert!(minecraft.has_scope(InternalScope::ReadOnly));
+        assert!(minecraft.has_scope(InternalScope::WalletAdmin));
+        assert!(!minecraft.has_scope(InternalScope::WebsocketAdmin));
+
+        let discord = &find_key(&keys, b"bot-secret").unwrap().caller;
+        assert!(discord.require(InternalScope::ReadOnly).is_ok());
+        assert!(discord.require(InternalScope::WalletAdmin).is_err());
+
+        assert!(find_key(&keys, b"mc-secre").is_none());
+        assert!(find_key(&keys, b"").is_none());
+    }
 }
@@ -0,0 +1,35 @@
+//! Authorization holds, funds a payer sets aside for a merchant to capture later.
+//!
+//! Authorizing a hold moves the funds from the payer's `balance` to their `held` balance, so they
+//! can't be spent twice. Captures move part of them back and transfer it to the merchant with a
+//! normal `transfer` transaction tagged with `hold=<id>`. Voiding or expiring the hold returns what
+//! was not captured, see [`crate::jobs::holds`].
+use surrealdb::{engine::any::Any, Surreal};
+
+use crate::database::models::hold::Model as Hold;
+use crate::database::models::transaction;
+use crate::invoices;
+use crate::models::websockets::{WebSocketEvent, WebSocketMessage};
+use crate::websockets::WebSocketServer;
+
+/// Send the payer and merchant of a hold its current state.
+pub async fn notify(server: &WebSocketServer, hold: Hold) {
+    let event = WebSocketMessage::new_event(WebSocketEvent::Hold { hold: hold.into() });
+    server.broadcast_event(event).await;
+}
+
+/// Announce a capture of a hold, and link it to the invoice it pays.
+pub async fn announce_capture(
+    db: &Surreal<Any>,
+    server: &WebSocketServer,
+    hold: Hold,
+    transaction: &transaction::Model,
+) {
+    let event = WebSocketMessage::new_event(WebSocketEvent::Transaction {
+        transaction: transaction.clone().into(),
+    });
+    server.broadcast_event(event).await;
+
+    invoices::link_payment(db, server, transaction).await;
+    notify(server, hold).await;
+}
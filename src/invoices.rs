@@ -0,0 +1,169 @@
+//! Paying invoices through the transfer endpoints, and linking payments to the invoice they pay.
+//!
+//! A transfer pays an invoice when its metadata contains `invoice=<id>`, or when it is sent to the
+//! deposit metaname of the invoice, e.g. `<metaname>@shop.kst`.
+use chrono::Utc;
+use rust_decimal::Decimal;
+use surrealdb::{engine::any::Any, Surreal};
+
+use crate::database::models::invoice::{InvoiceStatus, Model as Invoice};
+use crate::database::models::transaction::{self, TransactionNameData};
+use crate::errors::krist::{invoice::InvoiceError, KristError};
+use crate::models::websockets::{WebSocketEvent, WebSocketMessage};
+use crate::utils::common_meta;
+use crate::websockets::WebSocketServer;
+
+/// A transfer paying an invoice, see [`prepare_payment`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvoicePayment {
+    pub to: String,
+    pub amount: Decimal,
+    pub metadata: String,
+}
+
+/// Fill in a transfer paying an invoice.
+///
+/// An empty `to` defaults to the merchant, and a zero `amount` to what is still outstanding.
+pub async fn prepare_payment(
+    db: &Surreal<Any>,
+    invoice_id: &str,
+    to: &str,
+    amount: Decimal,
+    metadata: Option<String>,
+) -> Result<InvoicePayment, KristError> {
+    let invoice = Invoice::get_partial(db, invoice_id)
+        .await?
+        .ok_or_else(|| KristError::Invoice(InvoiceError::NotFound(invoice_id.to_owned())))?;
+
+    // A lapsed invoice may not be marked expired yet, the payment would not be linked to it.
+    if invoice.status == InvoiceStatus::Expired || invoice.expires_at.0 <= Utc::now() {
+        return Err(KristError::Invoice(InvoiceError::Expired(
+            invoice_id.to_owned(),
+        )));
+    }
+    if !to.is_empty() && to != invoice.merchant {
+        return Err(KristError::Invoice(InvoiceError::WrongRecipient(
+            invoice_id.to_owned(),
+            invoice.merchant,
+        )));
+    }
+
+    let amount = if amount.is_zero() {
+        invoice.outstanding()
+    } else {
+        amount
+    };
+    let reference = format!("invoice={invoice_id}");
+    let metadata = match metadata {
+        Some(metadata) if common_meta::get(&metadata, "invoice").is_some() => metadata,
+        Some(metadata) => common_meta::join([reference, metadata]),
+        None => reference,
+    };
+
+    Ok(InvoicePayment {
+        to: invoice.merchant,
+        amount,
+        metadata,
+    })
+}
+
+/// The invoice a transfer pays, if any.
+async fn invoice_for(
+    db: &Surreal<Any>,
+    transaction: &transaction::Model,
+) -> Result<Option<Invoice>, surrealdb::Error> {
+    let Some(metadata) = transaction.metadata.as_deref() else {
+        return Ok(None);
+    };
+
+    if let Some(id) = common_meta::get(metadata, "invoice") {
+        let invoice = Invoice::get_partial(db, id).await?;
+
+        return Ok(invoice.filter(|invoice| invoice.merchant == transaction.to));
+    }
+
+    match TransactionNameData::parse(metadata).meta {
+        Some(metaname) => Invoice::get_by_metaname(db, &transaction.to, &metaname).await,
+        None => Ok(None),
+    }
+}
+
+/// Link a transfer to the invoice it pays, if any, and tell the merchant.
+///
+/// The transfer already happened, so failures are only logged.
+pub async fn link_payment(
+    db: &Surreal<Any>,
+    server: &WebSocketServer,
+    transaction: &transaction::Model,
+) {
+    let invoice = match invoice_for(db, transaction).await {
+        Ok(Some(invoice)) => invoice,
+        Ok(None) => return,
+        Err(err) => {
+            tracing::error!("Failed to look up the invoice of a transaction: {err}");
+            return;
+        }
+    };
+
+    match invoice.record_payment(db, transaction).await {
+        Ok(Some(invoice)) => notify(server, invoice).await,
+        Ok(None) => tracing::debug!("Not linking a payment to an expired invoice"),
+        Err(err) => tracing::error!("Failed to link a payment to its invoice: {err}"),
+    }
+}
+
+/// Send the merchant of an invoice its current state.
+pub async fn notify(server: &WebSocketServer, invoice: Invoice) {
+    let event = WebSocketMessage::new_event(WebSocketEvent::Invoice {
+        invoice: invoice.into(),
+    });
+    server.broadcast_event(event).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::database::{models::invoice::InvoiceCreateData, testing};
+
+    async fn create(db: &Surreal<Any>, expires_in: TimeDelta) -> String {
+        let data = InvoiceCreateData {
+            merchant: "kmerchant0".to_owned(),
+            amount: dec!(25),
+            memo: None,
+            metaname: None,
+            expires_at: (Utc::now() + expires_in).into(),
+        };
+        let invoice = Invoice::create(db, data).await.unwrap().unwrap();
+
+        invoice.id.unwrap().id.to_raw()
+    }
+
+    #[actix_web::test]
+    async fn refuses_payments_of_lapsed_invoices() {
+        let db = testing::connect().await;
+        let pending = create(&db, TimeDelta::hours(1)).await;
+        let lapsed = create(&db, -TimeDelta::seconds(1)).await;
+
+        let payment = prepare_payment(&db, &pending, "", dec!(0), None).await;
+        assert_eq!(
+            payment.unwrap(),
+            InvoicePayment {
+                to: "kmerchant0".to_owned(),
+                amount: dec!(25),
+                metadata: format!("invoice={pending}"),
+            }
+        );
+
+        // Not marked expired yet, but past its expiry all the same.
+        let invoice = Invoice::get_partial(&db, &lapsed).await.unwrap().unwrap();
+        assert_eq!(invoice.status, InvoiceStatus::Pending);
+        let payment = prepare_payment(&db, &lapsed, "", dec!(0), None).await;
+        assert!(matches!(
+            payment,
+            Err(KristError::Invoice(InvoiceError::Expired(id))) if id == lapsed
+        ));
+    }
+}
@@ -0,0 +1,70 @@
+//! Purges wallets that were created by a login but never held any Krist.
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+use surrealdb::{engine::any::Any, Surreal};
+use tokio::time;
+
+use crate::audit::{Actor, AuditEntry};
+use crate::database::models::wallet::Model as Wallet;
+use crate::utils::env::env_or;
+
+#[derive(Debug, Clone)]
+pub struct DormantWalletConfig {
+    /// Wallets that were neither created nor seen for this many days are purged.
+    pub max_age_days: u64,
+    /// Time between purges, the job is disabled when this is zero.
+    pub interval: Duration,
+}
+
+impl Default for DormantWalletConfig {
+    fn default() -> Self {
+        Self {
+            max_age_days: 30,
+            interval: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl DormantWalletConfig {
+    /// Read the config from the environment, anything that is not set falls back to the default.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            max_age_days: env_or("DORMANT_WALLET_DAYS", default.max_age_days),
+            interval: Duration::from_secs(env_or(
+                "DORMANT_WALLET_PURGE_INTERVAL",
+                default.interval.as_secs(),
+            )),
+        }
+    }
+}
+
+pub fn spawn(db: Arc<Surreal<Any>>, config: DormantWalletConfig) {
+    if config.interval.is_zero() {
+        tracing::info!("Dormant wallet purging is disabled");
+        return;
+    }
+
+    actix_web::rt::spawn(async move {
+        let mut interval = time::interval(config.interval);
+
+        loop {
+            interval.tick().await;
+
+            match Wallet::purge_dormant(&db, config.max_age_days, false).await {
+                Ok(purged) if !purged.is_empty() => {
+                    tracing::info!("Purged {} dormant wallets", purged.len());
+                    AuditEntry::new(Actor::System("dormant_wallets"), "wallet.purge_dormant")
+                        .after(json!({ "days": config.max_age_days, "addresses": purged }))
+                        .write(&db)
+                        .await;
+                }
+                Ok(_) => {}
+                Err(err) => tracing::error!("Failed to purge dormant wallets: {err}"),
+            }
+        }
+    });
+}
@@ -0,0 +1,61 @@
+//! Returns the funds of escrows that were not released before their deadline.
+use std::sync::Arc;
+use std::time::Duration;
+
+use surrealdb::{engine::any::Any, Surreal};
+use tokio::time;
+
+use crate::audit::{Actor, AuditEntry};
+use crate::database::models::escrow::{EscrowStatus, Model as Escrow};
+use crate::escrows;
+use crate::models::escrows::EscrowJson;
+use crate::utils::env::env_or;
+use crate::websockets::WebSocketServer;
+
+/// Seconds between checks for escrows past their deadline, set with `ESCROW_EXPIRY_INTERVAL`.
+fn interval() -> Duration {
+    Duration::from_secs(env_or("ESCROW_EXPIRY_INTERVAL", 60).max(1))
+}
+
+async fn expire_overdue(
+    db: &Surreal<Any>,
+    server: &WebSocketServer,
+) -> Result<(), surrealdb::Error> {
+    for escrow in Escrow::overdue(db).await? {
+        let update = match escrow.refund(db, EscrowStatus::Expired).await {
+            Ok(Some(update)) => update,
+            // Released or refunded since it was fetched.
+            Ok(None) => continue,
+            // Don't hold up the other escrows, e.g. when the buyer's wallet is gone.
+            Err(err) => {
+                tracing::error!("Failed to expire an escrow of {}: {err}", escrow.buyer);
+                continue;
+            }
+        };
+        let after: EscrowJson = update.escrow.clone().into();
+
+        AuditEntry::new(Actor::System("escrows"), "escrow.expire")
+            .target(&after.id)
+            .after(&after)
+            .write(db)
+            .await;
+
+        escrows::notify(server, update).await;
+    }
+
+    Ok(())
+}
+
+pub fn spawn(db: Arc<Surreal<Any>>, server: WebSocketServer) {
+    actix_web::rt::spawn(async move {
+        let mut interval = time::interval(interval());
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = expire_overdue(&db, &server).await {
+                tracing::error!("Failed to expire escrows: {err}");
+            }
+        }
+    });
+}
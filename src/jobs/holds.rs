@@ -0,0 +1,55 @@
+//! Releases what was not captured of holds that expired.
+use std::sync::Arc;
+use std::time::Duration;
+
+use surrealdb::{engine::any::Any, Surreal};
+use tokio::time;
+
+use crate::audit::{Actor, AuditEntry};
+use crate::database::models::hold::{HoldStatus, Model as Hold};
+use crate::holds;
+use crate::models::holds::HoldJson;
+use crate::utils::env::env_or;
+use crate::websockets::WebSocketServer;
+
+/// Seconds between checks for expired holds, set with `HOLD_EXPIRY_INTERVAL`.
+fn interval() -> Duration {
+    Duration::from_secs(env_or("HOLD_EXPIRY_INTERVAL", 60).max(1))
+}
+
+async fn expire_overdue(
+    db: &Surreal<Any>,
+    server: &WebSocketServer,
+) -> Result<(), surrealdb::Error> {
+    for hold in Hold::overdue(db).await? {
+        // Captured in full or voided since it was fetched.
+        let Some(hold) = hold.release(db, HoldStatus::Expired).await? else {
+            continue;
+        };
+        let after: HoldJson = hold.clone().into();
+
+        AuditEntry::new(Actor::System("holds"), "hold.expire")
+            .target(&after.id)
+            .after(&after)
+            .write(db)
+            .await;
+
+        holds::notify(server, hold).await;
+    }
+
+    Ok(())
+}
+
+pub fn spawn(db: Arc<Surreal<Any>>, server: WebSocketServer) {
+    actix_web::rt::spawn(async move {
+        let mut interval = time::interval(interval());
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = expire_overdue(&db, &server).await {
+                tracing::error!("Failed to expire holds: {err}");
+            }
+        }
+    });
+}
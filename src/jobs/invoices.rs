@@ -0,0 +1,39 @@
+//! Expires invoices that were not paid in time.
+use std::sync::Arc;
+use std::time::Duration;
+
+use surrealdb::{engine::any::Any, Surreal};
+use tokio::time;
+
+use crate::database::models::invoice::Model as Invoice;
+use crate::invoices;
+use crate::utils::env::env_or;
+use crate::websockets::WebSocketServer;
+
+/// Seconds between checks for expired invoices, set with `INVOICE_EXPIRY_INTERVAL`.
+fn interval() -> Duration {
+    Duration::from_secs(env_or("INVOICE_EXPIRY_INTERVAL", 60).max(1))
+}
+
+pub fn spawn(db: Arc<Surreal<Any>>, server: WebSocketServer) {
+    actix_web::rt::spawn(async move {
+        let mut interval = time::interval(interval());
+
+        loop {
+            interval.tick().await;
+
+            match Invoice::expire_due(&db).await {
+                Ok(expired) => {
+                    if !expired.is_empty() {
+                        tracing::info!("Expired {} invoices", expired.len());
+                    }
+
+                    for invoice in expired {
+                        invoices::notify(&server, invoice).await;
+                    }
+                }
+                Err(err) => tracing::error!("Failed to expire invoices: {err}"),
+            }
+        }
+    });
+}
@@ -0,0 +1,27 @@
+//! Background jobs that run for as long as the server does.
+pub mod dormant_wallets;
+pub mod escrows;
+pub mod holds;
+pub mod invoices;
+pub mod scheduled_transfers;
+pub mod standing_orders;
+
+use std::sync::Arc;
+
+use surrealdb::{engine::any::Any, Surreal};
+
+use crate::websockets::WebSocketServer;
+
+/// Start every background job.
+pub fn spawn(db: Arc<Surreal<Any>>, server: WebSocketServer) {
+    dormant_wallets::spawn(db.clone(), dormant_wallets::DormantWalletConfig::from_env());
+    escrows::spawn(db.clone(), server.clone());
+    holds::spawn(db.clone(), server.clone());
+    invoices::spawn(db.clone(), server.clone());
+    scheduled_transfers::spawn(db.clone(), server.clone());
+    standing_orders::spawn(
+        db,
+        server,
+        standing_orders::StandingOrderConfig::from_env(),
+    );
+}
@@ -0,0 +1,58 @@
+//! Makes scheduled transfers once they are due.
+use std::sync::Arc;
+use std::time::Duration;
+
+use surrealdb::{engine::any::Any, Surreal};
+use tokio::time;
+
+use crate::audit::{Actor, AuditEntry};
+use crate::database::models::scheduled_transfer::{
+    Model as ScheduledTransfer, ScheduledTransferStatus,
+};
+use crate::models::scheduled_transfers::ScheduledTransferJson;
+use crate::scheduled_transfers;
+use crate::utils::env::env_or;
+use crate::websockets::WebSocketServer;
+
+/// Seconds between checks for due scheduled transfers, set with `SCHEDULED_TRANSFER_INTERVAL`.
+fn interval() -> Duration {
+    Duration::from_secs(env_or("SCHEDULED_TRANSFER_INTERVAL", 60).max(1))
+}
+
+async fn execute_due(db: &Surreal<Any>, server: &WebSocketServer) -> Result<(), surrealdb::Error> {
+    for transfer in ScheduledTransfer::due(db).await? {
+        // Cancelled since it was fetched.
+        let Some(update) = transfer.execute(db).await? else {
+            continue;
+        };
+        let after: ScheduledTransferJson = update.transfer.clone().into();
+        let action = match after.status {
+            ScheduledTransferStatus::Failed => "scheduled_transfer.fail",
+            _ => "scheduled_transfer.execute",
+        };
+
+        AuditEntry::new(Actor::System("scheduled_transfers"), action)
+            .target(&after.id)
+            .after(&after)
+            .write(db)
+            .await;
+
+        scheduled_transfers::announce_execution(db, server, update).await;
+    }
+
+    Ok(())
+}
+
+pub fn spawn(db: Arc<Surreal<Any>>, server: WebSocketServer) {
+    actix_web::rt::spawn(async move {
+        let mut interval = time::interval(interval());
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = execute_due(&db, &server).await {
+                tracing::error!("Failed to execute scheduled transfers: {err}");
+            }
+        }
+    });
+}
@@ -0,0 +1,106 @@
+//! Makes the transfers of standing orders that are due.
+use std::sync::Arc;
+use std::time::Duration;
+
+use surrealdb::{engine::any::Any, Surreal};
+use tokio::time;
+
+use crate::audit::{Actor, AuditEntry};
+use crate::database::models::standing_order::{
+    ExecutionOutcome, Model as StandingOrder, StandingOrderStatus,
+};
+use crate::models::standing_orders::StandingOrderJson;
+use crate::standing_orders;
+use crate::utils::env::env_or;
+use crate::websockets::WebSocketServer;
+
+#[derive(Debug, Clone)]
+pub struct StandingOrderConfig {
+    /// Time between checks for due standing orders.
+    pub interval: Duration,
+    /// Time until a transfer that failed for a lack of funds is tried again.
+    pub retry_delay: Duration,
+    /// Failed attempts in a row after which a standing order is suspended.
+    pub max_failures: u32,
+}
+
+impl Default for StandingOrderConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            retry_delay: Duration::from_secs(3600),
+            max_failures: 3,
+        }
+    }
+}
+
+impl StandingOrderConfig {
+    /// Read the config from the environment, anything that is not set falls back to the default.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            interval: Duration::from_secs(
+                env_or("STANDING_ORDER_INTERVAL", default.interval.as_secs()).max(1),
+            ),
+            retry_delay: Duration::from_secs(env_or(
+                "STANDING_ORDER_RETRY_DELAY",
+                default.retry_delay.as_secs(),
+            )),
+            max_failures: env_or("STANDING_ORDER_MAX_FAILURES", default.max_failures).max(1),
+        }
+    }
+}
+
+async fn execute_due(
+    db: &Surreal<Any>,
+    server: &WebSocketServer,
+    config: &StandingOrderConfig,
+) -> Result<(), surrealdb::Error> {
+    for order in StandingOrder::due(db).await? {
+        let outcome = order
+            .execute(db, config.retry_delay, config.max_failures)
+            .await?;
+
+        let (order, action) = match outcome {
+            ExecutionOutcome::Executed { order, transaction } => {
+                standing_orders::announce_transfer(db, server, &transaction).await;
+                (order, "standing_order.execute")
+            }
+            ExecutionOutcome::InsufficientFunds { order } => {
+                if order.status != StandingOrderStatus::Suspended {
+                    // Tried again later, the parties only hear about it once it is suspended.
+                    continue;
+                }
+                (order, "standing_order.suspend")
+            }
+            ExecutionOutcome::RecipientMissing { order } => (order, "standing_order.cancel"),
+            ExecutionOutcome::Skipped => continue,
+        };
+        let after: StandingOrderJson = (*order).clone().into();
+
+        AuditEntry::new(Actor::System("standing_orders"), action)
+            .target(&after.id)
+            .after(&after)
+            .write(db)
+            .await;
+
+        standing_orders::notify(server, *order).await;
+    }
+
+    Ok(())
+}
+
+pub fn spawn(db: Arc<Surreal<Any>>, server: WebSocketServer, config: StandingOrderConfig) {
+    actix_web::rt::spawn(async move {
+        let mut interval = time::interval(config.interval);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = execute_due(&db, &server, &config).await {
+                tracing::error!("Failed to execute standing orders: {err}");
+            }
+        }
+    });
+}
@@ -1,19 +1,31 @@
 use std::sync::Arc;
 
+use auth::lockout::FailedAuthTracker;
 use surrealdb::{engine::any::Any, Surreal};
 // use websockets::{token_cache::TokenCache, ws_manager::WsDataManager};
 
+pub mod allowances;
+pub mod audit;
+pub mod auth;
+pub mod batches;
 pub mod database;
 pub mod errors;
+pub mod escrows;
+pub mod holds;
 pub mod guards;
+pub mod invoices;
+pub mod jobs;
 pub mod models;
 pub mod routes;
+pub mod scheduled_transfers;
+pub mod standing_orders;
 pub mod utils;
 pub mod websockets;
 
 #[derive(Debug)]
 pub struct AppState {
     pub db: Arc<Surreal<Any>>,
+    pub auth_tracker: FailedAuthTracker,
     // pub token_cache: Arc<Mutex<TokenCache>>,
     // pub ws_manager: Arc<Mutex<WsDataManager>>,
 }
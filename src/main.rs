@@ -3,6 +3,7 @@ use std::sync::Arc;
 
 use actix_web::{middleware, web, App, HttpServer};
 
+use kromer::auth::lockout::{FailedAuthTracker, LockoutConfig};
 use kromer::websockets::WebSocketServer;
 use surrealdb::opt::auth::Root;
 use surrealdb_migrations::MigrationRunner;
@@ -53,8 +54,12 @@ async fn main() -> Result<(), KromerError> {
     Database::monitor_db_connection(db_arc.clone());
 
     let krist_ws_server = WebSocketServer::new();
+    kromer::jobs::spawn(db_arc.clone(), krist_ws_server.clone());
 
-    let state = web::Data::new(AppState { db: db_arc });
+    let state = web::Data::new(AppState {
+        db: db_arc,
+        auth_tracker: FailedAuthTracker::new(LockoutConfig::from_env()),
+    });
 
     let http_server = HttpServer::new(move || {
         App::new()
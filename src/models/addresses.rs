@@ -1,4 +1,5 @@
 use crate::database::models::wallet;
+use crate::models::transactions::TransactionJson;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
@@ -22,10 +23,39 @@ pub struct AddressCreationResponse {
     pub address: String,
 }
 
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct WalletRecoveryResponse {
+    pub ok: bool,
+    /// The address now holding the funds, unchanged when only the password was replaced.
+    pub address: String,
+    pub password: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<TransactionJson>,
+}
+
 #[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct AddressJson {
     pub address: String,
     pub balance: Decimal,
+    /// Funds reserved by holds, not included in `balance`.
+    #[serde(default, skip_serializing_if = "Decimal::is_zero")]
+    pub held: Decimal,
+    /// Funds of scheduled transfers to the address that did not execute yet.
+    #[serde(
+        rename = "pendingin",
+        default,
+        skip_serializing_if = "Decimal::is_zero"
+    )]
+    pub pending_in: Decimal,
+    /// Funds reserved by scheduled transfers from the address, not included in `balance`.
+    #[serde(
+        rename = "pendingout",
+        default,
+        skip_serializing_if = "Decimal::is_zero"
+    )]
+    pub pending_out: Decimal,
     #[serde(rename = "totalin")]
     pub total_in: Decimal,
     #[serde(rename = "totalout")]
@@ -47,6 +77,9 @@ impl From<wallet::Model> for AddressJson {
         Self {
             address: wallet.address,
             balance: wallet.balance,
+            held: wallet.held,
+            pending_in: wallet.pending_in,
+            pending_out: wallet.pending_out,
             total_in: wallet.total_in,
             total_out: wallet.total_out,
             first_seen: wallet.created_at.to_raw(), // Is this really the right thing?
@@ -80,6 +113,9 @@ mod tests {
             address: AddressJson {
                 address: "kre3w0i79j".to_owned(),
                 balance: rust_decimal_macros::dec!(86945.0),
+                held: Decimal::ZERO,
+                pending_in: Decimal::ZERO,
+                pending_out: Decimal::ZERO,
                 total_in: rust_decimal_macros::dec!(123364.0),
                 total_out: rust_decimal_macros::dec!(38292.0),
                 first_seen: "2015-03-13T12:55:18.000Z".to_owned(),
@@ -87,7 +123,7 @@ mod tests {
             },
         };
         let response_str = serde_json::to_string(&response).expect("Failed to serialize");
-        let response_str_test = r#"{"ok":true,"address":{"address":"kre3w0i79j","balance":86945,"totalin":123364,"totalout":38292,"firstseen":"2015-03-13T12:55:18.000Z"}}"#;
+        let response_str_test = r#"{"ok":true,"address":{"address":"kre3w0i79j","balance":86945.0,"totalin":123364.0,"totalout":38292.0,"firstseen":"2015-03-13T12:55:18.000Z"}}"#;
 
         assert_eq!(response_str, response_str_test);
     }
@@ -0,0 +1,94 @@
+use chrono::Utc;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::allowance;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GrantAllowanceRequest {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+    /// The wallet allowed to spend.
+    pub spender: String,
+    /// The most the spender can transfer, per period if there is one.
+    pub amount: Decimal,
+    /// Seconds after which the allowance starts over, it never does when omitted.
+    pub period: Option<u64>,
+    /// The only addresses the spender may send to, any when omitted.
+    pub recipients: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllowanceActionRequest {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllowanceListQuery {
+    /// List the allowances this address granted or was granted.
+    pub address: String,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllowanceJson {
+    pub id: String,
+    pub owner: String,
+    pub spender: String,
+    pub amount: Decimal,
+    /// What the spender can still transfer in the current period.
+    pub remaining: Decimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<u64>,
+    /// When the current period ends and the allowance starts over.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resets: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipients: Option<Vec<String>>,
+    pub created: String,
+    pub updated: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllowanceResponse {
+    pub ok: bool,
+    pub allowance: AllowanceJson,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllowanceListResponse {
+    pub ok: bool,
+    pub count: usize,
+    pub total: usize,
+    pub allowances: Vec<AllowanceJson>,
+}
+
+impl From<allowance::Model> for AllowanceJson {
+    fn from(allowance: allowance::Model) -> Self {
+        let now = Utc::now();
+        // A period that already ended starts over with the next transfer.
+        let resets = allowance
+            .period_end()
+            .filter(|end| *end > now)
+            .map(|end| surrealdb::sql::Datetime::from(end).to_raw());
+
+        Self {
+            id: allowance
+                .id
+                .as_ref()
+                .map(|id| id.id.to_raw())
+                .unwrap_or_default(),
+            remaining: allowance.remaining_at(now),
+            owner: allowance.owner,
+            spender: allowance.spender,
+            amount: allowance.amount,
+            period: allowance.period,
+            resets,
+            recipients: allowance.recipients,
+            created: allowance.created_at.to_raw(),
+            updated: allowance.updated_at.map(|updated| updated.to_raw()),
+        }
+    }
+}
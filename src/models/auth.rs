@@ -1,9 +1,31 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+use crate::errors::krist::{generic::GenericError, KristError};
+use crate::utils::wallet_format::WalletFormat;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LoginDetails {
+    /// The private key, or the wallet password when a `format` is given.
     #[serde(rename = "privatekey")]
     pub private_key: String,
+    #[serde(default)]
+    pub format: WalletFormat,
+    /// Required by the username wallet formats.
+    pub username: Option<String>,
+    /// Log in to a legacy v1 address derived from the private key, instead of the v2 address.
+    pub address: Option<String>,
+}
+
+impl LoginDetails {
+    /// The private key after applying the wallet format.
+    #[allow(clippy::result_large_err)]
+    pub fn derive_key(&self) -> Result<String, KristError> {
+        self.format
+            .derive_key(&self.private_key, self.username.as_deref())
+            .ok_or_else(|| {
+                KristError::Generic(GenericError::MissingParameter("username".to_string()))
+            })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
@@ -13,3 +35,37 @@ pub struct AddressAuthenticationResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub address: Option<String>,
 }
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct RegisterKeyRequest {
+    #[serde(rename = "privatekey")]
+    pub private_key: String,
+    /// Hex encoded ed25519 public key.
+    pub public_key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct RemoveKeyRequest {
+    #[serde(rename = "privatekey")]
+    pub private_key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct PublicKeyResponse {
+    pub ok: bool,
+    pub address: String,
+    pub public_key: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct NonceRequest {
+    pub address: String,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct NonceResponse {
+    pub ok: bool,
+    pub address: String,
+    pub nonce: String,
+    pub expires: String,
+}
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::escrow::{self, EscrowStatus};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateEscrowRequest {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+    pub seller: String,
+    pub amount: Decimal,
+    /// When the funds are returned to the buyer if they were not released yet.
+    pub deadline: DateTime<Utc>,
+    /// An address that can release the funds, or refund them, on behalf of both parties.
+    pub arbiter: Option<String>,
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EscrowActionRequest {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EscrowListQuery {
+    /// List the escrows this address is the buyer, seller or arbiter of.
+    pub address: String,
+    pub status: Option<EscrowStatus>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EscrowJson {
+    pub id: String,
+    pub buyer: String,
+    pub seller: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arbiter: Option<String>,
+    pub amount: Decimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    pub status: EscrowStatus,
+    pub deadline: String,
+    /// The ID of the transaction moving the funds into escrow.
+    pub funding: Option<String>,
+    /// The ID of the transaction paying the funds out again.
+    pub settlement: Option<String>,
+    pub created: String,
+    pub settled: Option<String>,
+}
+
+impl EscrowJson {
+    /// The buyer, seller and, if any, the arbiter.
+    pub fn parties(&self) -> impl Iterator<Item = &str> {
+        [Some(&self.buyer), Some(&self.seller), self.arbiter.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EscrowResponse {
+    pub ok: bool,
+    pub escrow: EscrowJson,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EscrowListResponse {
+    pub ok: bool,
+    pub count: usize,
+    pub total: usize,
+    pub escrows: Vec<EscrowJson>,
+}
+
+impl From<escrow::Model> for EscrowJson {
+    fn from(escrow: escrow::Model) -> Self {
+        Self {
+            id: escrow.id.map(|id| id.id.to_raw()).unwrap_or_default(),
+            buyer: escrow.buyer,
+            seller: escrow.seller,
+            arbiter: escrow.arbiter,
+            amount: escrow.amount,
+            memo: escrow.memo,
+            status: escrow.status,
+            deadline: escrow.deadline.to_raw(),
+            funding: escrow.funding.map(|funding| funding.id.to_raw()),
+            settlement: escrow.settlement.map(|settlement| settlement.id.to_raw()),
+            created: escrow.created_at.to_raw(),
+            settled: escrow.settled_at.map(|settled| settled.to_raw()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parties() {
+        let mut escrow = EscrowJson {
+            id: "abc123".to_owned(),
+            buyer: "kbuyer0000".to_owned(),
+            seller: "kseller000".to_owned(),
+            arbiter: None,
+            amount: dec!(10),
+            memo: None,
+            status: EscrowStatus::Funded,
+            deadline: String::new(),
+            funding: None,
+            settlement: None,
+            created: String::new(),
+            settled: None,
+        };
+        assert_eq!(
+            escrow.parties().collect::<Vec<_>>(),
+            ["kbuyer0000", "kseller000"]
+        );
+
+        escrow.arbiter = Some("karbiter00".to_owned());
+        assert_eq!(
+            escrow.parties().collect::<Vec<_>>(),
+            ["kbuyer0000", "kseller000", "karbiter00"]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::hold::{self, HoldStatus};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateHoldRequest {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+    pub merchant: String,
+    /// The most the merchant can capture.
+    pub amount: Decimal,
+    /// Seconds until whatever was not captured is released, an hour when omitted.
+    pub expires_in: Option<u64>,
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureHoldRequest {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+    /// Captures everything that is left when omitted.
+    pub amount: Option<Decimal>,
+    pub metadata: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoldActionRequest {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoldListQuery {
+    /// List the holds this address authorized or was authorized.
+    pub address: String,
+    pub status: Option<HoldStatus>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoldJson {
+    pub id: String,
+    pub payer: String,
+    pub merchant: String,
+    pub amount: Decimal,
+    pub captured: Decimal,
+    /// What the merchant can still capture, zero once the hold is no longer authorized.
+    pub remaining: Decimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    pub status: HoldStatus,
+    /// The IDs of the transactions made by captures.
+    pub transactions: Vec<String>,
+    pub expires: String,
+    pub created: String,
+    pub updated: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoldResponse {
+    pub ok: bool,
+    pub hold: HoldJson,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureHoldResponse {
+    pub ok: bool,
+    pub hold: HoldJson,
+    pub transaction: super::transactions::TransactionJson,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoldListResponse {
+    pub ok: bool,
+    pub count: usize,
+    pub total: usize,
+    pub holds: Vec<HoldJson>,
+}
+
+impl From<hold::Model> for HoldJson {
+    fn from(hold: hold::Model) -> Self {
+        let remaining = match hold.status {
+            HoldStatus::Authorized => hold.remaining(),
+            _ => Decimal::ZERO,
+        };
+
+        Self {
+            id: hold.id.map(|id| id.id.to_raw()).unwrap_or_default(),
+            payer: hold.payer,
+            merchant: hold.merchant,
+            amount: hold.amount,
+            captured: hold.captured,
+            remaining,
+            memo: hold.memo,
+            status: hold.status,
+            transactions: hold
+                .transactions
+                .into_iter()
+                .map(|transaction| transaction.id.to_raw())
+                .collect(),
+            expires: hold.expires_at.to_raw(),
+            created: hold.created_at.to_raw(),
+            updated: hold.updated_at.map(|updated| updated.to_raw()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+    use surrealdb::sql::Datetime;
+
+    use super::*;
+
+    fn hold(status: HoldStatus) -> hold::Model {
+        hold::Model {
+            id: None,
+            payer: "kpayer0000".to_owned(),
+            merchant: "kmerchant0".to_owned(),
+            amount: dec!(100),
+            captured: dec!(40),
+            memo: None,
+            status,
+            transactions: Vec::new(),
+            expires_at: Datetime::default(),
+            created_at: Datetime::default(),
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn remaining_is_what_was_not_captured() {
+        let json = HoldJson::from(hold(HoldStatus::Authorized));
+        assert_eq!(json.remaining, dec!(60));
+    }
+
+    #[test]
+    fn released_holds_have_nothing_remaining() {
+        for status in [HoldStatus::Voided, HoldStatus::Expired] {
+            let json = HoldJson::from(hold(status));
+            assert_eq!(json.remaining, Decimal::ZERO);
+        }
+    }
+}
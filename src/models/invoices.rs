@@ -0,0 +1,108 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::invoice::{self, InvoiceStatus};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateInvoiceRequest {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+    pub amount: Decimal,
+    pub memo: Option<String>,
+    /// Seconds until the invoice expires, defaults to a day.
+    pub expires_in: Option<i64>,
+    /// A metaname used only by this invoice, payments to `<metaname>@<name>.kst` are linked to it.
+    pub metaname: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvoiceListQuery {
+    pub merchant: String,
+    pub status: Option<InvoiceStatus>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvoiceJson {
+    pub id: String,
+    pub merchant: String,
+    pub amount: Decimal,
+    pub paid: Decimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metaname: Option<String>,
+    pub status: InvoiceStatus,
+    /// Pay the invoice by passing these parameters to a transfer endpoint, e.g.
+    /// `kromer:kabcdefghi?amount=10&invoice=<id>`.
+    pub uri: String,
+    /// The IDs of the payments linked to the invoice.
+    pub transactions: Vec<String>,
+    pub expires: String,
+    pub created: String,
+    pub updated: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvoiceResponse {
+    pub ok: bool,
+    pub invoice: InvoiceJson,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvoiceListResponse {
+    pub ok: bool,
+    pub count: usize,
+    pub total: usize,
+    pub invoices: Vec<InvoiceJson>,
+}
+
+/// The `kromer:` URI paying the outstanding amount of an invoice.
+pub fn payment_uri(merchant: &str, amount: Decimal, id: &str) -> String {
+    format!(
+        "kromer:{merchant}?amount={}&invoice={id}",
+        amount.normalize()
+    )
+}
+
+impl From<invoice::Model> for InvoiceJson {
+    fn from(invoice: invoice::Model) -> Self {
+        let id = invoice
+            .id
+            .as_ref()
+            .map(|id| id.id.to_raw())
+            .unwrap_or_default();
+
+        Self {
+            uri: payment_uri(&invoice.merchant, invoice.outstanding(), &id),
+            id,
+            merchant: invoice.merchant,
+            amount: invoice.amount,
+            paid: invoice.paid,
+            memo: invoice.memo,
+            metaname: invoice.metaname,
+            status: invoice.status,
+            transactions: invoice
+                .transactions
+                .iter()
+                .map(|transaction| transaction.id.to_raw())
+                .collect(),
+            expires: invoice.expires_at.to_raw(),
+            created: invoice.created_at.to_raw(),
+            updated: invoice.updated_at.map(|updated| updated.to_raw()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_payment_uri() {
+        let uri = payment_uri("kabcdefghi", dec!(12.50), "abc123");
+        assert_eq!(uri, "kromer:kabcdefghi?amount=12.5&invoice=abc123");
+    }
+}
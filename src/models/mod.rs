@@ -1,10 +1,17 @@
 pub mod addresses;
+pub mod allowances;
 pub mod auth;
 pub mod blocks;
 pub mod error;
+pub mod escrows;
+pub mod holds;
+pub mod invoices;
 pub mod misc;
 pub mod motd;
 pub mod names;
+pub mod scheduled_transfers;
+pub mod standing_orders;
+pub mod tokens;
 pub mod transactions;
 pub mod webserver;
 pub mod websockets;
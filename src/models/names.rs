@@ -36,7 +36,7 @@ pub struct RegisterNameRequest {
     //#[serde(rename = "desiredName")]
     //pub desired_name: String,
     #[serde(rename = "privatekey")]
-    pub private_key: String,
+    pub private_key: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
@@ -44,8 +44,9 @@ pub struct NameDataUpdateBody {
     /// The data you want to set for the name.
     /// You may pass an empty string (`""`), `null` (in JSON requests), or omit the a parameter entirely to remove the data.
     pub a: Option<String>,
+    /// Can be omitted when authenticating with an API token instead.
     #[serde(rename = "privatekey")]
-    pub private_key: String,
+    pub private_key: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize, ToResponse)]
@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::scheduled_transfer::{self, ScheduledTransferStatus};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateScheduledTransferRequest {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+    pub to: String,
+    pub amount: Decimal,
+    /// When the transfer is made, or the funds unlock for the recipient.
+    pub execute_at: DateTime<Utc>,
+    pub metadata: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledTransferActionRequest {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledTransferListQuery {
+    /// List the scheduled transfers from or to this address.
+    pub address: String,
+    pub status: Option<ScheduledTransferStatus>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledTransferJson {
+    pub id: String,
+    pub from: String,
+    pub to: String,
+    pub amount: Decimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<String>,
+    pub execute_at: String,
+    pub status: ScheduledTransferStatus,
+    /// The ID of the transaction made when it executed.
+    pub transaction: Option<String>,
+    pub created: String,
+    pub updated: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledTransferResponse {
+    pub ok: bool,
+    pub transfer: ScheduledTransferJson,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledTransferListResponse {
+    pub ok: bool,
+    pub count: usize,
+    pub total: usize,
+    pub transfers: Vec<ScheduledTransferJson>,
+}
+
+impl From<scheduled_transfer::Model> for ScheduledTransferJson {
+    fn from(transfer: scheduled_transfer::Model) -> Self {
+        Self {
+            id: transfer.id.map(|id| id.id.to_raw()).unwrap_or_default(),
+            from: transfer.from,
+            to: transfer.to,
+            amount: transfer.amount,
+            metadata: transfer.metadata,
+            execute_at: transfer.execute_at.to_raw(),
+            status: transfer.status,
+            transaction: transfer
+                .transaction
+                .map(|transaction| transaction.id.to_raw()),
+            created: transfer.created_at.to_raw(),
+            updated: transfer.updated_at.map(|updated| updated.to_raw()),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::standing_order::{self, StandingOrderStatus};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateStandingOrderRequest {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+    pub to: String,
+    pub amount: Decimal,
+    /// Seconds between transfers.
+    pub interval: u64,
+    /// When the first transfer is made, right away when omitted.
+    pub start: Option<DateTime<Utc>>,
+    /// No transfers are made after this.
+    pub ends_at: Option<DateTime<Utc>>,
+    /// The order completes after this many transfers.
+    pub count: Option<u64>,
+    pub metadata: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StandingOrderActionRequest {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StandingOrderListQuery {
+    /// List the standing orders this address pays or is paid by.
+    pub address: String,
+    pub status: Option<StandingOrderStatus>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StandingOrderJson {
+    pub id: String,
+    pub from: String,
+    pub to: String,
+    pub amount: Decimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<String>,
+    pub interval: u64,
+    pub next_run: String,
+    /// When a transfer that failed for a lack of funds is tried again.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_at: Option<String>,
+    pub ends: Option<String>,
+    pub count: Option<u64>,
+    pub runs: u64,
+    pub failures: u32,
+    pub status: StandingOrderStatus,
+    /// The ID of the last transfer made by the order.
+    pub last_transaction: Option<String>,
+    pub created: String,
+    pub updated: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StandingOrderResponse {
+    pub ok: bool,
+    pub order: StandingOrderJson,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StandingOrderListResponse {
+    pub ok: bool,
+    pub count: usize,
+    pub total: usize,
+    pub orders: Vec<StandingOrderJson>,
+}
+
+impl From<standing_order::Model> for StandingOrderJson {
+    fn from(order: standing_order::Model) -> Self {
+        Self {
+            id: order.id.map(|id| id.id.to_raw()).unwrap_or_default(),
+            from: order.from,
+            to: order.to,
+            amount: order.amount,
+            metadata: order.metadata,
+            interval: order.interval,
+            next_run: order.next_run.to_raw(),
+            retry_at: order.retry_at.map(|retry_at| retry_at.to_raw()),
+            ends: order.ends_at.map(|ends_at| ends_at.to_raw()),
+            count: order.max_runs,
+            runs: order.runs,
+            failures: order.failures,
+            status: order.status,
+            last_transaction: order
+                .last_transaction
+                .map(|transaction| transaction.id.to_raw()),
+            created: order.created_at.to_raw(),
+            updated: order.updated_at.map(|updated| updated.to_raw()),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::api_token;
+
+/// What an API token is allowed to do on behalf of its wallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    /// Log in as the wallet.
+    Read,
+    /// Send transactions from the wallet.
+    Transfer,
+    /// Register and manage names owned by the wallet.
+    Names,
+    /// Create invoices payable to the wallet.
+    Invoices,
+    /// Set up and manage recurring transfers of the wallet.
+    StandingOrders,
+    /// Capture and void holds authorized to the wallet.
+    Holds,
+    /// Grant and revoke allowances to spend from the wallet.
+    Allowances,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateTokenRequest {
+    #[serde(rename = "privatekey")]
+    pub private_key: String,
+    /// A label to tell tokens apart.
+    pub name: Option<String>,
+    pub scopes: Vec<TokenScope>,
+    /// The maximum amount of kromer the token may spend per day (UTC).
+    pub daily_limit: Option<Decimal>,
+    /// Seconds until the token expires, it never expires when omitted.
+    pub expires_in: Option<i64>,
+}
+
+/// Proves ownership of a wallet when listing or revoking its tokens.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenOwnerRequest {
+    #[serde(rename = "privatekey")]
+    pub private_key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiTokenJson {
+    pub id: String,
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub scopes: Vec<TokenScope>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daily_limit: Option<Decimal>,
+    pub spent_today: Decimal,
+    pub expires: Option<String>,
+    pub last_used: Option<String>,
+    pub created: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateTokenResponse {
+    pub ok: bool,
+    /// The secret token, this is the only time it is shown.
+    pub token: String,
+    pub info: ApiTokenJson,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub ok: bool,
+    pub token: ApiTokenJson,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenListResponse {
+    pub ok: bool,
+    pub count: usize,
+    pub tokens: Vec<ApiTokenJson>,
+}
+
+impl fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scope = match self {
+            TokenScope::Read => "read",
+            TokenScope::Transfer => "transfer",
+            TokenScope::Names => "names",
+            TokenScope::Invoices => "invoices",
+            TokenScope::StandingOrders => "standing_orders",
+            TokenScope::Holds => "holds",
+            TokenScope::Allowances => "allowances",
+        };
+
+        f.write_str(scope)
+    }
+}
+
+impl From<api_token::Model> for ApiTokenJson {
+    fn from(token: api_token::Model) -> Self {
+        let spent_today = token.spent_today();
+
+        Self {
+            id: token.id.map(|id| id.id.to_raw()).unwrap_or_default(),
+            address: token.address,
+            name: token.name,
+            scopes: token.scopes,
+            daily_limit: token.daily_limit,
+            spent_today,
+            expires: token.expires_at.map(|expires| expires.to_rfc3339()),
+            last_used: token.last_used.map(|last_used| last_used.to_rfc3339()),
+            created: token.created_at.to_rfc3339(),
+        }
+    }
+}
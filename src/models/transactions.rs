@@ -8,7 +8,7 @@ use utoipa::{
 
 use crate::database::models::serialize_record_id_opt;
 use crate::database::models::transaction;
-use transaction::TransactionNameData;
+use transaction::{BatchLeg, TransactionNameData};
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize, ToResponse, ToSchema)]
 pub struct TransactionListResponse {
@@ -25,11 +25,49 @@ pub struct TransactionListResponse {
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct TransactionDetails {
-    pub password: String,
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+    /// Defaults to the merchant when paying an invoice.
+    #[serde(default)]
     pub to: String,
+    /// Defaults to the outstanding amount when paying an invoice.
+    #[serde(default)]
     pub amount: Decimal,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<String>,
+    /// The ID of the invoice to pay.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoice: Option<String>,
+    /// Spend from this address through an allowance it granted, defaults to your own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct TransactionRefundDetails {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+    /// Defaults to everything that was not refunded yet.
+    pub amount: Option<Decimal>,
+    /// Extra metadata, added after the `ref=<id>` entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionBatchDetails {
+    /// Can be omitted when authenticating with an API token instead.
+    pub password: Option<String>,
+    pub transfers: Vec<BatchLeg>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionBatchResponse {
+    pub ok: bool,
+    /// The ID shared by the transactions of the batch.
+    pub batch: String,
+    /// One transaction per transfer, in the order they were given.
+    pub transactions: Vec<TransactionJson>,
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
@@ -56,7 +94,7 @@ pub struct TransactionJson {
     /// The sender of this transaction.
     pub from: String,
 
-    /// The recipient of this transaction. This may be `name` if the transaction was a name purchase, or `a` if it was a name's data change.
+    /// The recipient of this transaction. This may be `name` if the transaction was a name purchase, `a` if it was a name's data change, or `escrow` if funds were put in escrow.
     pub to: String,
 
     /// The amount of Krist transferred in this transaction. Can be 0, notably if the transaction was a name's data change.
@@ -76,6 +114,12 @@ pub struct TransactionJson {
     pub sent_name: Option<String>,
     #[serde(rename = "type")]
     pub transaction_type: TransactionType,
+    /// The ID of the batch transfer this transaction is a part of.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch: Option<String>,
+    /// The amount a forced reversal left its sender in debt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debt: Option<Decimal>,
 }
 
 impl<'__r> ToResponse<'__r> for TransactionJson {
@@ -124,6 +168,12 @@ pub enum TransactionType {
     NameARecord,
     NameTransfer,
     Transfer,
+    /// Funds moved from a lost wallet to a new one by the server.
+    Recovery,
+    /// Funds of an earlier transaction moved back by the server operators.
+    Reversal,
+    /// Funds moved into or out of escrow, the escrow side is the `escrow` address.
+    Escrow,
 }
 
 impl From<transaction::Model> for TransactionJson {
@@ -141,6 +191,8 @@ impl From<transaction::Model> for TransactionJson {
             sent_metaname: name_data.meta,
             sent_name: name_data.name,
             transaction_type: transaction.transaction_type,
+            batch: transaction.batch,
+            debt: transaction.debt,
         }
     }
 }
@@ -154,6 +206,9 @@ impl From<TransactionType> for &str {
             TransactionType::NameARecord => "name_a_record",
             TransactionType::NameTransfer => "name_transfer",
             TransactionType::Transfer => "transfer",
+            TransactionType::Recovery => "recovery",
+            TransactionType::Reversal => "reversal",
+            TransactionType::Escrow => "escrow",
         }
     }
 }
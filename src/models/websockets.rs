@@ -1,7 +1,15 @@
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-use super::{addresses::AddressJson, motd::DetailedMotd, transactions::TransactionJson};
+use super::{
+    addresses::AddressJson,
+    motd::DetailedMotd,
+    names::NameJson,
+    transactions::{AddressTransactionQuery, TransactionJson},
+};
+use crate::database::models::transaction::BatchLeg;
+use crate::routes::PaginationParams;
+use crate::utils::wallet_format::WalletFormat;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct WebSocketMessage {
@@ -36,20 +44,41 @@ pub enum WebSocketMessageInner {
         #[serde(flatten)]
         event: WebSocketEvent,
     },
+    /// A notice sent by the server operators, either to a single session or to everyone.
+    Notice {
+        message: String,
+    },
     Work,
     MakeTransaction {
-        /// The privatekey of your address.
+        /// The privatekey of your address. Optional if the session is logged in.
         #[serde(rename = "privatekey")]
-        private_key: String,
+        private_key: Option<String>,
 
-        /// The recipient of the transaction.
+        /// The recipient of the transaction, defaults to the merchant when paying an invoice.
+        #[serde(default)]
         to: String,
 
-        /// The amount to send to the recipient.
+        /// The amount to send to the recipient, defaults to the outstanding amount when paying an invoice.
+        #[serde(default)]
         amount: Decimal,
 
         /// Optional metadata to include in the transaction.
         metadata: Option<String>,
+
+        /// The ID of the invoice to pay.
+        invoice: Option<String>,
+
+        /// Spend from this address through an allowance it granted, defaults to your own.
+        from: Option<String>,
+    },
+
+    /// Send to many recipients at once, either every transfer is made or none are.
+    MakeBatchTransaction {
+        /// The privatekey of your address. Optional if the session is logged in.
+        #[serde(rename = "privatekey")]
+        private_key: Option<String>,
+
+        transfers: Vec<BatchLeg>,
     },
 
     GetValidSubscriptionLevels,
@@ -65,9 +94,19 @@ pub enum WebSocketMessageInner {
     Me,
     GetSubscriptionLevel,
     Logout,
+    /// Log in with either the privatekey, or a nonce signed with the wallet's keypair.
     Login {
         #[serde(rename = "privatekey")]
-        private_key: String,
+        private_key: Option<String>,
+        /// How to turn `privatekey` into the private key, see [`WalletFormat`].
+        #[serde(default)]
+        format: WalletFormat,
+        username: Option<String>,
+        /// The signing address, or a legacy v1 address when logging in with the privatekey.
+        address: Option<String>,
+        nonce: Option<String>,
+        /// Hex encoded ed25519 signature, see [`crate::auth::signature`].
+        signature: Option<String>,
     },
 
     Subscribe {
@@ -77,6 +116,39 @@ pub enum WebSocketMessageInner {
     Unsubscribe {
         event: String,
     },
+
+    /// Fetch a single transaction by its ID.
+    Transaction {
+        id: String,
+    },
+
+    /// Fetch the most recent transactions sent or received by an address.
+    AddressTransactions {
+        address: String,
+        #[serde(flatten)]
+        query: AddressTransactionQuery,
+    },
+
+    /// Fetch the names owned by an address.
+    AddressNames {
+        address: String,
+        #[serde(flatten)]
+        pagination: PaginationParams,
+    },
+
+    /// Fetch the details of a single name.
+    Name {
+        name: String,
+    },
+
+    /// Fetch the total amount of currency in circulation.
+    Supply,
+
+    /// Fetch the richest addresses.
+    Richlist {
+        #[serde(flatten)]
+        pagination: PaginationParams,
+    },
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -91,6 +163,12 @@ pub enum WebSocketMessageResponse {
         transaction: TransactionJson,
     },
 
+    MakeBatchTransaction {
+        /// The ID shared by the transactions of the batch.
+        batch: String,
+        transactions: Vec<TransactionJson>,
+    },
+
     GetValidSubscriptionLevels {
         /// All valid subscription levels
         valid_subscription_levels: Vec<String>,
@@ -129,9 +207,37 @@ pub enum WebSocketMessageResponse {
     Unsubscribe {
         subscription_level: Vec<String>,
     },
+
+    Transaction {
+        transaction: TransactionJson,
+    },
+
+    AddressTransactions {
+        count: usize,
+        transactions: Vec<TransactionJson>,
+    },
+
+    AddressNames {
+        count: usize,
+        names: Vec<NameJson>,
+    },
+
+    Name {
+        name: NameJson,
+    },
+
+    Supply {
+        supply: Decimal,
+    },
+
+    Richlist {
+        count: usize,
+        total: usize,
+        addresses: Vec<AddressJson>,
+    },
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(tag = "event", rename_all = "camelCase")]
 pub enum WebSocketEvent {
     Block {
@@ -144,6 +250,33 @@ pub enum WebSocketEvent {
     Name {
         name: super::names::NameJson,
     },
+    /// An invoice was paid or expired, only sent to its merchant.
+    Invoice {
+        invoice: super::invoices::InvoiceJson,
+    },
+    /// An escrow was funded or settled, only sent to its buyer, seller and arbiter.
+    Escrow {
+        escrow: super::escrows::EscrowJson,
+    },
+    /// A standing order changed status or made a transfer, only sent to its payer and recipient.
+    StandingOrder {
+        order: super::standing_orders::StandingOrderJson,
+    },
+    /// A hold was authorized, captured or released, only sent to its payer and merchant.
+    Hold {
+        hold: super::holds::HoldJson,
+    },
+    /// A scheduled transfer was created, executed or cancelled, only sent to its sender and recipient.
+    ScheduledTransfer {
+        transfer: super::scheduled_transfers::ScheduledTransferJson,
+    },
+    /// Failed logins to an address are piling up.
+    Security {
+        address: String,
+        failures: u32,
+        /// Amount of distinct IPs the failures came from.
+        sources: usize,
+    },
 }
 
 impl WebSocketMessage {
@@ -154,6 +287,14 @@ impl WebSocketMessage {
             r#type: WebSocketMessageInner::Event { event },
         }
     }
+
+    pub fn new_notice(message: String) -> WebSocketMessage {
+        WebSocketMessage {
+            ok: None,
+            id: None,
+            r#type: WebSocketMessageInner::Notice { message },
+        }
+    }
 }
 
 // #[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
@@ -217,13 +358,53 @@ impl WebSocketMessageInner {
             WebSocketMessageInner::GetValidSubscriptionLevels => "get_valid_subscription_levels",
             WebSocketMessageInner::Unsubscribe { .. } => "unsubscribe",
             WebSocketMessageInner::MakeTransaction { .. } => "make_transaction",
+            WebSocketMessageInner::MakeBatchTransaction { .. } => "make_batch_transaction",
             WebSocketMessageInner::Work => "work",
             WebSocketMessageInner::Hello { .. } => "hello",
             WebSocketMessageInner::Error { .. } => "error",
             WebSocketMessageInner::Response { .. } => "response",
             WebSocketMessageInner::Keepalive { .. } => "keepalive",
             WebSocketMessageInner::Event { .. } => "event",
+            WebSocketMessageInner::Notice { .. } => "notice",
+            WebSocketMessageInner::Transaction { .. } => "transaction",
+            WebSocketMessageInner::AddressTransactions { .. } => "address_transactions",
+            WebSocketMessageInner::AddressNames { .. } => "address_names",
+            WebSocketMessageInner::Name { .. } => "name",
+            WebSocketMessageInner::Supply => "supply",
+            WebSocketMessageInner::Richlist { .. } => "richlist",
             // WebSocketMessageInner::Unknown => "unknown",
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{WebSocketMessage, WebSocketMessageInner};
+
+    #[test]
+    fn test_deserialize_paginated_query() {
+        let raw = r#"{"type":"address_transactions","id":3,"address":"kabcdefghi","limit":10,"includeMined":true}"#;
+        let msg: WebSocketMessage = serde_json::from_str(raw).expect("failed to deserialize");
+
+        assert_eq!(msg.id, Some(3));
+        match msg.r#type {
+            WebSocketMessageInner::AddressTransactions { address, query } => {
+                assert_eq!(address, "kabcdefghi");
+                assert_eq!(query.limit, Some(10));
+                assert_eq!(query.offset, None);
+                assert_eq!(query.include_mined, Some(true));
+            }
+            _ => panic!("Invalid message type"),
+        }
+
+        let raw = r#"{"type":"richlist","id":4}"#;
+        let msg: WebSocketMessage = serde_json::from_str(raw).expect("failed to deserialize");
+        match msg.r#type {
+            WebSocketMessageInner::Richlist { pagination } => {
+                assert_eq!(pagination.limit, None);
+                assert_eq!(pagination.offset, None);
+            }
+            _ => panic!("Invalid message type"),
+        }
+    }
+}
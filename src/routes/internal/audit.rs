@@ -0,0 +1,31 @@
+use actix_web::{get, web, HttpResponse};
+use serde_json::json;
+
+use crate::database::models::audit_log::{AuditLogQuery, Model as AuditLog};
+use crate::errors::KromerError;
+use crate::guards::{InternalKey, InternalScope};
+use crate::AppState;
+
+/// Search the audit log, newest entries first.
+///
+/// There are deliberately no routes to change or remove entries, the table itself refuses it as well.
+#[get("")]
+async fn audit_search(
+    key: InternalKey,
+    state: web::Data<AppState>,
+    query: web::Query<AuditLogQuery>,
+) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::ReadOnly)?;
+    let (entries, total) = AuditLog::search(&state.db, &query).await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "count": entries.len(),
+        "total": total,
+        "entries": entries,
+    })))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/audit").service(audit_search));
+}
@@ -1,9 +1,15 @@
+pub mod audit;
+pub mod security;
+pub mod transactions;
 pub mod wallet;
 pub mod ws;
 
 use actix_web::web;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.configure(audit::config);
+    cfg.configure(security::config);
+    cfg.configure(transactions::config);
     cfg.configure(wallet::config);
     cfg.configure(ws::config);
 }
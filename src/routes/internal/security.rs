@@ -0,0 +1,27 @@
+use actix_web::{get, web, HttpResponse};
+use serde_json::json;
+
+use crate::errors::KromerError;
+use crate::guards::{InternalKey, InternalScope};
+use crate::AppState;
+
+/// Addresses that are the target of repeated failed logins, most attacked first.
+#[get("/attacked")]
+async fn get_attacked(
+    key: InternalKey,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::ReadOnly)?;
+
+    let addresses = state.auth_tracker.attacked_addresses();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "count": addresses.len(),
+        "addresses": addresses,
+    })))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/security").service(get_attacked));
+}
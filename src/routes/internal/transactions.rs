@@ -0,0 +1,101 @@
+use actix_web::{post, web, HttpResponse};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde_json::json;
+
+use crate::audit::{AuditEntry, RequestInfo};
+use crate::database::models::transaction::{Model as Transaction, ReturnOutcome};
+use crate::errors::transaction::TransactionError;
+use crate::guards::{InternalKey, InternalScope};
+use crate::models::transactions::{TransactionJson, TransactionType};
+use crate::models::websockets::{WebSocketEvent, WebSocketMessage};
+use crate::websockets::WebSocketServer;
+use crate::{errors::KromerError, AppState};
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+struct ReverseReq {
+    /// Defaults to everything that was not reversed yet.
+    pub amount: Option<Decimal>,
+    /// Reverse even if the recipient no longer holds the funds, leaving them with a negative balance.
+    /// What they could not cover is recorded as the `debt` of the reversal.
+    #[serde(default)]
+    pub force: bool,
+    /// Only recorded in the audit log.
+    pub reason: Option<String>,
+}
+
+/// Move the funds of a transaction back to its sender, e.g. after a scam.
+///
+/// Transactions can be reversed in several parts, but never for more than was not refunded yet.
+#[post("/{id}/reverse")]
+async fn transaction_reverse(
+    key: InternalKey,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    id: web::Path<String>,
+    data: web::Json<ReverseReq>,
+) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::TransactionAdmin)?;
+    let db = &state.db;
+    let data = data.into_inner();
+
+    if data.amount.is_some_and(|amount| amount <= dec!(0.0)) {
+        return Err(KromerError::Transaction(TransactionError::InvalidAmount));
+    }
+
+    let original = Transaction::get_partial(db, id.into_inner())
+        .await?
+        .ok_or(KromerError::Transaction(TransactionError::NotFound))?;
+    if original.transaction_type != TransactionType::Transfer {
+        return Err(KromerError::Transaction(TransactionError::NotReversible));
+    }
+
+    let (transaction, remaining) = match original.reverse(db, data.amount, data.force).await? {
+        ReturnOutcome::Returned {
+            transaction,
+            remaining,
+        } => (transaction, remaining),
+        ReturnOutcome::ExceedsRemaining { remaining } => {
+            return Err(KromerError::Transaction(
+                TransactionError::ExceedsRemaining(remaining),
+            ));
+        }
+        ReturnOutcome::InsufficientFunds { .. } => {
+            return Err(KromerError::Transaction(
+                TransactionError::InsufficientFunds,
+            ));
+        }
+    };
+    let original: TransactionJson = original.into();
+    let transaction: TransactionJson = (*transaction).into();
+
+    AuditEntry::new(&key, "transaction.reverse")
+        .target(&transaction.from)
+        .before(json!({ "transaction": original }))
+        .after(json!({
+            "transaction": transaction,
+            "remaining": remaining,
+            "forced": data.force,
+            "reason": data.reason,
+        }))
+        .request(&request)
+        .write(db)
+        .await;
+
+    // Both the recipient and the sender of the original are involved in the reversal.
+    let event = WebSocketMessage::new_event(WebSocketEvent::Transaction {
+        transaction: transaction.clone(),
+    });
+    server.broadcast_event(event).await;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": true,
+        "transaction": transaction,
+        "remaining": remaining,
+    })))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/transactions").service(transaction_reverse));
+}
@@ -3,12 +3,18 @@ use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use serde_json::json;
 
+use crate::audit::{AuditEntry, RequestInfo};
 use crate::database::models::player::Model as Player;
 use crate::database::models::wallet::Model as Wallet;
 use crate::errors::transaction::TransactionError;
 use crate::errors::wallet::WalletError;
-use crate::models::addresses::AddressCreationResponse;
+use crate::guards::{InternalKey, InternalScope};
+use crate::jobs::dormant_wallets::DormantWalletConfig;
+use crate::models::addresses::{AddressCreationResponse, WalletRecoveryResponse};
+use crate::models::transactions::TransactionJson;
+use crate::models::websockets::{WebSocketEvent, WebSocketMessage};
 use crate::utils::crypto::generate_random_password;
+use crate::websockets::WebSocketServer;
 use crate::{errors::KromerError, AppState};
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -23,6 +29,21 @@ struct GiveMoneyReq {
     pub amount: Decimal,
 }
 
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+struct PurgeDormantReq {
+    /// Defaults to `DORMANT_WALLET_DAYS`.
+    pub days: Option<u64>,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+struct RecoverReq {
+    pub mc_uuid: String,
+    /// Only needed when the player owns more than one wallet.
+    pub address: Option<String>,
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 struct Guh {
     pub name: String,
@@ -30,32 +51,29 @@ struct Guh {
 
 #[post("/create")]
 async fn wallet_create(
+    key: InternalKey,
+    request: RequestInfo,
     state: web::Data<AppState>,
     user: web::Json<MinecraftUser>,
 ) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::WalletAdmin)?;
     // fn::create_wallet_with_user(username)
     let db = &state.db;
     let user = user.into_inner();
 
     let player: Option<Player> = db
-        .create(("player", user.mc_uuid))
+        .create(("player", user.mc_uuid.clone()))
         .content(Guh { name: user.name })
         .await?;
     let player = player.ok_or_else(|| KromerError::Internal("Unable to get created player"))?;
 
     // Make V2 address based off randomly generated privatekey
     let password = generate_random_password();
-    let wallet_verify_resp = Wallet::verify_address(db, password.clone()).await?;
-    let wallet = wallet_verify_resp.address;
-    let address = wallet.address;
-
     // Set the initial balance to 100 Kromer to start with
-    let q = "UPDATE $wallet SET balance += $amount";
-    let _ = db
-        .query(q)
-        .bind(("wallet", wallet.id.clone().unwrap()))
-        .bind(("amount", dec!(100)))
-        .await?;
+    let wallet = Wallet::create_with_key(db, &password, dec!(100))
+        .await?
+        .ok_or_else(|| KromerError::Internal("Unable to get created wallet"))?;
+    let address = wallet.address;
 
     let q = "RELATE $player->owns->$wallet";
     let resp = db
@@ -64,6 +82,12 @@ async fn wallet_create(
         .bind(("wallet", wallet.id.unwrap()))
         .await?;
     tracing::debug!("Got response: {resp:?}");
+    AuditEntry::new(&key, "wallet.create")
+        .target(&address)
+        .after(json!({ "player": user.mc_uuid, "balance": wallet.balance }))
+        .request(&request)
+        .write(db)
+        .await;
 
     let resp = AddressCreationResponse { password, address };
 
@@ -72,9 +96,12 @@ async fn wallet_create(
 
 #[post("/give-money")]
 async fn wallet_give_money(
+    key: InternalKey,
+    request: RequestInfo,
     state: web::Data<AppState>,
     data: web::Json<GiveMoneyReq>,
 ) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::WalletAdmin)?;
     let db = &state.db;
     let data = data.into_inner();
 
@@ -91,6 +118,13 @@ async fn wallet_give_money(
         .bind(("wallet", wallet.id.unwrap()))
         .bind(("amount", data.amount))
         .await?;
+    AuditEntry::new(&key, "wallet.give_money")
+        .target(&wallet.address)
+        .before(json!({ "balance": wallet.balance }))
+        .after(json!({ "balance": wallet.balance + data.amount, "amount": data.amount }))
+        .request(&request)
+        .write(db)
+        .await;
 
     let resp = json!({
         "ok": true
@@ -99,10 +133,134 @@ async fn wallet_give_money(
     Ok(HttpResponse::Ok().json(resp))
 }
 
+#[post("/purge-dormant")]
+async fn wallet_purge_dormant(
+    key: InternalKey,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    data: web::Json<PurgeDormantReq>,
+) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::WalletAdmin)?;
+    let db = &state.db;
+    let data = data.into_inner();
+    let days = data
+        .days
+        .unwrap_or_else(|| DormantWalletConfig::from_env().max_age_days);
+
+    let addresses = Wallet::purge_dormant(db, days, data.dry_run).await?;
+    if !data.dry_run {
+        AuditEntry::new(&key, "wallet.purge_dormant")
+            .after(json!({ "days": days, "addresses": addresses }))
+            .request(&request)
+            .write(db)
+            .await;
+    }
+
+    let resp = json!({
+        "ok": true,
+        "dry_run": data.dry_run,
+        "count": addresses.len(),
+        "addresses": addresses,
+    });
+
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+/// Give a player access to a wallet they lost the password of.
+///
+/// v1 wallets get a new password. The address of other wallets is derived from their key, so their
+/// balance and names are moved to a new wallet instead.
+#[post("/recover")]
+async fn wallet_recover(
+    key: InternalKey,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    data: web::Json<RecoverReq>,
+) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::WalletAdmin)?;
+    let db = &state.db;
+    let data = data.into_inner();
+
+    let player = Player::get_partial(db, &data.mc_uuid)
+        .await?
+        .ok_or(KromerError::NotFound)?;
+    let player_id = player
+        .id
+        .ok_or_else(|| KromerError::Internal("Player has no ID"))?;
+    let wallets = Wallet::owned_by(db, player_id).await?;
+
+    let wallet = match data.address {
+        Some(address) => wallets.into_iter().find(|wallet| wallet.address == address),
+        None if wallets.len() > 1 => {
+            return Err(KromerError::Validation(
+                "Player owns multiple wallets, specify an address".to_owned(),
+            ));
+        }
+        None => wallets.into_iter().next(),
+    }
+    .ok_or(KromerError::Wallet(WalletError::NotFound))?;
+
+    let password = generate_random_password();
+    let reason = Some("Wallet was recovered".to_owned());
+
+    if wallet.reset_password(db, &password).await? {
+        server.kick_address(&wallet.address, reason).await;
+        AuditEntry::new(&key, "wallet.reset_password")
+            .target(&wallet.address)
+            .after(json!({ "player": data.mc_uuid }))
+            .request(&request)
+            .write(db)
+            .await;
+
+        return Ok(HttpResponse::Ok().json(WalletRecoveryResponse {
+            ok: true,
+            address: wallet.address,
+            password,
+            previous_address: None,
+            transaction: None,
+        }));
+    }
+
+    let recovery = wallet.recover_to_key(db, &password).await?;
+    let new_wallet = recovery.wallet;
+    let transaction: Option<TransactionJson> = recovery.transaction.map(Into::into);
+
+    server.kick_address(&wallet.address, reason).await;
+    if let Some(transaction) = &transaction {
+        let event = WebSocketMessage::new_event(WebSocketEvent::Transaction {
+            transaction: transaction.clone(),
+        });
+        server.broadcast_event(event).await;
+    }
+
+    AuditEntry::new(&key, "wallet.recover")
+        .target(&wallet.address)
+        .before(json!({ "balance": wallet.balance }))
+        .after(json!({
+            "player": data.mc_uuid,
+            "address": new_wallet.address,
+            "transaction": transaction,
+        }))
+        .request(&request)
+        .write(db)
+        .await;
+
+    Ok(HttpResponse::Ok().json(WalletRecoveryResponse {
+        ok: true,
+        address: new_wallet.address,
+        password,
+        previous_address: Some(wallet.address),
+        transaction,
+    }))
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/wallet")
             .service(wallet_create)
-            .service(wallet_give_money),
+            .service(wallet_give_money)
+            .service(wallet_purge_dormant)
+            .service(wallet_recover),
     );
 }
@@ -1,8 +1,14 @@
-use actix_web::{get, web, HttpResponse};
+use actix_web::{get, post, web, HttpResponse};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use surrealdb::Uuid;
 
+use crate::audit::{AuditEntry, RequestInfo};
 use crate::errors::KromerError;
+use crate::guards::{InternalKey, InternalScope};
+use crate::models::websockets::WebSocketMessage;
+use crate::AppState;
+use crate::websockets::types::common::WebSocketSubscriptionType;
 use crate::websockets::WebSocketServer;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -12,9 +18,11 @@ pub struct SessionQuery {
 
 #[get("/session")]
 async fn get_session(
+    key: InternalKey,
     server: web::Data<WebSocketServer>,
     params: web::Query<SessionQuery>,
 ) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::ReadOnly)?;
     let sessions = &server.inner.lock().await.sessions;
 
     let target_uuid = match params.session.parse::<Uuid>() {
@@ -39,12 +47,366 @@ async fn get_session(
 }
 
 #[get("/sessions")]
-async fn get_sessions(server: web::Data<WebSocketServer>) -> Result<HttpResponse, KromerError> {
+async fn get_sessions(
+    key: InternalKey,
+    server: web::Data<WebSocketServer>,
+) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::ReadOnly)?;
     let sessions = &server.inner.lock().await.sessions;
 
     Ok(HttpResponse::Ok().json(sessions))
 }
 
+#[derive(Debug, Deserialize)]
+struct KickSessionReq {
+    pub session: Uuid,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KickAddressReq {
+    pub address: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NoticeReq {
+    pub message: String,
+    /// Only send the notice to this session, everyone receives it when omitted.
+    pub session: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SetSubscriptionsReq {
+    pub session: Uuid,
+    pub subscriptions: Vec<WebSocketSubscriptionType>,
+}
+
+#[post("/session/kick")]
+async fn kick_session(
+    key: InternalKey,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    data: web::Json<KickSessionReq>,
+) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::WebsocketAdmin)?;
+    let data = data.into_inner();
+
+    if !server.kick_session(&data.session, data.reason.clone()).await {
+        return Err(KromerError::NotFound);
+    }
+    AuditEntry::new(&key, "websocket.kick_session")
+        .target(data.session)
+        .after(json!({ "reason": data.reason }))
+        .request(&request)
+        .write(&state.db)
+        .await;
+
+    Ok(HttpResponse::Ok().json(json!({ "ok": true, "kicked": 1 })))
+}
+
+#[post("/address/kick")]
+async fn kick_address(
+    key: InternalKey,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    data: web::Json<KickAddressReq>,
+) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::WebsocketAdmin)?;
+    let data = data.into_inner();
+
+    let kicked = server.kick_address(&data.address, data.reason.clone()).await;
+    AuditEntry::new(&key, "websocket.kick_address")
+        .target(&data.address)
+        .after(json!({ "kicked": kicked, "reason": data.reason }))
+        .request(&request)
+        .write(&state.db)
+        .await;
+
+    Ok(HttpResponse::Ok().json(json!({ "ok": true, "kicked": kicked })))
+}
+
+#[post("/notice")]
+async fn send_notice(
+    key: InternalKey,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    data: web::Json<NoticeReq>,
+) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::WebsocketAdmin)?;
+    let data = data.into_inner();
+    let notice = WebSocketMessage::new_notice(data.message.clone());
+
+    let delivered = match data.session {
+        Some(uuid) => {
+            if !server.send_to_session(&uuid, &notice).await {
+                return Err(KromerError::NotFound);
+            }
+
+            1
+        }
+        None => {
+            let msg = serde_json::to_string(&notice)
+                .map_err(|_| KromerError::Internal("Failed to serialize notice"))?;
+
+            server.broadcast(msg).await
+        }
+    };
+    let mut entry = AuditEntry::new(&key, "websocket.notice")
+        .after(json!({ "message": data.message, "delivered": delivered }))
+        .request(&request);
+    if let Some(session) = data.session {
+        entry = entry.target(session);
+    }
+    entry.write(&state.db).await;
+
+    Ok(HttpResponse::Ok().json(json!({ "ok": true, "delivered": delivered })))
+}
+
+#[post("/session/subscriptions")]
+async fn set_session_subscriptions(
+    key: InternalKey,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    data: web::Json<SetSubscriptionsReq>,
+) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::WebsocketAdmin)?;
+    let data = data.into_inner();
+
+    let subscriptions = server
+        .set_subscriptions(&data.session, data.subscriptions)
+        .await
+        .ok_or(KromerError::NotFound)?;
+    let subscriptions: Vec<String> = subscriptions
+        .into_iter()
+        .map(|x| x.into_string())
+        .collect();
+    AuditEntry::new(&key, "websocket.set_subscriptions")
+        .target(data.session)
+        .after(json!({ "subscriptions": subscriptions }))
+        .request(&request)
+        .write(&state.db)
+        .await;
+
+    Ok(HttpResponse::Ok().json(json!({ "ok": true, "subscriptions": subscriptions })))
+}
+
+#[get("/stats")]
+async fn get_stats(
+    key: InternalKey,
+    server: web::Data<WebSocketServer>,
+) -> Result<HttpResponse, KromerError> {
+    key.require(InternalScope::ReadOnly)?;
+    let stats = server.stats().await;
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/ws").service(get_session).service(get_sessions));
+    cfg.service(
+        web::scope("/ws")
+            .service(get_session)
+            .service(get_sessions)
+            .service(kick_session)
+            .service(kick_address)
+            .service(send_notice)
+            .service(set_session_subscriptions)
+            .service(get_stats),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use actix_web::dev::Service;
+    use actix_web::{web, App, HttpMessage};
+    use awc::ws;
+    use futures_util::StreamExt;
+    use surrealdb::{engine::any::Any, Surreal, Uuid};
+
+    use crate::guards::{InternalKey, InternalScope};
+    use crate::websockets::config::WebSocketConfig;
+    use crate::websockets::types::common::{SessionCredential, WebSocketTokenData};
+    use crate::websockets::WebSocketServer;
+    use crate::AppState;
+
+    type Connection = actix_codec::Framed<awc::BoxedSocket, ws::Codec>;
+
+    /// Serve the gateway next to the internal websocket routes, every internal request is made with an admin key.
+    fn start() -> (actix_test::TestServer, WebSocketServer) {
+        let server = WebSocketServer::with_config(WebSocketConfig::default());
+        let state = web::Data::new(AppState {
+            db: Arc::new(Surreal::<Any>::init()),
+            auth_tracker: Default::default(),
+        });
+
+        let ws_server = server.clone();
+        let srv = actix_test::start(move || {
+            App::new()
+                .app_data(state.clone())
+                .app_data(web::Data::new(ws_server.clone()))
+                .configure(crate::routes::krist::ws::config)
+                .service(
+                    web::scope("/internal")
+                        .wrap_fn(|req, srv| {
+                            req.extensions_mut().insert(InternalKey {
+                                name: "test".to_owned(),
+                                scopes: vec![InternalScope::WebsocketAdmin],
+                            });
+                            srv.call(req)
+                        })
+                        .configure(super::config),
+                )
+        });
+
+        (srv, server)
+    }
+
+    async fn connect(
+        srv: &actix_test::TestServer,
+        server: &WebSocketServer,
+        data: WebSocketTokenData,
+    ) -> (Uuid, Connection) {
+        let token = server.obtain_token(data).await;
+
+        let (_response, mut conn) = awc::Client::new()
+            .ws(srv.url(&format!("/ws/gateway/{token}")))
+            .connect()
+            .await
+            .expect("Failed to connect to the gateway");
+        let hello = next_frame(&mut conn).await;
+        assert!(matches!(hello, Some(ws::Frame::Text(_))), "Expected hello");
+
+        (token, conn)
+    }
+
+    /// Read the next text or close frame, skipping pings. `None` once the connection ended.
+    async fn next_frame(conn: &mut Connection) -> Option<ws::Frame> {
+        loop {
+            let frame = actix_web::rt::time::timeout(Duration::from_secs(5), conn.next())
+                .await
+                .expect("Timed out waiting for a frame")?
+                .ok()?;
+
+            match frame {
+                ws::Frame::Text(ref bytes) if bytes.windows(9).any(|x| x == b"keepalive") => (),
+                ws::Frame::Text(_) | ws::Frame::Close(_) => return Some(frame),
+                _ => (),
+            }
+        }
+    }
+
+    async fn expect_close(conn: &mut Connection) -> Option<String> {
+        match next_frame(conn).await {
+            Some(ws::Frame::Close(reason)) => reason.and_then(|reason| reason.description),
+            other => panic!("Expected the session to be closed, got {other:?}"),
+        }
+    }
+
+    fn authed_token(address: &str) -> WebSocketTokenData {
+        let credential = SessionCredential::derive(address, "test123");
+
+        WebSocketTokenData::new(address.to_owned(), Some(credential))
+    }
+
+    async fn stats(srv: &actix_test::TestServer) -> serde_json::Value {
+        let mut response = srv.get("/internal/ws/stats").send().await.unwrap();
+        assert!(response.status().is_success());
+
+        response.json().await.unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_kick_session() {
+        let (srv, server) = start();
+        let (uuid, mut kicked) = connect(&srv, &server, WebSocketTokenData::guest()).await;
+        let (_, mut other) = connect(&srv, &server, WebSocketTokenData::guest()).await;
+
+        let response = srv
+            .post("/internal/ws/session/kick")
+            .send_json(&serde_json::json!({ "session": uuid, "reason": "Bye" }))
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        assert_eq!(expect_close(&mut kicked).await.as_deref(), Some("Bye"));
+        assert_eq!(server.stats().await.sessions, 1);
+
+        // Kicking the same session twice finds nothing.
+        let response = srv
+            .post("/internal/ws/session/kick")
+            .send_json(&serde_json::json!({ "session": uuid }))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+
+        // The other session is still served.
+        let response = srv
+            .post("/internal/ws/notice")
+            .send_json(&serde_json::json!({ "message": "Still here" }))
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        assert!(matches!(
+            next_frame(&mut other).await,
+            Some(ws::Frame::Text(_))
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_kick_address() {
+        let (srv, server) = start();
+        let (_, mut first) = connect(&srv, &server, authed_token("kabcdefghi")).await;
+        let (_, mut second) = connect(&srv, &server, authed_token("kabcdefghi")).await;
+        let (_, _other) = connect(&srv, &server, authed_token("kzyxwvutsr")).await;
+
+        let mut response = srv
+            .post("/internal/ws/address/kick")
+            .send_json(&serde_json::json!({ "address": "kabcdefghi" }))
+            .await
+            .unwrap();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["kicked"], 2);
+
+        expect_close(&mut first).await;
+        expect_close(&mut second).await;
+
+        let stats = server.stats().await;
+        assert_eq!(stats.sessions, 1);
+        assert_eq!(stats.authed, 1);
+    }
+
+    #[actix_web::test]
+    async fn test_stats_match_sessions() {
+        let (srv, server) = start();
+        let (guest, _guest_conn) = connect(&srv, &server, WebSocketTokenData::guest()).await;
+        let (_, _authed_conn) = connect(&srv, &server, authed_token("kabcdefghi")).await;
+        let (_, _other_conn) = connect(&srv, &server, authed_token("kzyxwvutsr")).await;
+        server.obtain_token(WebSocketTokenData::guest()).await;
+
+        let response = srv
+            .post("/internal/ws/session/subscriptions")
+            .send_json(&serde_json::json!({ "session": guest, "subscriptions": ["transactions"] }))
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        let body = stats(&srv).await;
+        let expected = server.stats().await;
+        assert_eq!(body["sessions"], 3);
+        assert_eq!(body["guests"], 1);
+        assert_eq!(body["authed"], 2);
+        assert_eq!(body["pending_tokens"], 1);
+        assert_eq!(body["subscriptions"]["transactions"], 1);
+        assert_eq!(
+            body["subscriptions"],
+            serde_json::to_value(&expected.subscriptions).unwrap()
+        );
+    }
 }
@@ -0,0 +1,207 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use rust_decimal_macros::dec;
+
+use crate::audit::{Actor, AuditEntry, RequestInfo};
+use crate::auth::{self, signature::Signed};
+use crate::database::models::allowance::{AllowanceCreateData, Model as Allowance};
+use crate::database::models::wallet::Model as Wallet;
+use crate::errors::krist::{allowance::AllowanceError, generic::GenericError, KristError};
+use crate::models::allowances::{
+    AllowanceActionRequest, AllowanceJson, AllowanceListQuery, AllowanceListResponse,
+    AllowanceResponse, GrantAllowanceRequest,
+};
+use crate::models::tokens::TokenScope;
+use crate::routes::PaginationParams;
+use crate::AppState;
+
+/// Allowances can't start over more often than once a minute.
+const MIN_PERIOD: u64 = 60;
+
+/// Or less often than once a year.
+const MAX_PERIOD: u64 = 366 * 24 * 60 * 60;
+
+/// Maximum amount of recipients an allowance can be limited to.
+const MAX_RECIPIENTS: usize = 100;
+
+async fn get_allowance(state: &AppState, id: String) -> Result<Allowance, KristError> {
+    Allowance::get_partial(&state.db, &id)
+        .await?
+        .ok_or(KristError::Allowance(AllowanceError::NotFound(id)))
+}
+
+async fn get_wallet(state: &AppState, address: String) -> Result<Wallet, KristError> {
+    super::get_unlocked_wallet(&state.db, address).await
+}
+
+/// Allow another wallet to spend from yours, replacing the allowance it had before.
+#[post("")]
+async fn allowance_grant(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    details: Signed<GrantAllowanceRequest>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    if details.amount <= dec!(0) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "amount".to_string(),
+        )));
+    }
+    if details
+        .period
+        .is_some_and(|period| !(MIN_PERIOD..=MAX_PERIOD).contains(&period))
+    {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "period".to_string(),
+        )));
+    }
+    if details
+        .recipients
+        .as_ref()
+        .is_some_and(|recipients| recipients.is_empty() || recipients.len() > MAX_RECIPIENTS)
+    {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "recipients".to_string(),
+        )));
+    }
+
+    let auth = auth::authenticate(db, &req, details.password, TokenScope::Allowances).await?;
+    let owner = auth.wallet.address;
+
+    let spender = get_wallet(&state, details.spender).await?.address;
+    if spender == owner {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "spender".to_string(),
+        )));
+    }
+    let recipients = match details.recipients {
+        Some(addresses) => {
+            let mut recipients = Vec::with_capacity(addresses.len());
+            for address in addresses {
+                let address = get_wallet(&state, address).await?.address;
+                if !recipients.contains(&address) {
+                    recipients.push(address);
+                }
+            }
+            Some(recipients)
+        }
+        None => None,
+    };
+
+    let creation_data = AllowanceCreateData {
+        owner: owner.clone(),
+        spender,
+        amount: details.amount,
+        period: details.period,
+        recipients,
+    };
+    let allowance = Allowance::create(db, creation_data)
+        .await?
+        .ok_or(KristError::Custom("internal_server_error"))?;
+    let response: AllowanceJson = allowance.into();
+
+    AuditEntry::new(Actor::Wallet(owner), "allowance.grant")
+        .target(&response.id)
+        .after(&response)
+        .request(&request)
+        .write(db)
+        .await;
+
+    Ok(HttpResponse::Ok().json(AllowanceResponse {
+        ok: true,
+        allowance: response,
+    }))
+}
+
+/// Take the allowance away, done by its owner or given up by its spender.
+#[post("/{id}/revoke")]
+async fn allowance_revoke(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+    details: Signed<AllowanceActionRequest>,
+) -> Result<HttpResponse, KristError> {
+    let db = &state.db;
+    let allowance = get_allowance(&state, id.into_inner()).await?;
+
+    let auth = auth::authenticate(
+        db,
+        &req,
+        details.into_inner().password,
+        TokenScope::Allowances,
+    )
+    .await?;
+    let address = auth.wallet.address;
+    if address != allowance.owner && address != allowance.spender {
+        return Err(KristError::Allowance(AllowanceError::Forbidden));
+    }
+
+    let before: AllowanceJson = allowance.clone().into();
+    allowance
+        .revoke(db)
+        .await?
+        .ok_or_else(|| KristError::Allowance(AllowanceError::NotFound(before.id.clone())))?;
+
+    AuditEntry::new(Actor::Wallet(address), "allowance.revoke")
+        .target(&before.id)
+        .before(&before)
+        .request(&request)
+        .write(db)
+        .await;
+
+    Ok(HttpResponse::Ok().json(AllowanceResponse {
+        ok: true,
+        allowance: before,
+    }))
+}
+
+#[get("")]
+async fn allowance_list(
+    state: web::Data<AppState>,
+    query: web::Query<AllowanceListQuery>,
+) -> Result<HttpResponse, KristError> {
+    let query = query.into_inner();
+    let pagination = PaginationParams {
+        limit: query.limit,
+        offset: query.offset,
+    };
+
+    let (allowances, total) = Allowance::involving(&state.db, &query.address, &pagination).await?;
+    let allowances: Vec<AllowanceJson> = allowances
+        .into_iter()
+        .map(|allowance| allowance.into())
+        .collect();
+
+    Ok(HttpResponse::Ok().json(AllowanceListResponse {
+        ok: true,
+        count: allowances.len(),
+        total,
+        allowances,
+    }))
+}
+
+#[get("/{id}")]
+async fn allowance_get(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, KristError> {
+    let allowance = get_allowance(&state, id.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(AllowanceResponse {
+        ok: true,
+        allowance: allowance.into(),
+    }))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/allowances")
+            .service(allowance_grant)
+            .service(allowance_list)
+            .service(allowance_revoke)
+            .service(allowance_get),
+    );
+}
@@ -0,0 +1,260 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use chrono::{TimeDelta, Utc};
+use rust_decimal_macros::dec;
+
+use crate::audit::{Actor, AuditEntry, RequestInfo};
+use crate::auth::{self, signature::Signed};
+use crate::database::models::escrow::{EscrowCreateData, EscrowStatus, Model as Escrow};
+use crate::database::models::wallet::Model as Wallet;
+use crate::errors::krist::{
+    escrow::EscrowError, generic::GenericError, transaction::TransactionError, KristError,
+};
+use crate::escrows;
+use crate::models::escrows::{
+    CreateEscrowRequest, EscrowActionRequest, EscrowJson, EscrowListQuery, EscrowListResponse,
+    EscrowResponse,
+};
+use crate::models::tokens::TokenScope;
+use crate::routes::PaginationParams;
+use crate::websockets::WebSocketServer;
+use crate::AppState;
+
+/// Maximum length of an escrow's memo.
+const MAX_MEMO_LENGTH: usize = 255;
+
+/// Escrows can't hold funds for longer than 30 days.
+const MAX_DURATION: i64 = 30 * 24 * 60 * 60;
+
+async fn get_escrow(state: &AppState, id: String) -> Result<Escrow, KristError> {
+    Escrow::get_partial(&state.db, &id)
+        .await?
+        .ok_or(KristError::Escrow(EscrowError::NotFound(id)))
+}
+
+async fn get_wallet(state: &AppState, address: String) -> Result<Wallet, KristError> {
+    super::get_unlocked_wallet(&state.db, address).await
+}
+
+#[post("")]
+async fn escrow_create(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    details: Signed<CreateEscrowRequest>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    if details.amount <= dec!(0) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "amount".to_string(),
+        )));
+    }
+    if details
+        .memo
+        .as_ref()
+        .is_some_and(|memo| memo.chars().count() > MAX_MEMO_LENGTH)
+    {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "memo".to_string(),
+        )));
+    }
+    let duration = details.deadline - Utc::now();
+    if duration <= TimeDelta::zero() || duration.num_seconds() > MAX_DURATION {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "deadline".to_string(),
+        )));
+    }
+
+    let auth = auth::authenticate(db, &req, details.password, TokenScope::Transfer).await?;
+    let buyer = auth.wallet.address.clone();
+
+    let seller = get_wallet(&state, details.seller).await?.address;
+    if seller == buyer {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "seller".to_string(),
+        )));
+    }
+    let arbiter = match details.arbiter {
+        Some(arbiter) => Some(get_wallet(&state, arbiter).await?.address),
+        None => None,
+    };
+    if arbiter
+        .as_ref()
+        .is_some_and(|arbiter| *arbiter == buyer || *arbiter == seller)
+    {
+        return Err(KristError::Escrow(EscrowError::InvalidArbiter));
+    }
+
+    if auth.wallet.balance < details.amount {
+        return Err(KristError::Transaction(TransactionError::InsufficientFunds));
+    }
+
+    auth.reserve_spend(db, details.amount).await?;
+
+    let creation_data = EscrowCreateData {
+        buyer: buyer.clone(),
+        seller,
+        arbiter,
+        amount: details.amount,
+        memo: details.memo,
+        deadline: details.deadline.into(),
+    };
+    let update = match Escrow::create(db, creation_data).await {
+        Ok(Some(update)) => update,
+        Ok(None) => {
+            auth.release_spend(db, details.amount).await?;
+            return Err(KristError::Transaction(TransactionError::InsufficientFunds));
+        }
+        Err(err) => {
+            auth.release_spend(db, details.amount).await?;
+            return Err(err.into());
+        }
+    };
+    let escrow: EscrowJson = update.escrow.clone().into();
+
+    AuditEntry::new(Actor::Wallet(buyer), "escrow.create")
+        .target(&escrow.id)
+        .after(&escrow)
+        .request(&request)
+        .write(db)
+        .await;
+
+    escrows::notify(&server, update).await;
+
+    Ok(HttpResponse::Ok().json(EscrowResponse { ok: true, escrow }))
+}
+
+/// Pay the funds to the seller, done by the buyer or the arbiter.
+#[post("/{id}/release")]
+async fn escrow_release(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    id: web::Path<String>,
+    details: Signed<EscrowActionRequest>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    let auth = auth::authenticate(db, &req, details.password, TokenScope::Transfer).await?;
+    let address = auth.wallet.address;
+
+    let escrow = get_escrow(&state, id.into_inner()).await?;
+    if address != escrow.buyer && escrow.arbiter.as_ref() != Some(&address) {
+        return Err(KristError::Escrow(EscrowError::Forbidden(
+            "buyer or arbiter",
+        )));
+    }
+
+    let before: EscrowJson = escrow.clone().into();
+    let update = escrow
+        .release(db)
+        .await?
+        .ok_or_else(|| KristError::Escrow(EscrowError::AlreadySettled(before.id.clone())))?;
+    let escrow: EscrowJson = update.escrow.clone().into();
+
+    AuditEntry::new(Actor::Wallet(address), "escrow.release")
+        .target(&escrow.id)
+        .before(&before)
+        .after(&escrow)
+        .request(&request)
+        .write(db)
+        .await;
+
+    escrows::notify(&server, update).await;
+
+    Ok(HttpResponse::Ok().json(EscrowResponse { ok: true, escrow }))
+}
+
+/// Return the funds to the buyer, done by the arbiter or the seller.
+#[post("/{id}/refund")]
+async fn escrow_refund(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    id: web::Path<String>,
+    details: Signed<EscrowActionRequest>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    let auth = auth::authenticate(db, &req, details.password, TokenScope::Transfer).await?;
+    let address = auth.wallet.address;
+
+    let escrow = get_escrow(&state, id.into_inner()).await?;
+    if address != escrow.seller && escrow.arbiter.as_ref() != Some(&address) {
+        return Err(KristError::Escrow(EscrowError::Forbidden(
+            "seller or arbiter",
+        )));
+    }
+
+    let before: EscrowJson = escrow.clone().into();
+    let update = escrow
+        .refund(db, EscrowStatus::Refunded)
+        .await?
+        .ok_or_else(|| KristError::Escrow(EscrowError::AlreadySettled(before.id.clone())))?;
+    let escrow: EscrowJson = update.escrow.clone().into();
+
+    AuditEntry::new(Actor::Wallet(address), "escrow.refund")
+        .target(&escrow.id)
+        .before(&before)
+        .after(&escrow)
+        .request(&request)
+        .write(db)
+        .await;
+
+    escrows::notify(&server, update).await;
+
+    Ok(HttpResponse::Ok().json(EscrowResponse { ok: true, escrow }))
+}
+
+#[get("")]
+async fn escrow_list(
+    state: web::Data<AppState>,
+    query: web::Query<EscrowListQuery>,
+) -> Result<HttpResponse, KristError> {
+    let query = query.into_inner();
+    let pagination = PaginationParams {
+        limit: query.limit,
+        offset: query.offset,
+    };
+
+    let (escrows, total) =
+        Escrow::involving(&state.db, &query.address, query.status, &pagination).await?;
+    let escrows: Vec<EscrowJson> = escrows.into_iter().map(|escrow| escrow.into()).collect();
+
+    Ok(HttpResponse::Ok().json(EscrowListResponse {
+        ok: true,
+        count: escrows.len(),
+        total,
+        escrows,
+    }))
+}
+
+#[get("/{id}")]
+async fn escrow_get(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, KristError> {
+    let escrow = get_escrow(&state, id.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(EscrowResponse {
+        ok: true,
+        escrow: escrow.into(),
+    }))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/escrows")
+            .service(escrow_create)
+            .service(escrow_list)
+            .service(escrow_release)
+            .service(escrow_refund)
+            .service(escrow_get),
+    );
+}
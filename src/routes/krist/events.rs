@@ -0,0 +1,322 @@
+use std::convert::Infallible;
+use std::str::FromStr;
+use std::time::Duration;
+
+use actix_web::http::header;
+use actix_web::rt::time;
+use actix_web::web::Bytes;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use dashmap::DashSet;
+use futures_util::{stream, StreamExt};
+use surrealdb::Uuid;
+use tokio::sync::mpsc;
+
+use crate::errors::krist::{generic::GenericError, websockets::WebSocketError, KristError};
+use crate::utils::{net, validation_kromer::is_valid_kromer_address};
+use crate::websockets::events::{EventStreamClient, EVENT_STREAM_BUFFER};
+use crate::websockets::types::common::WebSocketSubscriptionType;
+use crate::websockets::WebSocketServer;
+
+/// How often a comment is sent to keep proxies from closing idle streams.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, serde::Deserialize)]
+struct EventStreamQuery {
+    /// Comma separated list of subscriptions, same as the websocket subscription levels.
+    subscribe: Option<String>,
+    /// Only stream events involving this address.
+    address: Option<String>,
+    /// Token obtained from `/ws/start`, required for the "own" subscriptions.
+    token: Option<String>,
+    /// Fallback for clients that can not set the `Last-Event-ID` header.
+    #[serde(rename = "lastEventId")]
+    last_event_id: Option<u64>,
+}
+
+#[get("/events")]
+#[tracing::instrument(name = "event_stream_route", level = "debug", skip_all)]
+async fn event_stream(
+    req: HttpRequest,
+    server: web::Data<WebSocketServer>,
+    query: web::Query<EventStreamQuery>,
+) -> Result<HttpResponse, KristError> {
+    let query = query.into_inner();
+
+    let subscriptions = match query.subscribe {
+        Some(subscribe) => subscribe
+            .split(',')
+            .map(|event| WebSocketSubscriptionType::from_str(event.trim()))
+            .collect::<Result<DashSet<_>, _>>()
+            .map_err(|_| GenericError::InvalidParameter("subscribe".to_string()))?,
+        None => DashSet::from_iter([
+            WebSocketSubscriptionType::OwnTransactions,
+            WebSocketSubscriptionType::Blocks,
+        ]),
+    };
+
+    if let Some(address) = &query.address {
+        if !is_valid_kromer_address(address) {
+            return Err(KristError::Generic(GenericError::InvalidParameter(
+                "address".to_string(),
+            )));
+        }
+    }
+
+    let address = match query.token {
+        Some(token) => {
+            let uuid = Uuid::from_str(&token)
+                .map_err(|_| KristError::WebSocket(WebSocketError::InvalidWebsocketToken))?;
+            let data = server
+                .use_token(&uuid)
+                .await
+                .map_err(|_| KristError::WebSocket(WebSocketError::InvalidWebsocketToken))?;
+
+            data.credential.map(|_| data.address)
+        }
+        None => None,
+    };
+
+    let last_event_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .or(query.last_event_id);
+
+    let (sender, receiver) = mpsc::channel(EVENT_STREAM_BUFFER);
+    let client = EventStreamClient {
+        address,
+        filter: query.address,
+        subscriptions,
+        ip: net::client_ip(&req, server.config.trust_forwarded_for),
+        sender: sender.clone(),
+    };
+
+    let uuid = Uuid::new_v4();
+    let missed = server
+        .insert_event_stream(uuid, client, last_event_id)
+        .await
+        .map_err(|_| KristError::WebSocket(WebSocketError::TooManySessions))?;
+
+    // Keepalive handling, this also notices when the client went away or was dropped for falling behind.
+    let server = server.into_inner();
+    actix_web::rt::spawn(async move {
+        let mut interval = time::interval(KEEPALIVE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            if !server.has_event_stream(&uuid).await {
+                break;
+            }
+
+            if sender
+                .send(Bytes::from_static(b": keepalive\n\n"))
+                .await
+                .is_err()
+            {
+                server.cleanup_event_stream(&uuid).await;
+                break;
+            }
+        }
+    });
+
+    let missed = stream::iter(missed.into_iter().map(|event| event.to_frame()));
+    let live = stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|frame| (frame, receiver))
+    });
+    let body = missed.chain(live).map(Ok::<_, Infallible>);
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(body))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(event_stream);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use actix_web::{web, App};
+    use futures_util::StreamExt;
+    use rust_decimal_macros::dec;
+    use surrealdb::{engine::any::Any, Surreal};
+
+    use crate::models::transactions::{TransactionJson, TransactionType};
+    use crate::models::websockets::{WebSocketEvent, WebSocketMessage};
+    use crate::websockets::config::WebSocketConfig;
+    use crate::websockets::types::common::{SessionCredential, WebSocketTokenData};
+    use crate::websockets::WebSocketServer;
+    use crate::AppState;
+
+    fn start() -> (actix_test::TestServer, WebSocketServer) {
+        let server = WebSocketServer::with_config(WebSocketConfig::default());
+        let state = web::Data::new(AppState {
+            db: Arc::new(Surreal::<Any>::init()),
+            auth_tracker: Default::default(),
+        });
+
+        let ws_server = server.clone();
+        let srv = actix_test::start(move || {
+            App::new()
+                .app_data(state.clone())
+                .app_data(web::Data::new(ws_server.clone()))
+                .configure(super::config)
+        });
+
+        (srv, server)
+    }
+
+    fn transaction(from: &str, to: &str) -> WebSocketMessage {
+        WebSocketMessage::new_event(WebSocketEvent::Transaction {
+            transaction: TransactionJson {
+                id: None,
+                from: from.to_owned(),
+                to: to.to_owned(),
+                value: dec!(1),
+                time: "2025-01-01T00:00:00.000Z".to_owned(),
+                name: None,
+                metadata: None,
+                sent_metaname: None,
+                sent_name: None,
+                transaction_type: TransactionType::Transfer,
+                batch: None,
+                debt: None,
+            },
+        })
+    }
+
+    /// Read event frames until `count` events were received, skipping keepalive comments.
+    async fn read_events<S, E>(body: &mut S, count: usize) -> Vec<(u64, serde_json::Value)>
+    where
+        S: futures_util::Stream<Item = Result<web::Bytes, E>> + Unpin,
+        E: std::fmt::Debug,
+    {
+        let mut buffer = String::new();
+        let mut events = Vec::new();
+
+        while events.len() < count {
+            let chunk = actix_web::rt::time::timeout(Duration::from_secs(5), body.next())
+                .await
+                .expect("Timed out waiting for an event")
+                .expect("Stream ended")
+                .expect("Failed to read the stream");
+            buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+
+            while let Some(end) = buffer.find("\n\n") {
+                let frame: String = buffer.drain(..end + 2).collect();
+                if frame.starts_with(':') {
+                    continue;
+                }
+
+                let mut lines = frame.lines();
+                let id = lines.next().unwrap().strip_prefix("id: ").unwrap();
+                let data = lines.next().unwrap().strip_prefix("data: ").unwrap();
+
+                events.push((id.parse().unwrap(), serde_json::from_str(data).unwrap()));
+            }
+        }
+
+        events
+    }
+
+    #[actix_web::test]
+    async fn test_stream_filters_events() {
+        let (srv, server) = start();
+
+        let mut response = srv
+            .get("/events?subscribe=transactions&address=kaaaaaaaaa")
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        // Wait for the stream to be registered before broadcasting.
+        while server.stats().await.event_streams == 0 {
+            actix_web::rt::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        server.broadcast_event(transaction("kbbbbbbbbb", "kccccccccc")).await;
+        server.broadcast_event(transaction("kbbbbbbbbb", "kaaaaaaaaa")).await;
+
+        let events = read_events(&mut response, 1).await;
+        assert_eq!(events[0].0, 2);
+        assert_eq!(events[0].1["type"], "event");
+        assert_eq!(events[0].1["event"], "transaction");
+        assert_eq!(events[0].1["transaction"]["to"], "kaaaaaaaaa");
+    }
+
+    #[actix_web::test]
+    async fn test_stream_resumes_from_last_event_id() {
+        let (srv, server) = start();
+
+        for _ in 0..4 {
+            server.broadcast_event(transaction("kbbbbbbbbb", "kccccccccc")).await;
+        }
+
+        let mut response = srv
+            .get("/events?subscribe=transactions")
+            .insert_header(("Last-Event-ID", "2"))
+            .send()
+            .await
+            .unwrap();
+
+        let events = read_events(&mut response, 2).await;
+        let ids: Vec<u64> = events.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![3, 4]);
+    }
+
+    #[actix_web::test]
+    async fn test_own_transactions_require_token() {
+        let (srv, server) = start();
+
+        for _ in 0..2 {
+            server.broadcast_event(transaction("kbbbbbbbbb", "kaaaaaaaaa")).await;
+        }
+
+        // Guests never receive their "own" transactions, even when filtering on an address.
+        let mut guest = srv
+            .get("/events?subscribe=ownTransactions&address=kaaaaaaaaa&lastEventId=0")
+            .send()
+            .await
+            .unwrap();
+
+        let credential = SessionCredential::derive("kaaaaaaaaa", "test123");
+        let token = server
+            .obtain_token(WebSocketTokenData::new("kaaaaaaaaa".to_owned(), Some(credential)))
+            .await;
+        let mut authed = srv
+            .get(format!(
+                "/events?subscribe=ownTransactions&token={token}&lastEventId=0"
+            ))
+            .send()
+            .await
+            .unwrap();
+
+        let events = read_events(&mut authed, 2).await;
+        assert_eq!(events.len(), 2);
+
+        server.broadcast_event(transaction("kbbbbbbbbb", "kaaaaaaaaa")).await;
+        let events = read_events(&mut authed, 1).await;
+        assert_eq!(events[0].0, 3);
+
+        let guest_events =
+            actix_web::rt::time::timeout(Duration::from_millis(200), read_events(&mut guest, 1))
+                .await;
+        assert!(guest_events.is_err());
+
+        // Tokens can only be used once.
+        let reused = srv
+            .get(format!("/events?token={token}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(reused.status(), 403);
+    }
+}
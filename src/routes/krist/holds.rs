@@ -0,0 +1,273 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use chrono::{TimeDelta, Utc};
+use rust_decimal_macros::dec;
+
+use crate::audit::{Actor, AuditEntry, RequestInfo};
+use crate::auth::{self, signature::Signed};
+use crate::database::models::hold::{CaptureOutcome, HoldCreateData, HoldStatus, Model as Hold};
+use crate::errors::krist::{
+    generic::GenericError, hold::HoldError, transaction::TransactionError, KristError,
+};
+use crate::holds;
+use crate::models::holds::{
+    CaptureHoldRequest, CaptureHoldResponse, CreateHoldRequest, HoldActionRequest, HoldJson,
+    HoldListQuery, HoldListResponse, HoldResponse,
+};
+use crate::models::tokens::TokenScope;
+use crate::models::transactions::TransactionJson;
+use crate::routes::PaginationParams;
+use crate::websockets::WebSocketServer;
+use crate::AppState;
+
+/// Maximum length of a hold's memo.
+const MAX_MEMO_LENGTH: usize = 255;
+
+/// Holds expire after an hour unless asked otherwise.
+const DEFAULT_DURATION: u64 = 60 * 60;
+
+/// Holds can't reserve funds for longer than 7 days.
+const MAX_DURATION: u64 = 7 * 24 * 60 * 60;
+
+async fn get_hold(state: &AppState, id: String) -> Result<Hold, KristError> {
+    Hold::get_partial(&state.db, &id)
+        .await?
+        .ok_or(KristError::Hold(HoldError::NotFound(id)))
+}
+
+/// Authenticate the merchant of a hold.
+async fn authenticate_merchant(
+    req: &HttpRequest,
+    state: &AppState,
+    password: Option<String>,
+    hold: &Hold,
+) -> Result<String, KristError> {
+    let auth = auth::authenticate(&state.db, req, password, TokenScope::Holds).await?;
+    if auth.wallet.address != hold.merchant {
+        return Err(KristError::Hold(HoldError::Forbidden));
+    }
+
+    Ok(auth.wallet.address)
+}
+
+/// Authorize a merchant to capture up to an amount, done by the payer.
+#[post("")]
+async fn hold_create(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    details: Signed<CreateHoldRequest>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    if details.amount <= dec!(0) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "amount".to_string(),
+        )));
+    }
+    if details
+        .memo
+        .as_ref()
+        .is_some_and(|memo| memo.chars().count() > MAX_MEMO_LENGTH)
+    {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "memo".to_string(),
+        )));
+    }
+    let expires_in = details.expires_in.unwrap_or(DEFAULT_DURATION);
+    if !(1..=MAX_DURATION).contains(&expires_in) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "expires_in".to_string(),
+        )));
+    }
+
+    let auth = auth::authenticate(db, &req, details.password, TokenScope::Transfer).await?;
+    let payer = auth.wallet.address.clone();
+
+    let merchant = super::get_unlocked_wallet(db, details.merchant)
+        .await?
+        .address;
+    if merchant == payer {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "merchant".to_string(),
+        )));
+    }
+
+    if auth.wallet.balance < details.amount {
+        return Err(KristError::Transaction(TransactionError::InsufficientFunds));
+    }
+
+    auth.reserve_spend(db, details.amount).await?;
+
+    let expires_at = Utc::now() + TimeDelta::seconds(expires_in as i64);
+    let creation_data = HoldCreateData {
+        payer: payer.clone(),
+        merchant,
+        amount: details.amount,
+        memo: details.memo,
+        expires_at: expires_at.into(),
+    };
+    let hold = match Hold::create(db, creation_data).await {
+        Ok(Some(hold)) => hold,
+        Ok(None) => {
+            auth.release_spend(db, details.amount).await?;
+            return Err(KristError::Transaction(TransactionError::InsufficientFunds));
+        }
+        Err(err) => {
+            auth.release_spend(db, details.amount).await?;
+            return Err(err.into());
+        }
+    };
+    let response: HoldJson = hold.clone().into();
+
+    AuditEntry::new(Actor::Wallet(payer), "hold.create")
+        .target(&response.id)
+        .after(&response)
+        .request(&request)
+        .write(db)
+        .await;
+
+    holds::notify(&server, hold).await;
+
+    Ok(HttpResponse::Ok().json(HoldResponse {
+        ok: true,
+        hold: response,
+    }))
+}
+
+/// Transfer some or all of the held funds to the merchant, done by the merchant.
+#[post("/{id}/capture")]
+async fn hold_capture(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    id: web::Path<String>,
+    details: Signed<CaptureHoldRequest>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    if details.amount.is_some_and(|amount| amount <= dec!(0)) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "amount".to_string(),
+        )));
+    }
+
+    let hold = get_hold(&state, id.into_inner()).await?;
+    let address = authenticate_merchant(&req, &state, details.password, &hold).await?;
+
+    let before: HoldJson = hold.clone().into();
+    let metadata = hold.capture_meta(details.metadata.as_deref());
+    let (hold, transaction) = match hold.capture(db, details.amount, metadata).await? {
+        CaptureOutcome::Captured { hold, transaction } => (*hold, *transaction),
+        CaptureOutcome::ExceedsRemaining { remaining } => {
+            return Err(KristError::Hold(HoldError::ExceedsRemaining(remaining)));
+        }
+        CaptureOutcome::NotAuthorized => {
+            return Err(KristError::Hold(HoldError::NotAuthorized(before.id)));
+        }
+    };
+    let response: HoldJson = hold.clone().into();
+    let transaction_json: TransactionJson = transaction.clone().into();
+
+    AuditEntry::new(Actor::Wallet(address), "hold.capture")
+        .target(&response.id)
+        .before(&before)
+        .after(&transaction_json)
+        .request(&request)
+        .write(db)
+        .await;
+
+    holds::announce_capture(db, &server, hold, &transaction).await;
+
+    Ok(HttpResponse::Ok().json(CaptureHoldResponse {
+        ok: true,
+        hold: response,
+        transaction: transaction_json,
+    }))
+}
+
+/// Give what was not captured back to the payer, done by the merchant.
+#[post("/{id}/void")]
+async fn hold_void(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    id: web::Path<String>,
+    details: Signed<HoldActionRequest>,
+) -> Result<HttpResponse, KristError> {
+    let hold = get_hold(&state, id.into_inner()).await?;
+    let address = authenticate_merchant(&req, &state, details.into_inner().password, &hold).await?;
+
+    let before: HoldJson = hold.clone().into();
+    let hold = hold
+        .release(&state.db, HoldStatus::Voided)
+        .await?
+        .ok_or_else(|| KristError::Hold(HoldError::NotAuthorized(before.id.clone())))?;
+    let response: HoldJson = hold.clone().into();
+
+    AuditEntry::new(Actor::Wallet(address), "hold.void")
+        .target(&response.id)
+        .before(&before)
+        .after(&response)
+        .request(&request)
+        .write(&state.db)
+        .await;
+
+    holds::notify(&server, hold).await;
+
+    Ok(HttpResponse::Ok().json(HoldResponse {
+        ok: true,
+        hold: response,
+    }))
+}
+
+#[get("")]
+async fn hold_list(
+    state: web::Data<AppState>,
+    query: web::Query<HoldListQuery>,
+) -> Result<HttpResponse, KristError> {
+    let query = query.into_inner();
+    let pagination = PaginationParams {
+        limit: query.limit,
+        offset: query.offset,
+    };
+
+    let (holds, total) =
+        Hold::involving(&state.db, &query.address, query.status, &pagination).await?;
+    let holds: Vec<HoldJson> = holds.into_iter().map(|hold| hold.into()).collect();
+
+    Ok(HttpResponse::Ok().json(HoldListResponse {
+        ok: true,
+        count: holds.len(),
+        total,
+        holds,
+    }))
+}
+
+#[get("/{id}")]
+async fn hold_get(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, KristError> {
+    let hold = get_hold(&state, id.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(HoldResponse {
+        ok: true,
+        hold: hold.into(),
+    }))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/holds")
+            .service(hold_create)
+            .service(hold_list)
+            .service(hold_capture)
+            .service(hold_void)
+            .service(hold_get),
+    );
+}
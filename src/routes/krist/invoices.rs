@@ -0,0 +1,167 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use chrono::{TimeDelta, Utc};
+use rust_decimal_macros::dec;
+
+use crate::audit::{Actor, AuditEntry, RequestInfo};
+use crate::auth::{self, signature::Signed};
+use crate::database::models::invoice::{InvoiceCreateData, Model as Invoice};
+use crate::errors::krist::{generic::GenericError, invoice::InvoiceError, KristError};
+use crate::models::invoices::{
+    CreateInvoiceRequest, InvoiceJson, InvoiceListQuery, InvoiceListResponse, InvoiceResponse,
+};
+use crate::models::tokens::TokenScope;
+use crate::routes::PaginationParams;
+use crate::AppState;
+
+/// Maximum length of an invoice's memo.
+const MAX_MEMO_LENGTH: usize = 255;
+
+/// Seconds until an invoice expires when no expiry is given, a day.
+const DEFAULT_EXPIRY: i64 = 24 * 60 * 60;
+
+/// Invoices can't be open for longer than 30 days.
+const MAX_EXPIRY: i64 = 30 * 24 * 60 * 60;
+
+/// Whether a metaname can be used in `<metaname>@<name>.kst`.
+fn is_valid_metaname(metaname: &str) -> bool {
+    (1..=32).contains(&metaname.len())
+        && metaname
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+#[post("")]
+async fn invoice_create(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    details: Signed<CreateInvoiceRequest>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    if details.amount <= dec!(0) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "amount".to_string(),
+        )));
+    }
+    if details
+        .memo
+        .as_ref()
+        .is_some_and(|memo| memo.chars().count() > MAX_MEMO_LENGTH)
+    {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "memo".to_string(),
+        )));
+    }
+    if details
+        .metaname
+        .as_deref()
+        .is_some_and(|metaname| !is_valid_metaname(metaname))
+    {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "metaname".to_string(),
+        )));
+    }
+
+    let expires_at = TimeDelta::try_seconds(details.expires_in.unwrap_or(DEFAULT_EXPIRY))
+        .filter(|delta| *delta > TimeDelta::zero() && delta.num_seconds() <= MAX_EXPIRY)
+        .and_then(|delta| Utc::now().checked_add_signed(delta))
+        .ok_or_else(|| {
+            KristError::Generic(GenericError::InvalidParameter("expires_in".to_string()))
+        })?;
+
+    let auth = auth::authenticate(db, &req, details.password, TokenScope::Invoices).await?;
+    let merchant = auth.wallet.address;
+
+    if let Some(metaname) = &details.metaname {
+        if Invoice::metaname_used(db, &merchant, metaname).await? {
+            return Err(KristError::Invoice(InvoiceError::MetanameUsed(
+                metaname.clone(),
+            )));
+        }
+    }
+
+    let creation_data = InvoiceCreateData {
+        merchant: merchant.clone(),
+        amount: details.amount,
+        memo: details.memo,
+        metaname: details.metaname,
+        expires_at: expires_at.into(),
+    };
+    let invoice = Invoice::create(db, creation_data)
+        .await?
+        .ok_or(KristError::Custom("internal_server_error"))?;
+    let invoice: InvoiceJson = invoice.into();
+
+    AuditEntry::new(Actor::Wallet(merchant), "invoice.create")
+        .target(&invoice.id)
+        .after(&invoice)
+        .request(&request)
+        .write(db)
+        .await;
+
+    Ok(HttpResponse::Ok().json(InvoiceResponse { ok: true, invoice }))
+}
+
+#[get("")]
+async fn invoice_list(
+    state: web::Data<AppState>,
+    query: web::Query<InvoiceListQuery>,
+) -> Result<HttpResponse, KristError> {
+    let query = query.into_inner();
+    let pagination = PaginationParams {
+        limit: query.limit,
+        offset: query.offset,
+    };
+
+    let (invoices, total) =
+        Invoice::by_merchant(&state.db, &query.merchant, query.status, &pagination).await?;
+    let invoices: Vec<InvoiceJson> = invoices.into_iter().map(|invoice| invoice.into()).collect();
+
+    Ok(HttpResponse::Ok().json(InvoiceListResponse {
+        ok: true,
+        count: invoices.len(),
+        total,
+        invoices,
+    }))
+}
+
+#[get("/{id}")]
+async fn invoice_get(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, KristError> {
+    let id = id.into_inner();
+
+    let invoice = Invoice::get_partial(&state.db, &id)
+        .await?
+        .ok_or(KristError::Invoice(InvoiceError::NotFound(id)))?;
+
+    Ok(HttpResponse::Ok().json(InvoiceResponse {
+        ok: true,
+        invoice: invoice.into(),
+    }))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/invoices")
+            .service(invoice_create)
+            .service(invoice_list)
+            .service(invoice_get),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metaname_validation() {
+        assert!(is_valid_metaname("order-42"));
+        assert!(!is_valid_metaname(""));
+        assert!(!is_valid_metaname("Order"));
+        assert!(!is_valid_metaname("a@b"));
+    }
+}
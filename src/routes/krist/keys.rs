@@ -0,0 +1,142 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+
+use crate::audit::{Actor, AuditEntry, RequestInfo};
+use crate::auth::{self, signature::parse_public_key};
+use crate::database::models::auth_nonce::Model as AuthNonce;
+use crate::database::models::wallet::Model as Wallet;
+use crate::errors::krist::{address::AddressError, generic::GenericError, KristError};
+use crate::models::auth::{
+    NonceRequest, NonceResponse, PublicKeyResponse, RegisterKeyRequest, RemoveKeyRequest,
+};
+use crate::AppState;
+
+#[post("")]
+async fn key_register(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    details: web::Json<RegisterKeyRequest>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    let public_key = details.public_key.trim().to_lowercase();
+    if parse_public_key(&public_key).is_none() {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "public_key".to_string(),
+        )));
+    }
+
+    let owner = auth::verify_request_key(&req, &details.private_key, None).await?;
+
+    let wallet = Wallet::set_public_key(db, &owner.address, Some(public_key))
+        .await?
+        .ok_or_else(|| KristError::Address(AddressError::NotFound(owner.address.clone())))?;
+
+    AuditEntry::new(
+        Actor::Wallet(owner.address.clone()),
+        "wallet.set_public_key",
+    )
+    .target(&owner.address)
+    .before(serde_json::json!({ "public_key": owner.public_key }))
+    .after(serde_json::json!({ "public_key": wallet.public_key }))
+    .request(&request)
+    .write(db)
+    .await;
+
+    Ok(HttpResponse::Ok().json(PublicKeyResponse {
+        ok: true,
+        address: wallet.address,
+        public_key: wallet.public_key,
+    }))
+}
+
+#[post("/remove")]
+async fn key_remove(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    details: web::Json<RemoveKeyRequest>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    let owner = auth::verify_request_key(&req, &details.private_key, None).await?;
+
+    let wallet = Wallet::set_public_key(db, &owner.address, None)
+        .await?
+        .ok_or_else(|| KristError::Address(AddressError::NotFound(owner.address.clone())))?;
+
+    AuditEntry::new(
+        Actor::Wallet(owner.address.clone()),
+        "wallet.set_public_key",
+    )
+    .target(&owner.address)
+    .before(serde_json::json!({ "public_key": owner.public_key }))
+    .after(serde_json::json!({ "public_key": wallet.public_key }))
+    .request(&request)
+    .write(db)
+    .await;
+
+    Ok(HttpResponse::Ok().json(PublicKeyResponse {
+        ok: true,
+        address: wallet.address,
+        public_key: wallet.public_key,
+    }))
+}
+
+#[post("/nonce")]
+async fn key_nonce(
+    state: web::Data<AppState>,
+    details: web::Json<NonceRequest>,
+) -> Result<HttpResponse, KristError> {
+    let address = details.into_inner().address;
+    let db = &state.db;
+
+    let wallet = Wallet::get_by_address(db, address.clone())
+        .await?
+        .ok_or_else(|| KristError::Address(AddressError::NotFound(address.clone())))?;
+    if wallet.public_key.is_none() {
+        return Err(KristError::Address(AddressError::NoPublicKey(address)));
+    }
+
+    let nonce = AuthNonce::issue(db, &wallet.address)
+        .await?
+        .ok_or(KristError::Custom("internal_server_error"))?;
+
+    Ok(HttpResponse::Ok().json(NonceResponse {
+        ok: true,
+        address: nonce.address,
+        nonce: nonce.nonce,
+        expires: nonce.expires_at.to_rfc3339(),
+    }))
+}
+
+#[get("/{address}")]
+async fn key_get(
+    state: web::Data<AppState>,
+    address: web::Path<String>,
+) -> Result<HttpResponse, KristError> {
+    let address = address.into_inner();
+    let db = &state.db;
+
+    let wallet = Wallet::get_by_address(db, address.clone())
+        .await?
+        .ok_or_else(|| KristError::Address(AddressError::NotFound(address)))?;
+
+    Ok(HttpResponse::Ok().json(PublicKeyResponse {
+        ok: true,
+        address: wallet.address,
+        public_key: wallet.public_key,
+    }))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/keys")
+            .service(key_nonce)
+            .service(key_remove)
+            .service(key_get)
+            .service(key_register),
+    );
+}
@@ -0,0 +1,383 @@
+//! Krist's pre-REST query-string API, e.g. `/api/krist/?getbalance=kaaaaaaaaa`.
+//!
+//! Old ComputerCraft programs parse these plain-text responses directly, so their formats must not change:
+//!
+//! - `getbalance=<address>`: the balance, `0` for unknown addresses
+//! - `getsupply`, `getmoneysupply`: the money supply
+//! - `v2=<privatekey>`: the v2 address of the key
+//! - `listnames=<address>`, `dumpnames`: names, each followed by `;`
+//! - `getnames=<address>`: amount of names owned by the address
+//! - `a=<name>`: the name's data, empty when it has none
+//! - `name_check=<name>`: `true` when the name is available, `false` otherwise
+//! - `namecost`, `getnamecost`: the cost of a name
+//! - `namebonus`: amount of unpaid names
+//! - `pushtx2&q=<to>&pkey=<privatekey>&amt=<amount>[&com=<metadata>]`: `Success`, `Error1` (insufficient
+//!   funds), `Error3` (invalid amount), `Error4` (invalid recipient) or `Access denied`
+//!
+//! The router is only mounted when `KRIST_LEGACY_API=true`.
+use std::collections::HashMap;
+use std::env;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use surrealdb::{engine::any::Any, Surreal};
+
+use crate::audit::{Actor, AuditEntry, RequestInfo};
+use crate::auth;
+use crate::database::models::name::Model as Name;
+use crate::database::models::transaction::{Model as Transaction, TransactionCreateData};
+use crate::database::models::wallet::Model as Wallet;
+use crate::errors::krist::{address::AddressError, KristError};
+use crate::models::motd::MINING_CONSTANTS;
+use crate::models::tokens::TokenScope;
+use crate::models::transactions::{TransactionJson, TransactionType};
+use crate::models::websockets::{WebSocketEvent, WebSocketMessage};
+use crate::routes::{not_found, PaginationParams};
+use crate::utils::{crypto, validation_kromer};
+use crate::websockets::WebSocketServer;
+use crate::{invoices, AppState};
+
+/// Page size used when a verb needs every row.
+const PAGE_SIZE: u64 = 1000;
+
+fn text<S: Into<String>>(body: S) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body(body.into())
+}
+
+/// Krist balances were integers, keep trailing zeroes out of the way of `tonumber`.
+fn format_amount(amount: Decimal) -> String {
+    amount.normalize().to_string()
+}
+
+/// Names in the legacy list format, every name followed by a `;`.
+fn format_names(names: &[Name]) -> String {
+    names.iter().map(|name| format!("{};", name.name)).collect()
+}
+
+async fn address_names(db: &Surreal<Any>, address: &str) -> Result<Vec<Name>, surrealdb::Error> {
+    let mut names = Vec::new();
+
+    loop {
+        let pagination = PaginationParams {
+            limit: Some(PAGE_SIZE),
+            offset: Some(names.len() as u64),
+        };
+        let page = Wallet::names(db, address, &pagination).await?;
+        let done = (page.len() as u64) < PAGE_SIZE;
+        names.extend(page);
+
+        if done {
+            return Ok(names);
+        }
+    }
+}
+
+async fn all_names(db: &Surreal<Any>) -> Result<Vec<Name>, surrealdb::Error> {
+    let mut names = Vec::new();
+
+    loop {
+        let pagination = PaginationParams {
+            limit: Some(PAGE_SIZE),
+            offset: Some(names.len() as u64),
+        };
+        let page = Name::all(db, &pagination).await?;
+        let done = (page.len() as u64) < PAGE_SIZE;
+        names.extend(page);
+
+        if done {
+            return Ok(names);
+        }
+    }
+}
+
+async fn push_transaction(
+    req: &HttpRequest,
+    request: &RequestInfo,
+    db: &Surreal<Any>,
+    server: &WebSocketServer,
+    query: &HashMap<String, String>,
+) -> Result<HttpResponse, KristError> {
+    let amount = query
+        .get("amt")
+        .and_then(|amount| amount.parse::<Decimal>().ok())
+        .filter(|amount| *amount > dec!(0));
+    let Some(amount) = amount else {
+        return Ok(text("Error3"));
+    };
+
+    let to = query
+        .get("q")
+        .filter(|to| validation_kromer::is_valid_kromer_address(to));
+    let Some(to) = to else {
+        return Ok(text("Error4"));
+    };
+
+    let auth =
+        match auth::authenticate(db, req, query.get("pkey").cloned(), TokenScope::Transfer).await {
+            Ok(auth) => auth,
+            Err(
+                KristError::Address(AddressError::AuthFailed | AddressError::TooManyAttempts(_))
+                | KristError::Generic(_),
+            ) => {
+                return Ok(text("Access denied"));
+            }
+            Err(err) => return Err(err),
+        };
+
+    let recipient = Wallet::get_by_address(db, to.clone()).await?;
+    let Some(recipient) = recipient.filter(|wallet| !wallet.locked) else {
+        return Ok(text("Error4"));
+    };
+
+    if auth.wallet.balance < amount {
+        return Ok(text("Error1"));
+    }
+
+    auth.reserve_spend(db, amount).await?;
+
+    let creation_data = TransactionCreateData {
+        from: auth.wallet.address.clone(),
+        to: recipient.address,
+        amount,
+        metadata: query.get("com").cloned(),
+        transaction_type: TransactionType::Transfer,
+    };
+    let response: Result<Vec<Transaction>, _> =
+        db.insert("transaction").content(creation_data).await;
+    let response = match response {
+        Ok(response) => response,
+        Err(err) => {
+            auth.release_spend(db, amount).await?;
+            return Err(err.into());
+        }
+    };
+
+    if let Some(model) = response.into_iter().next() {
+        let transaction: TransactionJson = model.clone().into();
+        AuditEntry::new(Actor::Wallet(auth.wallet.address), "transaction.create")
+            .target(&transaction.to)
+            .after(&transaction)
+            .request(request)
+            .write(db)
+            .await;
+
+        invoices::link_payment(db, server, &model).await;
+        let event = WebSocketMessage::new_event(WebSocketEvent::Transaction { transaction });
+        server.broadcast_event(event).await;
+    }
+
+    Ok(text("Success"))
+}
+
+/// Answer the first legacy verb found in the query, `None` if there is none.
+async fn dispatch(
+    req: &HttpRequest,
+    request: &RequestInfo,
+    db: &Surreal<Any>,
+    server: &WebSocketServer,
+    query: &HashMap<String, String>,
+) -> Result<Option<HttpResponse>, KristError> {
+    if let Some(address) = query.get("getbalance") {
+        let wallet = Wallet::get_by_address(db, address.clone()).await?;
+        let balance = wallet.map(|wallet| wallet.balance).unwrap_or_default();
+
+        return Ok(Some(text(format_amount(balance))));
+    }
+
+    if query.contains_key("getsupply") || query.contains_key("getmoneysupply") {
+        let supply = Wallet::supply(db).await?;
+
+        return Ok(Some(text(format_amount(supply))));
+    }
+
+    if let Some(key) = query.get("v2") {
+        return Ok(Some(text(crypto::make_v2_address(key, "k"))));
+    }
+
+    if let Some(address) = query.get("listnames") {
+        let names = address_names(db, address).await?;
+
+        return Ok(Some(text(format_names(&names))));
+    }
+
+    if query.contains_key("dumpnames") {
+        let names = all_names(db).await?;
+
+        return Ok(Some(text(format_names(&names))));
+    }
+
+    if let Some(address) = query.get("getnames") {
+        let names = address_names(db, address).await?;
+
+        return Ok(Some(text(names.len().to_string())));
+    }
+
+    if let Some(name) = query.get("a") {
+        let name = Name::get_by_name(db, name.trim().to_lowercase()).await?;
+
+        return Ok(Some(text(name.and_then(|name| name.a).unwrap_or_default())));
+    }
+
+    if let Some(name) = query.get("name_check") {
+        if !validation_kromer::is_valid_name(name, false) {
+            return Ok(Some(text("false")));
+        }
+        let name = Name::get_by_name(db, name.trim().to_lowercase()).await?;
+
+        return Ok(Some(text(name.is_none().to_string())));
+    }
+
+    if query.contains_key("namecost") || query.contains_key("getnamecost") {
+        return Ok(Some(text(MINING_CONSTANTS.name_cost.to_string())));
+    }
+
+    if query.contains_key("namebonus") {
+        let bonus = Name::count_unpaid(db).await?;
+
+        return Ok(Some(text(bonus.to_string())));
+    }
+
+    if query.contains_key("pushtx2") {
+        return push_transaction(req, request, db, server, query)
+            .await
+            .map(Some);
+    }
+
+    Ok(None)
+}
+
+async fn legacy_api(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let query = query.into_inner();
+
+    match dispatch(&req, &request, &state.db, &server, &query).await? {
+        Some(response) => Ok(response),
+        None => Ok(not_found::not_found().await?),
+    }
+}
+
+/// Whether the legacy API is enabled, through `KRIST_LEGACY_API=true`.
+pub fn is_enabled() -> bool {
+    env::var("KRIST_LEGACY_API").is_ok_and(|value| value == "true")
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("").route(web::get().to(legacy_api)));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::App;
+
+    use super::*;
+    use crate::database::testing;
+
+    /// Send a legacy query, returning the status and body of the response.
+    async fn call(db: &Surreal<Any>, query: &str) -> (StatusCode, String) {
+        let state = web::Data::new(AppState {
+            db: Arc::new(db.clone()),
+            auth_tracker: Default::default(),
+        });
+        let app = init_service(
+            App::new()
+                .app_data(state)
+                .app_data(web::Data::new(WebSocketServer::new()))
+                .service(web::scope("/api/krist").configure(config)),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri(&format!("/api/krist?{query}"))
+            .to_request();
+        let response = call_service(&app, req).await;
+        let status = response.status();
+        let body = read_body(response).await;
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    /// Insert a wallet owned by a private key, returning its address.
+    async fn insert_keyed_wallet(db: &Surreal<Any>, private_key: &str, balance: i64) -> String {
+        let address = crypto::make_v2_address(private_key, "k");
+        let digest = crypto::make_wallet_digest(&address, private_key);
+        let hash = Wallet::hash_digest(db, &digest).await.unwrap();
+        testing::insert_wallet(db, &address, &hash, balance).await;
+
+        address
+    }
+
+    fn ok(body: &str) -> (StatusCode, String) {
+        (StatusCode::OK, body.to_owned())
+    }
+
+    #[actix_web::test]
+    async fn answers_balances_and_names() {
+        let db = testing::connect().await;
+        testing::insert_wallet(&db, "kaaaaaaaaa", "hash", 100).await;
+        let q = r#"
+            CREATE name CONTENT { name: "first", owner: "kaaaaaaaaa", unpaid: 0 };
+            CREATE name CONTENT { name: "second", owner: "kaaaaaaaaa", unpaid: 0 };
+        "#;
+        db.query(q).await.unwrap().check().unwrap();
+
+        assert_eq!(call(&db, "getbalance=kaaaaaaaaa").await, ok("100"));
+        assert_eq!(call(&db, "getbalance=kbbbbbbbbb").await, ok("0"));
+        assert_eq!(call(&db, "getnames=kaaaaaaaaa").await, ok("2"));
+        assert_eq!(call(&db, "getnames=kbbbbbbbbb").await, ok("0"));
+        assert_eq!(call(&db, "v2=test123").await, ok("krcgbmalxg"));
+    }
+
+    #[actix_web::test]
+    async fn pushes_transactions() {
+        let db = testing::connect().await;
+        let sender = insert_keyed_wallet(&db, "sender-key", 100).await;
+        testing::insert_wallet(&db, "kbbbbbbbbb", "hash", 0).await;
+        let push = |query: &str| format!("pushtx2&pkey=sender-key&{query}");
+
+        assert_eq!(call(&db, &push("q=kbbbbbbbbb&amt=0")).await, ok("Error3"));
+        assert_eq!(call(&db, &push("q=kbbbbbbbbb&amt=ten")).await, ok("Error3"));
+        assert_eq!(call(&db, &push("q=nowhere&amt=10")).await, ok("Error4"));
+        assert_eq!(call(&db, &push("q=kmissing01&amt=10")).await, ok("Error4"));
+        assert_eq!(call(&db, &push("q=kbbbbbbbbb&amt=150")).await, ok("Error1"));
+        let query = "pushtx2&q=kbbbbbbbbb&amt=10";
+        assert_eq!(call(&db, query).await, ok("Access denied"));
+        assert_eq!(testing::balance(&db, &sender).await, dec!(100));
+
+        let query = push("q=kbbbbbbbbb&amt=30&com=hello");
+        assert_eq!(call(&db, &query).await, ok("Success"));
+        assert_eq!(testing::balance(&db, &sender).await, dec!(70));
+        assert_eq!(testing::balance(&db, "kbbbbbbbbb").await, dec!(30));
+        assert_eq!(call(&db, &format!("getbalance={sender}")).await, ok("70"));
+    }
+
+    #[actix_web::test]
+    async fn refuses_unknown_verbs() {
+        let db = testing::connect().await;
+
+        let (status, body) = call(&db, "getfortune=kaaaaaaaaa").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(
+            body,
+            r#"{"message":"not_found","description":"Resource not found"}"#
+        );
+    }
+
+    #[test]
+    fn formats_amounts_for_legacy_clients() {
+        assert_eq!(format_amount(dec!(100.00)), "100");
+        assert_eq!(format_amount(dec!(12.50)), "12.5");
+        assert_eq!(format_amount(dec!(0)), "0");
+    }
+}
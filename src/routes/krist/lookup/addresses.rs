@@ -30,6 +30,9 @@ async fn addresses_lookup(
             address: lookup.model.address,
             first_seen: lookup.model.created_at.to_raw(),
             balance: lookup.model.balance,
+            held: lookup.model.held,
+            pending_in: lookup.model.pending_in,
+            pending_out: lookup.model.pending_out,
             total_in: lookup.model.total_in,
             total_out: lookup.model.total_in,
             names: Some(lookup.names),
@@ -1,10 +1,13 @@
-use actix_web::{get, post, web, HttpResponse};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
 
+use crate::auth::{self, signature::Signed};
 use crate::database::models::wallet::Model as Wallet;
+use crate::errors::krist::address::AddressError;
 use crate::models::misc::{MoneySupplyResponse, PrivateKeyAddressResponse, WalletVersionResponse};
 use crate::models::motd::{
     Constants, CurrencyInfo, DetailedMotd, DetailedMotdResponse, PackageInfo,
 };
+use crate::models::tokens::TokenScope;
 use crate::{
     errors::krist::KristError,
     models::auth::{AddressAuthenticationResponse, LoginDetails},
@@ -13,18 +16,42 @@ use crate::{utils, AppState};
 
 #[post("/login")]
 async fn login_address(
+    req: HttpRequest,
     state: web::Data<AppState>,
-    query: web::Json<LoginDetails>,
+    query: Signed<Option<LoginDetails>>,
 ) -> Result<HttpResponse, KristError> {
     let db = &state.db;
-    let query = query.into_inner();
-
-    let private_key = query.private_key;
-    let result = Wallet::verify_address(db, private_key).await?;
+    let details = query.into_inner();
+    let private_key = details.as_ref().map(LoginDetails::derive_key).transpose()?;
+
+    // Legacy v1 addresses can't be derived from the key alone, the client has to name them.
+    let v1_address = details
+        .and_then(|details| details.address)
+        .filter(|address| utils::validation_kromer::is_valid_v1_address(address));
+    if let (Some(v1_address), Some(private_key)) = (v1_address, &private_key) {
+        let address = match auth::verify_request_key(&req, private_key, Some(&v1_address)).await {
+            Ok(wallet) => Some(wallet.address),
+            Err(KristError::Address(AddressError::AuthFailed)) => None,
+            Err(err) => return Err(err),
+        };
+
+        return Ok(HttpResponse::Ok().json(AddressAuthenticationResponse {
+            authed: address.is_some(),
+            address,
+            ok: true,
+        }));
+    }
+
+    // Failing to authenticate is a normal response here, not an error.
+    let address = match auth::authenticate(db, &req, private_key, TokenScope::Read).await {
+        Ok(auth) => Some(auth.wallet.address),
+        Err(KristError::Address(AddressError::AuthFailed) | KristError::Token(_)) => None,
+        Err(err) => return Err(err),
+    };
 
     Ok(HttpResponse::Ok().json(AddressAuthenticationResponse {
-        address: result.authed.then_some(result.address.address),
-        authed: result.authed,
+        authed: address.is_some(),
+        address,
         ok: true,
     }))
 }
@@ -98,7 +125,7 @@ async fn get_kromer_supply(state: web::Data<AppState>) -> Result<HttpResponse, K
 #[post("/v2")]
 async fn get_v2_address(query: web::Json<LoginDetails>) -> Result<HttpResponse, KristError> {
     let query = query.into_inner();
-    let key = query.private_key;
+    let key = query.derive_key()?;
 
     let address = utils::crypto::make_v2_address(&key, "k");
     let response = PrivateKeyAddressResponse { address, ok: true };
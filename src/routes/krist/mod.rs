@@ -1,20 +1,58 @@
+mod allowances;
+mod escrows;
+mod events;
+mod holds;
+mod invoices;
+mod keys;
+mod legacy;
 mod lookup;
 mod misc;
 mod names;
+mod scheduled_transfers;
+mod standing_orders;
+mod tokens;
 mod transactions;
 mod wallet;
-mod ws;
+pub(crate) mod ws;
 
+use crate::database::models::wallet::Model as Wallet;
+use crate::errors::krist::{address::AddressError, KristError};
 use crate::routes::krist::transactions::__path_transaction_list;
 use actix_web::web;
+use surrealdb::{engine::any::Any, Surreal};
 use utoipa::OpenApi;
+
+/// Get a wallet funds can be moved to, locked wallets are refused.
+async fn get_unlocked_wallet(db: &Surreal<Any>, address: String) -> Result<Wallet, KristError> {
+    let wallet = Wallet::get_by_address(db, address.clone())
+        .await?
+        .ok_or(KristError::Address(AddressError::NotFound(address)))?;
+    if wallet.locked {
+        return Err(KristError::Address(AddressError::Locked(wallet.address)));
+    }
+
+    Ok(wallet)
+}
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(web::scope("/lookup").configure(lookup::config));
 
+    cfg.configure(events::config);
     cfg.configure(wallet::config);
     cfg.configure(transactions::config);
+    cfg.configure(invoices::config);
+    cfg.configure(escrows::config);
+    cfg.configure(standing_orders::config);
+    cfg.configure(holds::config);
+    cfg.configure(allowances::config);
+    cfg.configure(scheduled_transfers::config);
+    cfg.configure(tokens::config);
+    cfg.configure(keys::config);
     cfg.configure(ws::config);
     cfg.configure(names::config);
+    // Has to come before misc, its empty scope would otherwise swallow the request.
+    if legacy::is_enabled() {
+        cfg.configure(legacy::config);
+    }
     cfg.configure(misc::config);
 }
 
@@ -1,10 +1,10 @@
-use actix_web::{get, post, web, HttpResponse};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
 use serde_json::json;
 
+use crate::audit::{Actor, AuditEntry, RequestInfo};
+use crate::auth::{self, signature::Signed};
 use crate::database::models::name::Model as Name;
 use crate::database::models::transaction::{Model as Transaction, TransactionCreateData};
-use crate::database::models::wallet::Model as Wallet;
-use crate::errors::krist::address::AddressError;
 use crate::errors::krist::generic::GenericError;
 use crate::errors::krist::transaction::TransactionError;
 use crate::errors::krist::{name::NameError, KristError};
@@ -13,6 +13,7 @@ use crate::models::names::{
     NameCostResponse, NameDataUpdateBody, NameJson, NameListResponse, NameResponse,
     RegisterNameRequest,
 };
+use crate::models::tokens::TokenScope;
 use crate::models::transactions::TransactionType;
 use crate::utils::validation_kromer::is_valid_name;
 use crate::{routes::PaginationParams, AppState};
@@ -99,24 +100,20 @@ async fn name_bonus(state: web::Data<AppState>) -> Result<HttpResponse, KristErr
 
 #[post("/{name}")]
 async fn name_register(
+    req: HttpRequest,
+    request: RequestInfo,
     state: web::Data<AppState>,
     name: web::Path<String>,
-    details: web::Json<Option<RegisterNameRequest>>,
+    details: Signed<Option<RegisterNameRequest>>,
 ) -> Result<HttpResponse, KristError> {
     let db = &state.db;
     let name = name.into_inner().trim().to_lowercase();
     let new_name_cost = rust_decimal::Decimal::new(MINING_CONSTANTS.name_cost, 0);
 
     let private_key = details
-        .as_ref()
-        .map(|json_details| json_details.private_key.clone());
+        .into_inner()
+        .and_then(|json_details| json_details.private_key);
 
-    // Manual error handling here
-    if private_key.is_none() {
-        return Err(KristError::Generic(GenericError::MissingParameter(
-            "privatekey".to_string(),
-        )));
-    }
     // if desired_name.is_none() {
     //     return Err(KristError::Generic(GenericError::MissingParameter("desiredName".to_string())))
     // }
@@ -126,41 +123,42 @@ async fn name_register(
         )));
     }
 
-    let verify_addr_resp = Wallet::verify_address(
-        db,
-        // Unwrap should be okay
-        private_key.unwrap().clone(),
-    )
-    .await?;
-
-    if !verify_addr_resp.authed {
-        tracing::info!(
-            "Name registration REJECTED for {}",
-            verify_addr_resp.address.address
-        );
-        return Err(KristError::Address(AddressError::AuthFailed));
-    }
+    let auth = auth::authenticate(db, &req, private_key, TokenScope::Names).await?;
+    let owner = &auth.wallet;
 
     // TODO: Rate limit check. Apply a 2x cost to name events
 
     // Reject insufficient funds
-    if verify_addr_resp.address.balance < new_name_cost {
+    if owner.balance < new_name_cost {
         return Err(KristError::Transaction(TransactionError::InsufficientFunds));
     }
 
+    auth.reserve_spend(db, new_name_cost).await?;
+
     // Create the transaction
     let creation_data = TransactionCreateData {
-        from: verify_addr_resp.address.address.clone(),
+        from: owner.address.clone(),
         to: "name".to_string(),
         amount: new_name_cost,
         metadata: None,
         transaction_type: TransactionType::NamePurchase,
     };
-    let _trans_response: Vec<Transaction> = db.insert("transaction").content(creation_data).await?;
+    let trans_response: Result<Vec<Transaction>, _> =
+        db.insert("transaction").content(creation_data).await;
+    if let Err(err) = trans_response {
+        auth.release_spend(db, new_name_cost).await?;
+        return Err(err.into());
+    }
 
     // Create the new name
-    let _name_response =
-        Name::register_name(db, name.clone(), verify_addr_resp.address.address).await?;
+    let _name_response = Name::register_name(db, name.clone(), owner.address.clone()).await?;
+
+    AuditEntry::new(Actor::Wallet(owner.address.clone()), "name.register")
+        .target(&name)
+        .after(json!({ "owner": owner.address, "cost": new_name_cost }))
+        .request(&request)
+        .write(db)
+        .await;
 
     Ok(HttpResponse::Ok().json(json!({
         "ok": true,
@@ -188,15 +186,34 @@ async fn name_get(
 }
 
 async fn name_update_data(
+    req: HttpRequest,
+    request: RequestInfo,
     state: web::Data<AppState>,
     name: web::Path<String>,
-    body: web::Json<NameDataUpdateBody>,
+    body: Signed<NameDataUpdateBody>,
 ) -> Result<HttpResponse, KristError> {
     let db = &state.db;
     let name = name.into_inner();
     let body = body.into_inner();
 
-    let model = Name::ctrl_modify_data(db, name, body).await?;
+    let auth = auth::authenticate(db, &req, body.private_key, TokenScope::Names).await?;
+
+    let before = Name::get_by_name(db, name.trim().to_lowercase())
+        .await?
+        .and_then(|model| model.a);
+    let model = Name::ctrl_modify_data(db, name, body.a, &auth.wallet).await?;
+
+    AuditEntry::new(
+        Actor::Wallet(auth.wallet.address.clone()),
+        "name.update_data",
+    )
+    .target(&model.name)
+    .before(json!({ "a": before }))
+    .after(json!({ "a": model.a }))
+    .request(&request)
+    .write(db)
+    .await;
+
     let name: NameJson = model.into();
     let resp = NameResponse { ok: true, name };
 
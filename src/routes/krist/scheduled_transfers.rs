@@ -0,0 +1,205 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use rust_decimal_macros::dec;
+
+use crate::audit::{Actor, AuditEntry, RequestInfo};
+use crate::auth::{self, signature::Signed};
+use crate::database::models::scheduled_transfer::{
+    Model as ScheduledTransfer, ScheduledTransferCreateData,
+};
+use crate::errors::krist::{
+    generic::GenericError, scheduled_transfer::ScheduledTransferError,
+    transaction::TransactionError, KristError,
+};
+use crate::models::scheduled_transfers::{
+    CreateScheduledTransferRequest, ScheduledTransferActionRequest, ScheduledTransferJson,
+    ScheduledTransferListQuery, ScheduledTransferListResponse, ScheduledTransferResponse,
+};
+use crate::models::tokens::TokenScope;
+use crate::routes::PaginationParams;
+use crate::scheduled_transfers;
+use crate::websockets::WebSocketServer;
+use crate::AppState;
+
+/// Transfers can't be scheduled more than a year ahead.
+const MAX_DELAY: i64 = 366 * 24 * 60 * 60;
+
+async fn get_transfer(state: &AppState, id: String) -> Result<ScheduledTransfer, KristError> {
+    ScheduledTransfer::get_partial(&state.db, &id)
+        .await?
+        .ok_or(KristError::ScheduledTransfer(
+            ScheduledTransferError::NotFound(id),
+        ))
+}
+
+#[post("")]
+async fn scheduled_transfer_create(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    details: Signed<CreateScheduledTransferRequest>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    if details.amount <= dec!(0) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "amount".to_string(),
+        )));
+    }
+    let delay = (details.execute_at - Utc::now()).num_seconds();
+    if delay <= 0 || delay > MAX_DELAY {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "execute_at".to_string(),
+        )));
+    }
+
+    let auth = auth::authenticate(db, &req, details.password, TokenScope::Transfer).await?;
+    let from = auth.wallet.address.clone();
+
+    let to = super::get_unlocked_wallet(db, details.to).await?.address;
+    if to == from {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "to".to_string(),
+        )));
+    }
+
+    if auth.wallet.balance < details.amount {
+        return Err(KristError::Transaction(TransactionError::InsufficientFunds));
+    }
+
+    auth.reserve_spend(db, details.amount).await?;
+
+    let creation_data = ScheduledTransferCreateData {
+        from: from.clone(),
+        to,
+        amount: details.amount,
+        metadata: details.metadata,
+        execute_at: details.execute_at.into(),
+    };
+    let transfer = match ScheduledTransfer::create(db, creation_data).await {
+        Ok(Some(transfer)) => transfer,
+        Ok(None) => {
+            auth.release_spend(db, details.amount).await?;
+            return Err(KristError::Transaction(TransactionError::InsufficientFunds));
+        }
+        Err(err) => {
+            auth.release_spend(db, details.amount).await?;
+            return Err(err.into());
+        }
+    };
+    let response: ScheduledTransferJson = transfer.clone().into();
+
+    AuditEntry::new(Actor::Wallet(from), "scheduled_transfer.create")
+        .target(&response.id)
+        .after(&response)
+        .request(&request)
+        .write(db)
+        .await;
+
+    scheduled_transfers::notify(&server, transfer).await;
+
+    Ok(HttpResponse::Ok().json(ScheduledTransferResponse {
+        ok: true,
+        transfer: response,
+    }))
+}
+
+/// Give the reserved funds back before the transfer executes, done by the sender.
+#[post("/{id}/cancel")]
+async fn scheduled_transfer_cancel(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    id: web::Path<String>,
+    details: Signed<ScheduledTransferActionRequest>,
+) -> Result<HttpResponse, KristError> {
+    let db = &state.db;
+    let transfer = get_transfer(&state, id.into_inner()).await?;
+
+    let auth = auth::authenticate(
+        db,
+        &req,
+        details.into_inner().password,
+        TokenScope::Transfer,
+    )
+    .await?;
+    let address = auth.wallet.address;
+    if address != transfer.from {
+        return Err(KristError::ScheduledTransfer(
+            ScheduledTransferError::Forbidden,
+        ));
+    }
+
+    let before: ScheduledTransferJson = transfer.clone().into();
+    let transfer = transfer.cancel(db).await?.ok_or_else(|| {
+        KristError::ScheduledTransfer(ScheduledTransferError::NotPending(before.id.clone()))
+    })?;
+    let response: ScheduledTransferJson = transfer.clone().into();
+
+    AuditEntry::new(Actor::Wallet(address), "scheduled_transfer.cancel")
+        .target(&response.id)
+        .before(&before)
+        .after(&response)
+        .request(&request)
+        .write(db)
+        .await;
+
+    scheduled_transfers::notify(&server, transfer).await;
+
+    Ok(HttpResponse::Ok().json(ScheduledTransferResponse {
+        ok: true,
+        transfer: response,
+    }))
+}
+
+#[get("")]
+async fn scheduled_transfer_list(
+    state: web::Data<AppState>,
+    query: web::Query<ScheduledTransferListQuery>,
+) -> Result<HttpResponse, KristError> {
+    let query = query.into_inner();
+    let pagination = PaginationParams {
+        limit: query.limit,
+        offset: query.offset,
+    };
+
+    let (transfers, total) =
+        ScheduledTransfer::involving(&state.db, &query.address, query.status, &pagination).await?;
+    let transfers: Vec<ScheduledTransferJson> = transfers
+        .into_iter()
+        .map(|transfer| transfer.into())
+        .collect();
+
+    Ok(HttpResponse::Ok().json(ScheduledTransferListResponse {
+        ok: true,
+        count: transfers.len(),
+        total,
+        transfers,
+    }))
+}
+
+#[get("/{id}")]
+async fn scheduled_transfer_get(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, KristError> {
+    let transfer = get_transfer(&state, id.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(ScheduledTransferResponse {
+        ok: true,
+        transfer: transfer.into(),
+    }))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/scheduled-transfers")
+            .service(scheduled_transfer_create)
+            .service(scheduled_transfer_list)
+            .service(scheduled_transfer_cancel)
+            .service(scheduled_transfer_get),
+    );
+}
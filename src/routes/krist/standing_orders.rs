@@ -0,0 +1,315 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use rust_decimal_macros::dec;
+
+use crate::audit::{Actor, AuditEntry, RequestInfo};
+use crate::auth::{self, signature::Signed};
+use crate::database::models::standing_order::{Model as StandingOrder, StandingOrderCreateData};
+use crate::errors::krist::{generic::GenericError, standing_order::StandingOrderError, KristError};
+use crate::models::standing_orders::{
+    CreateStandingOrderRequest, StandingOrderActionRequest, StandingOrderJson,
+    StandingOrderListQuery, StandingOrderListResponse, StandingOrderResponse,
+};
+use crate::models::tokens::TokenScope;
+use crate::routes::PaginationParams;
+use crate::standing_orders;
+use crate::websockets::WebSocketServer;
+use crate::AppState;
+
+/// Standing orders can't run more often than once an hour.
+const MIN_INTERVAL: u64 = 60 * 60;
+
+/// Or less often than once a year.
+const MAX_INTERVAL: u64 = 366 * 24 * 60 * 60;
+
+/// The first transfer can't be scheduled more than a year ahead.
+const MAX_START_DELAY: i64 = 366 * 24 * 60 * 60;
+
+async fn get_order(state: &AppState, id: String) -> Result<StandingOrder, KristError> {
+    StandingOrder::get_partial(&state.db, &id)
+        .await?
+        .ok_or(KristError::StandingOrder(StandingOrderError::NotFound(id)))
+}
+
+/// Authenticate one of the parties of a standing order, `payer_only` leaves out its recipient.
+async fn authenticate_party(
+    req: &HttpRequest,
+    state: &AppState,
+    password: Option<String>,
+    order: &StandingOrder,
+    payer_only: bool,
+) -> Result<String, KristError> {
+    let auth = auth::authenticate(&state.db, req, password, TokenScope::StandingOrders).await?;
+    let address = auth.wallet.address;
+
+    if payer_only && address != order.from {
+        return Err(KristError::StandingOrder(StandingOrderError::Forbidden(
+            "payer",
+        )));
+    }
+    if address != order.from && address != order.to {
+        return Err(KristError::StandingOrder(StandingOrderError::Forbidden(
+            "payer or recipient",
+        )));
+    }
+
+    Ok(address)
+}
+
+#[post("")]
+async fn standing_order_create(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    details: Signed<CreateStandingOrderRequest>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    if details.amount <= dec!(0) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "amount".to_string(),
+        )));
+    }
+    if !(MIN_INTERVAL..=MAX_INTERVAL).contains(&details.interval) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "interval".to_string(),
+        )));
+    }
+    if details.count == Some(0) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "count".to_string(),
+        )));
+    }
+
+    let now = Utc::now();
+    let start = details.start.unwrap_or(now).max(now);
+    if (start - now).num_seconds() > MAX_START_DELAY {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "start".to_string(),
+        )));
+    }
+    if details.ends_at.is_some_and(|ends_at| ends_at < start) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "ends_at".to_string(),
+        )));
+    }
+
+    let auth = auth::authenticate(db, &req, details.password, TokenScope::StandingOrders).await?;
+    let from = auth.wallet.address;
+
+    let to = super::get_unlocked_wallet(db, details.to).await?.address;
+    if to == from {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "to".to_string(),
+        )));
+    }
+
+    let creation_data = StandingOrderCreateData {
+        from: from.clone(),
+        to,
+        amount: details.amount,
+        metadata: details.metadata,
+        interval: details.interval,
+        next_run: start.into(),
+        ends_at: details.ends_at.map(Into::into),
+        max_runs: details.count,
+    };
+    let order = StandingOrder::create(db, creation_data)
+        .await?
+        .ok_or(KristError::Custom("internal_server_error"))?;
+    let response: StandingOrderJson = order.clone().into();
+
+    AuditEntry::new(Actor::Wallet(from), "standing_order.create")
+        .target(&response.id)
+        .after(&response)
+        .request(&request)
+        .write(db)
+        .await;
+
+    standing_orders::notify(&server, order).await;
+
+    Ok(HttpResponse::Ok().json(StandingOrderResponse {
+        ok: true,
+        order: response,
+    }))
+}
+
+/// Stop making transfers until the payer resumes the order, done by either party.
+#[post("/{id}/pause")]
+async fn standing_order_pause(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    id: web::Path<String>,
+    details: Signed<StandingOrderActionRequest>,
+) -> Result<HttpResponse, KristError> {
+    let order = get_order(&state, id.into_inner()).await?;
+    let address =
+        authenticate_party(&req, &state, details.into_inner().password, &order, false).await?;
+
+    let before: StandingOrderJson = order.clone().into();
+    let order = order.pause(&state.db).await?.ok_or_else(|| {
+        KristError::StandingOrder(StandingOrderError::InvalidStatus(
+            before.id.clone(),
+            "not active",
+        ))
+    })?;
+
+    finish_change(
+        &state,
+        &server,
+        &request,
+        address,
+        "standing_order.pause",
+        before,
+        order,
+    )
+    .await
+}
+
+/// Make transfers again after the order was paused or suspended, done by the payer.
+#[post("/{id}/resume")]
+async fn standing_order_resume(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    id: web::Path<String>,
+    details: Signed<StandingOrderActionRequest>,
+) -> Result<HttpResponse, KristError> {
+    let order = get_order(&state, id.into_inner()).await?;
+    let address =
+        authenticate_party(&req, &state, details.into_inner().password, &order, true).await?;
+
+    let before: StandingOrderJson = order.clone().into();
+    let order = order.resume(&state.db).await?.ok_or_else(|| {
+        KristError::StandingOrder(StandingOrderError::InvalidStatus(
+            before.id.clone(),
+            "not paused or suspended",
+        ))
+    })?;
+
+    finish_change(
+        &state,
+        &server,
+        &request,
+        address,
+        "standing_order.resume",
+        before,
+        order,
+    )
+    .await
+}
+
+/// Stop the order for good, done by either party.
+#[post("/{id}/cancel")]
+async fn standing_order_cancel(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    id: web::Path<String>,
+    details: Signed<StandingOrderActionRequest>,
+) -> Result<HttpResponse, KristError> {
+    let order = get_order(&state, id.into_inner()).await?;
+    let address =
+        authenticate_party(&req, &state, details.into_inner().password, &order, false).await?;
+
+    let before: StandingOrderJson = order.clone().into();
+    let order = order.cancel(&state.db).await?.ok_or_else(|| {
+        KristError::StandingOrder(StandingOrderError::InvalidStatus(
+            before.id.clone(),
+            "already ended",
+        ))
+    })?;
+
+    finish_change(
+        &state,
+        &server,
+        &request,
+        address,
+        "standing_order.cancel",
+        before,
+        order,
+    )
+    .await
+}
+
+/// Audit and announce a change made by one of the parties, and respond with the order.
+async fn finish_change(
+    state: &AppState,
+    server: &WebSocketServer,
+    request: &RequestInfo,
+    address: String,
+    action: &'static str,
+    before: StandingOrderJson,
+    order: StandingOrder,
+) -> Result<HttpResponse, KristError> {
+    let response: StandingOrderJson = order.clone().into();
+
+    AuditEntry::new(Actor::Wallet(address), action)
+        .target(&response.id)
+        .before(&before)
+        .after(&response)
+        .request(request)
+        .write(&state.db)
+        .await;
+
+    standing_orders::notify(server, order).await;
+
+    Ok(HttpResponse::Ok().json(StandingOrderResponse {
+        ok: true,
+        order: response,
+    }))
+}
+
+#[get("")]
+async fn standing_order_list(
+    state: web::Data<AppState>,
+    query: web::Query<StandingOrderListQuery>,
+) -> Result<HttpResponse, KristError> {
+    let query = query.into_inner();
+    let pagination = PaginationParams {
+        limit: query.limit,
+        offset: query.offset,
+    };
+
+    let (orders, total) =
+        StandingOrder::involving(&state.db, &query.address, query.status, &pagination).await?;
+    let orders: Vec<StandingOrderJson> = orders.into_iter().map(|order| order.into()).collect();
+
+    Ok(HttpResponse::Ok().json(StandingOrderListResponse {
+        ok: true,
+        count: orders.len(),
+        total,
+        orders,
+    }))
+}
+
+#[get("/{id}")]
+async fn standing_order_get(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, KristError> {
+    let order = get_order(&state, id.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(StandingOrderResponse {
+        ok: true,
+        order: order.into(),
+    }))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/standing-orders")
+            .service(standing_order_create)
+            .service(standing_order_list)
+            .service(standing_order_pause)
+            .service(standing_order_resume)
+            .service(standing_order_cancel)
+            .service(standing_order_get),
+    );
+}
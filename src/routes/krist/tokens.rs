@@ -0,0 +1,161 @@
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use chrono::{TimeDelta, Utc};
+use rust_decimal_macros::dec;
+
+use crate::audit::{Actor, AuditEntry, RequestInfo};
+use crate::auth;
+use crate::database::models::api_token::{ApiTokenCreateData, Model as ApiToken};
+use crate::database::models::wallet::Model as Wallet;
+use crate::errors::krist::{generic::GenericError, token::TokenError, KristError};
+use crate::models::tokens::{
+    ApiTokenJson, CreateTokenRequest, CreateTokenResponse, TokenListResponse, TokenOwnerRequest,
+    TokenResponse,
+};
+use crate::{utils, AppState};
+
+/// Maximum length of a token's name.
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Tokens can only be managed with the private key, a token can't be used to mint or revoke other tokens.
+async fn verify_owner(req: &HttpRequest, private_key: String) -> Result<Wallet, KristError> {
+    auth::verify_request_key(req, &private_key, None).await
+}
+
+#[post("")]
+async fn token_create(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    details: web::Json<CreateTokenRequest>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    if details.scopes.is_empty() {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "scopes".to_string(),
+        )));
+    }
+    if details.daily_limit.is_some_and(|limit| limit < dec!(0)) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "daily_limit".to_string(),
+        )));
+    }
+    if details
+        .name
+        .as_ref()
+        .is_some_and(|name| name.chars().count() > MAX_NAME_LENGTH)
+    {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "name".to_string(),
+        )));
+    }
+
+    let expires_at = match details.expires_in {
+        Some(seconds) => {
+            let expires_at = TimeDelta::try_seconds(seconds)
+                .filter(|delta| *delta > TimeDelta::zero())
+                .and_then(|delta| Utc::now().checked_add_signed(delta))
+                .ok_or_else(|| {
+                    KristError::Generic(GenericError::InvalidParameter("expires_in".to_string()))
+                })?;
+            Some(expires_at.into())
+        }
+        None => None,
+    };
+
+    let wallet = verify_owner(&req, details.private_key).await?;
+
+    let mut scopes = details.scopes;
+    scopes.sort();
+    scopes.dedup();
+
+    let token = utils::crypto::generate_api_token();
+    let creation_data = ApiTokenCreateData {
+        address: wallet.address.clone(),
+        name: details.name,
+        token_hash: utils::crypto::sha256(&token),
+        scopes,
+        daily_limit: details.daily_limit,
+        expires_at,
+    };
+    let model = ApiToken::create(db, creation_data)
+        .await?
+        .ok_or(KristError::Custom("internal_server_error"))?;
+    let info: ApiTokenJson = model.into();
+
+    AuditEntry::new(Actor::Wallet(wallet.address.clone()), "api_token.create")
+        .target(&wallet.address)
+        .after(&info)
+        .request(&request)
+        .write(db)
+        .await;
+
+    Ok(HttpResponse::Ok().json(CreateTokenResponse {
+        ok: true,
+        token,
+        info,
+    }))
+}
+
+#[post("/list")]
+async fn token_list(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    details: web::Json<TokenOwnerRequest>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    let wallet = verify_owner(&req, details.private_key).await?;
+
+    let tokens: Vec<ApiTokenJson> = ApiToken::get_by_address(db, wallet.address)
+        .await?
+        .into_iter()
+        .map(|token| token.into())
+        .collect();
+
+    Ok(HttpResponse::Ok().json(TokenListResponse {
+        ok: true,
+        count: tokens.len(),
+        tokens,
+    }))
+}
+
+#[post("/{id}/revoke")]
+async fn token_revoke(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+    details: web::Json<TokenOwnerRequest>,
+) -> Result<HttpResponse, KristError> {
+    let id = id.into_inner();
+    let details = details.into_inner();
+    let db = &state.db;
+
+    let wallet = verify_owner(&req, details.private_key).await?;
+
+    let token = ApiToken::revoke(db, &wallet.address, &id)
+        .await?
+        .ok_or_else(|| KristError::Token(TokenError::NotFound(id)))?;
+    let token: ApiTokenJson = token.into();
+
+    AuditEntry::new(Actor::Wallet(wallet.address.clone()), "api_token.revoke")
+        .target(&wallet.address)
+        .before(&token)
+        .request(&request)
+        .write(db)
+        .await;
+
+    Ok(HttpResponse::Ok().json(TokenResponse { ok: true, token }))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/tokens")
+            .service(token_list)
+            .service(token_revoke)
+            .service(token_create),
+    );
+}
@@ -1,18 +1,27 @@
-use actix_web::{get, post, web, HttpResponse};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
 use rust_decimal_macros::dec;
+use surrealdb::{engine::any::Any, Surreal};
 
-use crate::database::models::transaction::{Model as Transaction, TransactionCreateData};
+use crate::audit::{Actor, AuditEntry, RequestInfo};
+use crate::auth::{self, signature::Signed};
+use crate::database::models::name::Model as Name;
+use crate::database::models::transaction::{
+    Model as Transaction, ReturnOutcome, TransactionCreateData, TransactionNameData,
+};
 use crate::database::models::wallet::Model as Wallet;
 use crate::errors::krist::address::AddressError;
 use crate::errors::krist::generic::GenericError;
+use crate::errors::krist::name::NameError;
 use crate::errors::krist::{transaction::TransactionError, KristError};
+use crate::models::tokens::TokenScope;
 use crate::models::transactions::{
-    TransactionDetails, TransactionJson, TransactionListResponse, TransactionResponse,
-    TransactionType,
+    TransactionBatchDetails, TransactionBatchResponse, TransactionDetails, TransactionJson,
+    TransactionListResponse, TransactionRefundDetails, TransactionResponse, TransactionType,
 };
 use crate::models::websockets::{WebSocketEvent, WebSocketMessage};
+use crate::utils::common_meta;
 use crate::websockets::WebSocketServer;
-use crate::{routes::PaginationParams, AppState};
+use crate::{allowances, batches, invoices, routes::PaginationParams, AppState};
 
 #[utoipa::path(
     get,
@@ -53,11 +62,13 @@ pub async fn transaction_list(
 
 #[post("")]
 async fn transaction_create(
+    req: HttpRequest,
+    request: RequestInfo,
     state: web::Data<AppState>,
     server: web::Data<WebSocketServer>,
-    details: web::Json<TransactionDetails>,
+    details: Signed<TransactionDetails>,
 ) -> Result<HttpResponse, KristError> {
-    let details = details.into_inner();
+    let mut details = details.into_inner();
     let db = &state.db;
 
     // Check on the server so DB doesnt throw.
@@ -67,34 +78,90 @@ async fn transaction_create(
         )));
     }
 
-    let sender_verify_response = Wallet::verify_address(db, details.password).await?;
+    if let Some(invoice) = &details.invoice {
+        let payment = invoices::prepare_payment(
+            db,
+            invoice,
+            &details.to,
+            details.amount,
+            details.metadata.take(),
+        )
+        .await?;
+        details.to = payment.to;
+        details.amount = payment.amount;
+        details.metadata = Some(payment.metadata);
+    }
 
-    let sender = sender_verify_response.address;
+    // A zero amount only stands for the outstanding amount of an invoice, which may be nothing.
+    if details.amount <= dec!(0.0) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "amount".to_string(),
+        )));
+    }
 
-    let recipient = Wallet::get_by_address(db, details.to.clone())
-        .await?
-        .ok_or_else(|| KristError::Address(AddressError::NotFound(details.to)))?;
+    let auth = auth::authenticate(db, &req, details.password, TokenScope::Transfer).await?;
+    let sender = auth.wallet.clone();
+
+    let recipient = super::get_unlocked_wallet(db, details.to).await?;
+
+    // Spending from another wallet, its allowance and balance are checked along with the transfer.
+    let owner = details.from.filter(|from| *from != sender.address);
 
     // Make sure to check the request to see if the funds are available.
-    if sender.balance < details.amount {
+    if owner.is_none() && sender.balance < details.amount {
         return Err(KristError::Transaction(TransactionError::InsufficientFunds));
     }
 
-    let creation_data = TransactionCreateData {
-        from: sender.address,
-        to: recipient.address,
-        amount: details.amount,
-        metadata: details.metadata,
-        transaction_type: TransactionType::Transfer,
+    auth.reserve_spend(db, details.amount).await?;
+
+    let response = match owner {
+        Some(owner) => {
+            allowances::spend(
+                db,
+                owner,
+                &sender.address,
+                recipient.address,
+                details.amount,
+                details.metadata,
+            )
+            .await
+        }
+        None => {
+            let creation_data = TransactionCreateData {
+                from: sender.address.clone(),
+                to: recipient.address,
+                amount: details.amount,
+                metadata: details.metadata,
+                transaction_type: TransactionType::Transfer,
+            };
+            let response: Result<Vec<Transaction>, _> =
+                db.insert("transaction").content(creation_data).await;
+            response
+                .map(|response| response.first().unwrap().clone()) // the fuck man
+                .map_err(KristError::from)
+        }
+    };
+    let model = match response {
+        Ok(model) => model,
+        Err(err) => {
+            auth.release_spend(db, details.amount).await?;
+            return Err(err);
+        }
     };
-    let response: Vec<Transaction> = db.insert("transaction").content(creation_data).await?;
-    let model = response.first().unwrap(); // the fuck man
     let response: TransactionJson = model.clone().into();
 
+    AuditEntry::new(Actor::Wallet(sender.address), "transaction.create")
+        .target(&response.to)
+        .after(&response)
+        .request(&request)
+        .write(db)
+        .await;
+
     let event = WebSocketMessage::new_event(WebSocketEvent::Transaction {
         transaction: response.clone(),
     });
     server.broadcast_event(event).await;
+    invoices::link_payment(db, &server, &model).await;
 
     let final_response = TransactionResponse {
         ok: true,
@@ -146,10 +213,173 @@ async fn transaction_get(
     .ok_or_else(|| KristError::Transaction(TransactionError::NotFound))
 }
 
+/// Where a refund of a transaction goes, the `return=` address or name of its metadata, or else its sender.
+///
+/// When returning to a name, the name is also returned so it can lead the refund's metadata.
+async fn refund_target(
+    db: &Surreal<Any>,
+    transaction: &Transaction,
+) -> Result<(String, Option<String>), KristError> {
+    let target = transaction
+        .metadata
+        .as_deref()
+        .and_then(|metadata| common_meta::get(metadata, "return"));
+    let Some(target) = target else {
+        return Ok((transaction.from.clone(), None));
+    };
+
+    if let Some(name) = TransactionNameData::parse(target).name {
+        let name = Name::get_by_name(db, name.clone())
+            .await?
+            .ok_or(KristError::Name(NameError::NameNotFound(name)))?;
+
+        return Ok((name.owner, Some(target.to_owned())));
+    }
+
+    let wallet = Wallet::get_by_address(db, target.to_owned())
+        .await?
+        .ok_or_else(|| KristError::Address(AddressError::NotFound(target.to_owned())))?;
+
+    Ok((wallet.address, None))
+}
+
+/// Send (part of) a received transaction back, to the `return=` address of its metadata or its sender.
+///
+/// The refund references the original with `ref=<id>`, and all refunds together never exceed its amount.
+#[post("/{id}/refund")]
+async fn transaction_refund(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    id: web::Path<String>,
+    details: Signed<TransactionRefundDetails>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    if details.amount.is_some_and(|amount| amount <= dec!(0.0)) {
+        return Err(KristError::Generic(GenericError::InvalidParameter(
+            "amount".to_string(),
+        )));
+    }
+
+    let auth = auth::authenticate(db, &req, details.password, TokenScope::Transfer).await?;
+    let sender = auth.wallet.clone();
+
+    let original = Transaction::get_partial(db, id.into_inner())
+        .await?
+        .ok_or(KristError::Transaction(TransactionError::NotFound))?;
+    if original.to != sender.address {
+        return Err(KristError::Transaction(TransactionError::NotRecipient));
+    }
+    if original.transaction_type != TransactionType::Transfer {
+        return Err(KristError::Transaction(TransactionError::NotRefundable));
+    }
+
+    let (to, name) = refund_target(db, &original).await?;
+    let metadata = common_meta::join([
+        name.unwrap_or_default(),
+        original.reference_meta(),
+        details.metadata.unwrap_or_default(),
+    ]);
+
+    // The exact amount is only known once the refund is made, count the most it can be.
+    let reserved = details.amount.unwrap_or(original.amount);
+    auth.reserve_spend(db, reserved).await?;
+
+    let outcome = match original.refund(db, &to, details.amount, metadata).await {
+        Ok(outcome) => outcome,
+        Err(err) => {
+            auth.release_spend(db, reserved).await?;
+            return Err(err.into());
+        }
+    };
+    let transaction = match outcome {
+        ReturnOutcome::Returned { transaction, .. } => *transaction,
+        ReturnOutcome::ExceedsRemaining { remaining } => {
+            auth.release_spend(db, reserved).await?;
+            return Err(KristError::Transaction(
+                TransactionError::RefundExceedsAmount(remaining),
+            ));
+        }
+        ReturnOutcome::InsufficientFunds { .. } => {
+            auth.release_spend(db, reserved).await?;
+            return Err(KristError::Transaction(TransactionError::InsufficientFunds));
+        }
+    };
+    if transaction.amount < reserved {
+        auth.release_spend(db, reserved - transaction.amount)
+            .await?;
+    }
+    let response: TransactionJson = transaction.into();
+
+    AuditEntry::new(Actor::Wallet(sender.address), "transaction.refund")
+        .target(&response.to)
+        .after(&response)
+        .request(&request)
+        .write(db)
+        .await;
+
+    let event = WebSocketMessage::new_event(WebSocketEvent::Transaction {
+        transaction: response.clone(),
+    });
+    server.broadcast_event(event).await;
+
+    Ok(HttpResponse::Ok().json(TransactionResponse {
+        ok: true,
+        transaction: response,
+    }))
+}
+
+/// Send to many recipients at once, either every transfer is made or none are.
+#[post("/batch")]
+async fn transaction_batch(
+    req: HttpRequest,
+    request: RequestInfo,
+    state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
+    details: Signed<TransactionBatchDetails>,
+) -> Result<HttpResponse, KristError> {
+    let details = details.into_inner();
+    let db = &state.db;
+
+    let total = batches::validate(&details.transfers)?;
+
+    let auth = auth::authenticate(db, &req, details.password, TokenScope::Transfer).await?;
+    let sender = auth.wallet.address.clone();
+
+    auth.reserve_spend(db, total).await?;
+
+    let batch = match batches::send(db, &server, &sender, &details.transfers).await {
+        Ok(batch) => batch,
+        Err(err) => {
+            auth.release_spend(db, total).await?;
+            return Err(err);
+        }
+    };
+    let response = TransactionBatchResponse {
+        ok: true,
+        batch: batch.id,
+        transactions: batch.transactions.into_iter().map(Into::into).collect(),
+    };
+
+    AuditEntry::new(Actor::Wallet(sender), "transaction.batch")
+        .target(&response.batch)
+        .after(&response)
+        .request(&request)
+        .write(db)
+        .await;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/transactions")
             .service(transaction_create)
+            .service(transaction_batch)
+            .service(transaction_refund)
             .service(transaction_latest)
             .service(transaction_get)
             .service(transaction_list),
@@ -11,11 +11,12 @@ use serde_json::json;
 use surrealdb::Uuid;
 use tokio::sync::Mutex;
 
-use crate::database::models::wallet::Model as Wallet;
+use crate::auth;
 use crate::errors::krist::KristErrorExt;
 use crate::errors::krist::{address::AddressError, websockets::WebSocketError, KristError};
 use crate::models::websockets::{WebSocketMessage, WebSocketMessageInner};
-use crate::websockets::types::common::WebSocketTokenData;
+use crate::utils::net;
+use crate::websockets::types::common::{SessionCredential, WebSocketTokenData};
 use crate::websockets::types::convert_to_iso_string;
 use crate::websockets::{handler, utils, WebSocketServer, CLIENT_TIMEOUT, HEARTBEAT_INTERVAL};
 use crate::AppState;
@@ -28,26 +29,28 @@ struct WsConnDetails {
 #[post("/start")]
 #[tracing::instrument(name = "setup_ws_route", level = "debug", skip_all)]
 pub async fn setup_ws(
-    state: web::Data<AppState>,
+    req: HttpRequest,
     server: web::Data<WebSocketServer>,
     details: Option<web::Json<WsConnDetails>>,
 ) -> Result<HttpResponse, KristError> {
-    let db = &state.db;
     let private_key = details.map(|json_details| json_details.privatekey.clone());
 
     let uuid = match private_key {
         Some(private_key) => {
-            let wallet = Wallet::verify_address(db, &private_key)
+            let model = auth::verify_request_key(&req, &private_key, None)
                 .await
-                .map_err(|_| KristError::Address(AddressError::AuthFailed))?;
-            let model = wallet.address;
+                .map_err(|err| match err {
+                    KristError::Address(AddressError::TooManyAttempts(_)) => err,
+                    _ => KristError::Address(AddressError::AuthFailed),
+                })?;
 
-            let token_data = WebSocketTokenData::new(model.address, Some(private_key));
+            let credential = SessionCredential::derive(&model.address, &private_key);
+            let token_data = WebSocketTokenData::new(model.address, Some(credential));
 
             server.obtain_token(token_data).await
         }
         None => {
-            let token_data = WebSocketTokenData::new("guest".into(), None);
+            let token_data = WebSocketTokenData::guest();
 
             server.obtain_token(token_data).await
         }
@@ -123,7 +126,22 @@ pub async fn gateway(
         .aggregate_continuations()
         .max_continuation_size(2 * 1024 * 1024);
 
-    server.insert_session(uuid, session.clone(), data).await; // Not a big fan of cloning but here it is needed.
+    let ip = net::client_ip(&req, server.config.trust_forwarded_for);
+
+    // Not a big fan of cloning but here it is needed.
+    if let Err(err) = server.insert_session(uuid, session.clone(), data, ip).await {
+        let error = json!({
+            "ok": false,
+            "error": err.error_type(),
+            "message": err.to_string(),
+            "type": "error"
+        });
+
+        let _ = session.text(error.to_string()).await;
+        let _ = session.close(None).await;
+
+        return Ok(response);
+    }
 
     let alive = Arc::new(Mutex::new(Instant::now()));
     let mut session2 = session.clone();
@@ -162,6 +180,15 @@ pub async fn gateway(
 
                 break;
             }
+
+            if server2.is_idle_guest(&uuid).await {
+                tracing::info!("Evicting idle guest session {uuid}");
+                server2
+                    .kick_session(&uuid, Some("Idle timeout".to_owned()))
+                    .await;
+
+                break;
+            }
         }
     });
 
@@ -169,15 +196,26 @@ pub async fn gateway(
     actix_web::rt::spawn(async move {
         while let Some(Ok(msg)) = stream.recv().await {
             match msg {
-                AggregatedMessage::Ping(bytes) => {
-                    if session.pong(&bytes).await.is_err() {
-                        tracing::error!("Failed to send pong back to session");
-                        return;
-                    }
+                AggregatedMessage::Ping(bytes) if session.pong(&bytes).await.is_err() => {
+                    tracing::error!("Failed to send pong back to session");
+                    return;
                 }
 
                 AggregatedMessage::Text(string) => {
-                    if string.chars().count() > 512 {
+                    server.record_message();
+
+                    if !server.check_rate_limit(&uuid).await {
+                        let error_msg = json!({
+                            "ok": false,
+                            "error": "rate_limit_hit",
+                            "message": "Rate limit hit",
+                            "type": "error"
+                        })
+                        .to_string();
+                        tracing::info!("Session {uuid} hit the rate limit");
+
+                        let _ = session.text(error_msg).await;
+                    } else if string.chars().count() > 512 {
                         // TODO: Possibly use error message struct in models
                         // This isn't super necessary though and this shortcut saves some unnecessary error handling...
                         let error_msg = json!({
@@ -191,10 +229,10 @@ pub async fn gateway(
 
                         let _ = session.text(error_msg).await;
                     } else {
-                        tracing::debug!("Message received: {string}");
+                        tracing::debug!("Message received from session {uuid}");
 
                         let process_result =
-                            handler::process_text_msg(&state.db, &server, &uuid, &string).await;
+                            handler::process_text_msg(&state, &server, &uuid, &string).await;
 
                         if let Ok(message) = process_result {
                             let msg = serde_json::to_string(&message)
@@ -233,3 +271,197 @@ pub async fn gateway(
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(web::scope("/ws").service(setup_ws).service(gateway));
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use actix_web::{web, App};
+    use awc::ws;
+    use futures_util::{SinkExt, StreamExt};
+    use surrealdb::{engine::any::Any, Surreal};
+
+    use crate::websockets::config::WebSocketConfig;
+    use crate::websockets::types::common::{SessionCredential, WebSocketTokenData};
+    use crate::websockets::WebSocketServer;
+    use crate::AppState;
+
+    type Connection = actix_codec::Framed<awc::BoxedSocket, ws::Codec>;
+
+    fn start(config: WebSocketConfig) -> (actix_test::TestServer, WebSocketServer) {
+        let server = WebSocketServer::with_config(config);
+        let state = web::Data::new(AppState {
+            db: Arc::new(Surreal::<Any>::init()),
+            auth_tracker: Default::default(),
+        });
+
+        let ws_server = server.clone();
+        let srv = actix_test::start(move || {
+            App::new()
+                .app_data(state.clone())
+                .app_data(web::Data::new(ws_server.clone()))
+                .configure(super::config)
+        });
+
+        (srv, server)
+    }
+
+    async fn connect(
+        srv: &actix_test::TestServer,
+        server: &WebSocketServer,
+        data: WebSocketTokenData,
+    ) -> Connection {
+        let token = server.obtain_token(data).await;
+
+        let (_response, conn) = awc::Client::new()
+            .ws(srv.url(&format!("/ws/gateway/{token}")))
+            .connect()
+            .await
+            .expect("Failed to connect to the gateway");
+
+        conn
+    }
+
+    /// Read the next message, skipping pings and keepalives. `None` once the server closed the session.
+    async fn next_message(conn: &mut Connection) -> Option<serde_json::Value> {
+        loop {
+            let frame = actix_web::rt::time::timeout(Duration::from_secs(5), conn.next())
+                .await
+                .expect("Timed out waiting for a message")?
+                .ok()?;
+
+            match frame {
+                ws::Frame::Text(bytes) => {
+                    let value: serde_json::Value =
+                        serde_json::from_slice(&bytes).expect("Got invalid JSON");
+                    if value["type"] != "keepalive" {
+                        return Some(value);
+                    }
+                }
+                ws::Frame::Close(_) => return None,
+                _ => (),
+            }
+        }
+    }
+
+    fn authed_token(address: &str) -> WebSocketTokenData {
+        let credential = SessionCredential::derive(address, "test123");
+
+        WebSocketTokenData::new(address.to_owned(), Some(credential))
+    }
+
+    #[actix_web::test]
+    async fn test_sessions_per_ip_limit() {
+        let (srv, server) = start(WebSocketConfig {
+            max_sessions_per_ip: 3,
+            ..Default::default()
+        });
+
+        let mut connections = Vec::new();
+        for _ in 0..3 {
+            let mut conn = connect(&srv, &server, WebSocketTokenData::guest()).await;
+            let hello = next_message(&mut conn).await.expect("Expected hello");
+            assert_eq!(hello["type"], "hello");
+
+            connections.push(conn);
+        }
+
+        for _ in 0..5 {
+            let mut conn = connect(&srv, &server, WebSocketTokenData::guest()).await;
+            let error = next_message(&mut conn).await.expect("Expected an error");
+            assert_eq!(error["error"], "too_many_sessions");
+        }
+
+        assert_eq!(server.stats().await.sessions, 3);
+    }
+
+    #[actix_web::test]
+    async fn test_sessions_per_address_limit() {
+        let (srv, server) = start(WebSocketConfig {
+            max_sessions_per_address: 2,
+            ..Default::default()
+        });
+
+        let mut connections = Vec::new();
+        for _ in 0..2 {
+            let mut conn = connect(&srv, &server, authed_token("kabcdefghi")).await;
+            let hello = next_message(&mut conn).await.expect("Expected hello");
+            assert_eq!(hello["type"], "hello");
+
+            connections.push(conn);
+        }
+
+        let mut conn = connect(&srv, &server, authed_token("kabcdefghi")).await;
+        let error = next_message(&mut conn).await.expect("Expected an error");
+        assert_eq!(error["error"], "too_many_sessions");
+
+        // Other addresses and guests are not affected.
+        for data in [authed_token("kzyxwvutsr"), WebSocketTokenData::guest()] {
+            let mut conn = connect(&srv, &server, data).await;
+            let hello = next_message(&mut conn).await.expect("Expected hello");
+            assert_eq!(hello["type"], "hello");
+
+            connections.push(conn);
+        }
+
+        let stats = server.stats().await;
+        assert_eq!(stats.authed, 3);
+        assert_eq!(stats.guests, 1);
+    }
+
+    #[actix_web::test]
+    async fn test_message_rate_limit() {
+        let (srv, server) = start(WebSocketConfig {
+            message_burst: 3,
+            messages_per_second: 0.01,
+            ..Default::default()
+        });
+
+        let mut conn = connect(&srv, &server, WebSocketTokenData::guest()).await;
+        next_message(&mut conn).await.expect("Expected hello");
+
+        for id in 0..5 {
+            let msg = format!(r#"{{"type":"get_valid_subscription_levels","id":{id}}}"#);
+            conn.send(ws::Message::Text(msg.into())).await.unwrap();
+        }
+
+        for id in 0..3 {
+            let response = next_message(&mut conn).await.expect("Expected a response");
+            assert_eq!(response["ok"], true);
+            assert_eq!(response["id"], id);
+        }
+
+        for _ in 0..2 {
+            let error = next_message(&mut conn).await.expect("Expected an error");
+            assert_eq!(error["error"], "rate_limit_hit");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_idle_guests_are_evicted() {
+        let (srv, server) = start(WebSocketConfig {
+            guest_idle_timeout: Duration::ZERO,
+            ..Default::default()
+        });
+
+        let mut authed = connect(&srv, &server, authed_token("kabcdefghi")).await;
+        next_message(&mut authed).await.expect("Expected hello");
+
+        let mut guests = Vec::new();
+        for _ in 0..5 {
+            let mut conn = connect(&srv, &server, WebSocketTokenData::guest()).await;
+            next_message(&mut conn).await.expect("Expected hello");
+
+            guests.push(conn);
+        }
+
+        for conn in guests.iter_mut() {
+            assert!(next_message(conn).await.is_none());
+        }
+
+        let stats = server.stats().await;
+        assert_eq!(stats.guests, 0);
+        assert_eq!(stats.authed, 1);
+    }
+}
@@ -2,9 +2,12 @@ mod name;
 mod transaction;
 mod wallet;
 
-use actix_web::{get, web, HttpResponse};
+use actix_web::{get, web, HttpRequest, HttpResponse};
 
-use crate::errors::KromerError;
+use crate::auth;
+use crate::database::models::wallet::Model as Wallet;
+use crate::errors::{wallet::WalletError, KromerError};
+use crate::AppState;
 
 static VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -13,6 +16,34 @@ pub struct LoginDetail {
     pub password: String,
 }
 
+/// Verify a v1 password, locking out IPs that fail too often.
+///
+/// The password does not tell which address it belongs to, so only the IP is tracked.
+async fn verify_password(
+    req: &HttpRequest,
+    state: &AppState,
+    password: String,
+) -> Result<Wallet, KromerError> {
+    let ip = auth::request_ip(req);
+    if let Some(locked_for) = state.auth_tracker.locked_for(ip, None) {
+        tracing::info!("Refusing v1 login from {ip:?}, locked out");
+        return Err(KromerError::Wallet(WalletError::TooManyAttempts(
+            locked_for.as_secs().max(1),
+        )));
+    }
+
+    match Wallet::verify(&state.db, password).await? {
+        Some(wallet) => {
+            state.auth_tracker.record_success(&wallet.address);
+            Ok(wallet)
+        }
+        None => {
+            state.auth_tracker.record_failure(ip, None);
+            Err(KromerError::Wallet(WalletError::InvalidPassword))
+        }
+    }
+}
+
 #[get("/")]
 async fn index_get() -> Result<HttpResponse, KromerError> {
     Ok(HttpResponse::Ok().body("Hello, world!"))
@@ -1,14 +1,19 @@
-use actix_web::{get, post, web, HttpResponse};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 
+use crate::audit::{Actor, AuditEntry, RequestInfo};
+use crate::database::models::allowance::{Model as Allowance, SpendOutcome};
 use crate::database::models::transaction::{Model as Transaction, TransactionCreateData};
 use crate::database::models::wallet::Model as Wallet;
 
 use crate::errors::wallet::WalletError;
-use crate::models::transactions::TransactionType;
+use crate::models::transactions::{TransactionJson, TransactionType};
+use crate::websockets::WebSocketServer;
 use crate::{
+    allowances,
     errors::{transaction::TransactionError, KromerError},
+    invoices,
     routes::PaginationParams,
     AppState,
 };
@@ -19,6 +24,8 @@ struct TransactionDetails {
     pub to: String,
     pub amount: Decimal,
     pub metadata: Option<String>,
+    /// Spend from this wallet through the allowance it granted the sender instead.
+    pub from: Option<String>,
 }
 
 #[get("/list")]
@@ -49,40 +56,97 @@ async fn transaction_get(
 
 #[post("/create")]
 async fn transaction_create(
+    req: HttpRequest,
+    request: RequestInfo,
     state: web::Data<AppState>,
+    server: web::Data<WebSocketServer>,
     details: web::Json<TransactionDetails>,
 ) -> Result<HttpResponse, KromerError> {
     let details = details.into_inner();
     let db = &state.db;
 
     // Check on the server so DB doesnt throw.
-    if details.amount < dec!(0.0) {
+    if details.amount <= dec!(0.0) {
         return Err(KromerError::Transaction(TransactionError::InvalidAmount));
     }
 
-    let sender = Wallet::verify(db, details.password)
-        .await?
-        .ok_or_else(|| KromerError::Wallet(WalletError::InvalidPassword))?;
+    let sender = super::verify_password(&req, &state, details.password).await?;
     let recipient = Wallet::get_by_address(db, details.to)
         .await?
         .ok_or_else(|| KromerError::Wallet(WalletError::NotFound))?;
+    if recipient.locked {
+        return Err(KromerError::Wallet(WalletError::Locked));
+    }
+
+    // Spending from another wallet, its allowance and balance are checked along with the transfer.
+    let owner = details.from.filter(|from| *from != sender.address);
 
     // Make sure to check the request to see if the funds are available.
-    if sender.balance < details.amount {
+    if owner.is_none() && sender.balance < details.amount {
         return Err(KromerError::Transaction(
             TransactionError::InsufficientFunds,
         ));
     }
 
-    let creation_data = TransactionCreateData {
-        from: sender.address,
-        to: recipient.address,
-        amount: details.amount,
-        metadata: details.metadata,
-        transaction_type: TransactionType::Transfer,
+    let response = match owner {
+        Some(owner) => {
+            let metadata = allowances::spender_meta(details.metadata, &sender.address);
+            let outcome = Allowance::spend(
+                db,
+                owner.clone(),
+                sender.address.clone(),
+                recipient.address.clone(),
+                details.amount,
+                Some(metadata),
+            )
+            .await?;
+
+            match outcome {
+                SpendOutcome::Spent { transaction } => *transaction,
+                SpendOutcome::NoAllowance => {
+                    return Err(KromerError::Forbidden(format!(
+                        "{owner} has not granted you an allowance"
+                    )));
+                }
+                SpendOutcome::RecipientNotAllowed => {
+                    return Err(KromerError::Forbidden(format!(
+                        "The allowance does not cover transfers to {}",
+                        recipient.address
+                    )));
+                }
+                SpendOutcome::ExceedsAllowance { remaining } => {
+                    return Err(KromerError::Forbidden(format!(
+                        "Only {remaining} of the allowance can still be spent"
+                    )));
+                }
+                SpendOutcome::InsufficientFunds => {
+                    return Err(KromerError::Transaction(
+                        TransactionError::InsufficientFunds,
+                    ));
+                }
+            }
+        }
+        None => {
+            let creation_data = TransactionCreateData {
+                from: sender.address.clone(),
+                to: recipient.address,
+                amount: details.amount,
+                metadata: details.metadata,
+                transaction_type: TransactionType::Transfer,
+            };
+            let response: Vec<Transaction> =
+                db.insert("transaction").content(creation_data).await?;
+            response.first().unwrap().clone() // the fuck man
+        }
     };
-    let response: Vec<Transaction> = db.insert("transaction").content(creation_data).await?;
-    let response = response.first().unwrap(); // the fuck man
+
+    AuditEntry::new(Actor::Wallet(sender.address), "transaction.create")
+        .target(&response.to)
+        .after(TransactionJson::from(response.clone()))
+        .request(&request)
+        .write(db)
+        .await;
+    invoices::link_payment(db, &server, &response).await;
 
     Ok(HttpResponse::Ok().json(response))
 }
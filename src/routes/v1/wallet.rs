@@ -1,4 +1,4 @@
-use actix_web::{get, post, web, HttpResponse};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
 use serde_json::json;
 
 use crate::database::models::wallet::Model as Wallet;
@@ -7,19 +7,17 @@ use crate::errors::KromerError;
 use crate::routes::PaginationParams;
 use crate::AppState;
 
-use crate::routes::v1::LoginDetail;
+use crate::routes::v1::{verify_password, LoginDetail};
 
 #[post("/verify")]
 async fn wallet_verify(
+    req: HttpRequest,
     state: web::Data<AppState>,
     detail: web::Json<LoginDetail>,
 ) -> Result<HttpResponse, KromerError> {
     let detail = detail.into_inner();
-    let db = &state.db;
 
-    let wallet = Wallet::verify(db, detail.password)
-        .await?
-        .ok_or_else(|| KromerError::Wallet(WalletError::InvalidPassword))?;
+    let wallet = verify_password(&req, &state, detail.password).await?;
 
     Ok(HttpResponse::Ok().json(json!({
         "address": wallet.address
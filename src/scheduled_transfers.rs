@@ -0,0 +1,38 @@
+//! One-off transfers made at a later time, e.g. event rewards that vest on a given date.
+//!
+//! Scheduling a transfer moves its funds from the sender's `balance` to their `pending_out`, and
+//! counts them towards the recipient's `pending_in`, so both see it coming. A background job makes
+//! the due ones, see [`crate::jobs::scheduled_transfers`]. The funds go back to the sender's balance
+//! and are then sent with a normal `transfer` transaction tagged with `scheduled_transfer=<id>`.
+use surrealdb::{engine::any::Any, Surreal};
+
+use crate::database::models::scheduled_transfer::{ExecutionUpdate, Model as ScheduledTransfer};
+use crate::invoices;
+use crate::models::websockets::{WebSocketEvent, WebSocketMessage};
+use crate::websockets::WebSocketServer;
+
+/// Send the sender and recipient of a scheduled transfer its current state.
+pub async fn notify(server: &WebSocketServer, transfer: ScheduledTransfer) {
+    let event = WebSocketMessage::new_event(WebSocketEvent::ScheduledTransfer {
+        transfer: transfer.into(),
+    });
+    server.broadcast_event(event).await;
+}
+
+/// Announce an executed scheduled transfer, and link its transaction to the invoice it pays.
+pub async fn announce_execution(
+    db: &Surreal<Any>,
+    server: &WebSocketServer,
+    update: ExecutionUpdate,
+) {
+    if let Some(transaction) = &update.transaction {
+        let event = WebSocketMessage::new_event(WebSocketEvent::Transaction {
+            transaction: transaction.clone().into(),
+        });
+        server.broadcast_event(event).await;
+
+        invoices::link_payment(db, server, transaction).await;
+    }
+
+    notify(server, update.transfer).await;
+}
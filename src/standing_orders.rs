@@ -0,0 +1,34 @@
+//! Recurring transfers made on behalf of a wallet.
+//!
+//! A background job makes the transfers of due standing orders, see [`crate::jobs::standing_orders`].
+//! Each one is a normal `transfer` transaction tagged with `standing_order=<id>`, so it is announced
+//! and linked to the invoice it pays like any other transfer.
+use surrealdb::{engine::any::Any, Surreal};
+
+use crate::database::models::standing_order::Model as StandingOrder;
+use crate::database::models::transaction;
+use crate::invoices;
+use crate::models::websockets::{WebSocketEvent, WebSocketMessage};
+use crate::websockets::WebSocketServer;
+
+/// Send the parties of a standing order its current state.
+pub async fn notify(server: &WebSocketServer, order: StandingOrder) {
+    let event = WebSocketMessage::new_event(WebSocketEvent::StandingOrder {
+        order: order.into(),
+    });
+    server.broadcast_event(event).await;
+}
+
+/// Announce a transfer made by a standing order, and link it to the invoice it pays.
+pub async fn announce_transfer(
+    db: &Surreal<Any>,
+    server: &WebSocketServer,
+    transaction: &transaction::Model,
+) {
+    let event = WebSocketMessage::new_event(WebSocketEvent::Transaction {
+        transaction: transaction.clone().into(),
+    });
+    server.broadcast_event(event).await;
+
+    invoices::link_payment(db, server, transaction).await;
+}
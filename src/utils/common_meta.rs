@@ -0,0 +1,41 @@
+//! Helpers for CommonMeta transaction metadata, e.g. `meta@name.kst;return=kabcdefghi;message=Thanks`.
+//!
+//! Entries are separated by `;`, named entries are `key=value` pairs.
+
+/// Get the value of the first entry named `key`.
+///
+/// # Examples
+/// ```
+/// # use kromer::utils::common_meta;
+/// let metadata = "shop@store.kst;return=kabcdefghi;message=Thanks";
+/// assert_eq!(common_meta::get(metadata, "return"), Some("kabcdefghi"));
+/// assert_eq!(common_meta::get(metadata, "ref"), None);
+/// ```
+pub fn get<'a>(metadata: &'a str, key: &str) -> Option<&'a str> {
+    metadata
+        .split(';')
+        .filter_map(|entry| entry.split_once('='))
+        .find(|(name, _)| name.trim() == key)
+        .map(|(_, value)| value.trim())
+}
+
+/// Join entries into metadata, leaving out empty ones.
+///
+/// # Examples
+/// ```
+/// # use kromer::utils::common_meta;
+/// let metadata = common_meta::join(["ref=abc", "", "message=Out of stock"]);
+/// assert_eq!(metadata, "ref=abc;message=Out of stock");
+/// ```
+pub fn join<I, S>(entries: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    entries
+        .into_iter()
+        .filter(|entry| !entry.as_ref().is_empty())
+        .map(|entry| entry.as_ref().to_owned())
+        .collect::<Vec<_>>()
+        .join(";")
+}
@@ -1,6 +1,24 @@
+use std::env;
+
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
 use rand::{distr::Uniform, Rng};
 use sha2::{Digest, Sha256};
 
+/// Secret that keys password fingerprints, so a leaked database can't be used to brute force them.
+static FINGERPRINT_KEY: Lazy<Option<String>> = Lazy::new(|| {
+    let key = env::var("WALLET_FINGERPRINT_KEY")
+        .ok()
+        .filter(|key| !key.is_empty());
+    if key.is_none() {
+        tracing::warn!(
+            "WALLET_FINGERPRINT_KEY is not set, v1 password lookups fall back to a scan"
+        );
+    }
+
+    key
+});
+
 pub fn generate_random_password() -> String {
     // Define the character set, including letters, digits, underscores, and hyphens
     let charset: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ\
@@ -13,6 +31,11 @@ pub fn generate_random_password() -> String {
     (0..32).map(|_| charset[rng.sample(dist)] as char).collect()
 }
 
+/// Generate the secret for a new API token, only its hash is ever stored.
+pub fn generate_api_token() -> String {
+    format!("kt_{}", generate_random_password())
+}
+
 pub fn sha256(data: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(data.as_bytes());
@@ -24,6 +47,45 @@ pub fn double_sha256(data: &str) -> String {
     sha256(&first_hash)
 }
 
+/// Keyed fingerprint of a v1 password, used to find its wallet before checking the argon2 hash.
+///
+/// Returns `None` when no fingerprint key is configured.
+pub fn password_fingerprint(password: &str) -> Option<String> {
+    let key = FINGERPRINT_KEY.as_ref()?;
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(password.as_bytes());
+
+    Some(hex::encode(mac.finalize().into_bytes()))
+}
+
+/// How a Krist-style wallet hash was made.
+///
+/// Argon2 hashes are stored as PHC strings, their `$argon2` prefix doubles as the version tag.
+/// Anything else is a legacy unsalted digest from before wallets were hashed with argon2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletHashVersion {
+    LegacySha256,
+    Argon2,
+}
+
+impl WalletHashVersion {
+    pub fn of(hash: &str) -> Self {
+        if hash.starts_with("$argon2") {
+            Self::Argon2
+        } else {
+            Self::LegacySha256
+        }
+    }
+}
+
+/// Digest used to authenticate Krist-style wallets, `sha256(address + privatekey)`.
+///
+/// Wallets store an argon2 hash of this digest, so sessions can be re-verified without the private key.
+pub fn make_wallet_digest(address: &str, private_key: &str) -> String {
+    sha256(&format!("{address}{private_key}"))
+}
+
 fn hex_to_base36(byte: u8) -> char {
     let res_byte = match byte / 7 {
         byte @ 0..=9 => byte + b'0',
@@ -34,6 +96,11 @@ fn hex_to_base36(byte: u8) -> char {
     res_byte as char
 }
 
+/// Krist v1 addresses are the first 10 hex characters of `sha256(privatekey)`.
+pub fn make_v1_address(key: &str) -> String {
+    sha256(key)[..10].to_owned()
+}
+
 pub fn make_v2_address(key: &str, address_prefix: &str) -> String {
     let mut protein = [0u8; 9];
     let mut used = [false; 9];
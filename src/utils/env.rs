@@ -0,0 +1,12 @@
+use std::{env, str::FromStr};
+
+/// Read and parse an environment variable, falling back to the default when it is unset or invalid.
+pub fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            tracing::warn!("Invalid value for {name}, using the default");
+            default
+        }),
+        Err(_) => default,
+    }
+}
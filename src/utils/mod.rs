@@ -1,2 +1,6 @@
+pub mod common_meta;
 pub mod crypto;
+pub mod env;
+pub mod net;
 pub mod validation_kromer;
+pub mod wallet_format;
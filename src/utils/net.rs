@@ -0,0 +1,40 @@
+use std::net::{IpAddr, SocketAddr};
+
+use actix_web::dev::RequestHead;
+use actix_web::HttpRequest;
+
+/// Find the IP address of the client that sent a request.
+///
+/// `X-Forwarded-For` and friends can be set by anyone, so they are only used when `trust_forwarded_for` is set.
+pub fn client_ip(req: &HttpRequest, trust_forwarded_for: bool) -> Option<IpAddr> {
+    if trust_forwarded_for {
+        let info = req.connection_info();
+        let addr = info.realip_remote_addr()?;
+
+        // The peer address is used as a fallback, which includes a port.
+        return addr
+            .parse::<IpAddr>()
+            .ok()
+            .or_else(|| addr.parse::<SocketAddr>().ok().map(|addr| addr.ip()));
+    }
+
+    req.peer_addr().map(|addr| addr.ip())
+}
+
+/// Like [`client_ip`], for guards which only get to see the request head.
+pub fn head_ip(head: &RequestHead, trust_forwarded_for: bool) -> Option<IpAddr> {
+    if trust_forwarded_for {
+        let forwarded = ["x-forwarded-for", "x-real-ip"]
+            .into_iter()
+            .find_map(|name| {
+                let value = head.headers().get(name)?.to_str().ok()?;
+                value.split(',').next()?.trim().parse::<IpAddr>().ok()
+            });
+
+        if forwarded.is_some() {
+            return forwarded;
+        }
+    }
+
+    head.peer_addr.map(|addr| addr.ip())
+}
@@ -1,6 +1,7 @@
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+static ADDRESS_RE_V1: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-f0-9]{10}$").unwrap());
 static ADDRESS_RE_V2: Lazy<Regex> = Lazy::new(|| Regex::new(r"^k[a-z0-9]{9}$").unwrap());
 static ADDRESS_LIST_RE: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^(?:k[a-z0-9]{9}|[a-f0-9]{10})(?:,(?:k[a-z0-9]{9}|[a-f0-9]{10}))*$").unwrap()
@@ -28,6 +29,11 @@ pub fn is_valid_kromer_address(address: &str) -> bool {
     ADDRESS_RE_V2.is_match(address)
 }
 
+#[inline(always)]
+pub fn is_valid_v1_address(address: &str) -> bool {
+    ADDRESS_RE_V1.is_match(address)
+}
+
 #[inline(always)]
 pub fn is_valid_kromer_address_list(address_list: &str) -> bool {
     ADDRESS_LIST_RE.is_match(address_list)
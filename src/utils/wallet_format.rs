@@ -0,0 +1,99 @@
+//! Formats Krist clients use to turn a wallet password into a private key.
+use serde::{Deserialize, Serialize};
+
+use super::crypto::sha256;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalletFormat {
+    /// `sha256("KRISTWALLET" + password) + "-000"`, used by KristWallet and most CC wallets.
+    Kristwallet,
+    /// KristWallet's username extension, with the `-000` suffix.
+    KristwalletUsernameAppendhashes,
+    /// KristWallet's username extension.
+    KristwalletUsername,
+    /// 18 rounds of sha256 over the password.
+    Jwalelset,
+    /// The password is the private key.
+    #[default]
+    Api,
+}
+
+impl WalletFormat {
+    /// Derive the private key from a password, returns `None` when the format needs a username that was not given.
+    pub fn derive_key(&self, password: &str, username: Option<&str>) -> Option<String> {
+        let key = match self {
+            WalletFormat::Kristwallet => {
+                format!("{}-000", sha256(&format!("KRISTWALLET{password}")))
+            }
+            WalletFormat::KristwalletUsernameAppendhashes => {
+                format!("{}-000", username_extension(password, username?))
+            }
+            WalletFormat::KristwalletUsername => username_extension(password, username?),
+            WalletFormat::Jwalelset => (0..18).fold(password.to_owned(), |key, _| sha256(&key)),
+            WalletFormat::Api => password.to_owned(),
+        };
+
+        Some(key)
+    }
+}
+
+fn username_extension(password: &str, username: &str) -> String {
+    let inner = sha256(&format!("{}^{}", sha256(username), sha256(password)));
+
+    sha256(&format!("KRISTWALLETEXTENSION{inner}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::crypto::make_v2_address;
+    use super::*;
+
+    /// The private key and address each format gives `password`, with `username` where one is needed.
+    const VECTORS: [(WalletFormat, &str, &str); 5] = [
+        (
+            WalletFormat::Kristwallet,
+            "5be219a6073f621cb72ef49916ed1fff0f8300b2dd54dad6c248d8c5fe3ed6cf-000",
+            "kabi8gw3cg",
+        ),
+        (
+            WalletFormat::KristwalletUsernameAppendhashes,
+            "2066bf2ca3c32f96bc04e7f5a78dfe58ebf3ab55b0ad86b642af67ce733ecb28-000",
+            "kljja67esf",
+        ),
+        (
+            WalletFormat::KristwalletUsername,
+            "2066bf2ca3c32f96bc04e7f5a78dfe58ebf3ab55b0ad86b642af67ce733ecb28",
+            "kl04uov82t",
+        ),
+        (
+            WalletFormat::Jwalelset,
+            "6f472a976c499fcdb52853ee865ebed84da8fade16ce1af4d826c5765455f7e6",
+            "kno3mjmfe2",
+        ),
+        (WalletFormat::Api, "password", "kuf56v2ikn"),
+    ];
+
+    #[test]
+    fn derives_known_keys_and_addresses() {
+        for (format, key, address) in VECTORS {
+            let derived = format.derive_key("password", Some("username")).unwrap();
+
+            assert_eq!(derived, key, "{format:?}");
+            assert_eq!(make_v2_address(&derived, "k"), address, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn needs_a_username_for_username_formats() {
+        for format in [
+            WalletFormat::KristwalletUsername,
+            WalletFormat::KristwalletUsernameAppendhashes,
+        ] {
+            assert!(format.derive_key("password", None).is_none());
+        }
+        assert!(WalletFormat::Kristwallet
+            .derive_key("password", None)
+            .is_some());
+    }
+}
@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use crate::utils::env::env_or;
+
+/// Limits applied to WebSocket sessions.
+#[derive(Debug, Clone)]
+pub struct WebSocketConfig {
+    /// Maximum amount of concurrent sessions from a single IP.
+    pub max_sessions_per_ip: usize,
+    /// Maximum amount of concurrent sessions logged in as a single address.
+    pub max_sessions_per_address: usize,
+    /// Amount of messages a session can send in a burst before being throttled.
+    pub message_burst: u32,
+    /// Rate at which a throttled session regains messages.
+    pub messages_per_second: f64,
+    /// Guest sessions that have not sent a message for this long are disconnected.
+    pub guest_idle_timeout: Duration,
+    /// Amount of recent events kept around so event stream clients can resume.
+    pub event_history: usize,
+    /// Use the `X-Forwarded-For` header to find the IP of a client, only enable this behind a proxy.
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self {
+            max_sessions_per_ip: 16,
+            max_sessions_per_address: 8,
+            message_burst: 20,
+            messages_per_second: 5.0,
+            guest_idle_timeout: Duration::from_secs(300),
+            event_history: 1000,
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+impl WebSocketConfig {
+    /// Read the config from the environment, anything that is not set falls back to the default.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            max_sessions_per_ip: env_or("WS_MAX_SESSIONS_PER_IP", default.max_sessions_per_ip),
+            max_sessions_per_address: env_or(
+                "WS_MAX_SESSIONS_PER_ADDRESS",
+                default.max_sessions_per_address,
+            ),
+            message_burst: env_or("WS_MESSAGE_BURST", default.message_burst),
+            messages_per_second: env_or("WS_MESSAGES_PER_SECOND", default.messages_per_second),
+            guest_idle_timeout: Duration::from_secs(env_or(
+                "WS_GUEST_IDLE_TIMEOUT",
+                default.guest_idle_timeout.as_secs(),
+            )),
+            event_history: env_or("WS_EVENT_HISTORY", default.event_history),
+            trust_forwarded_for: env_or("WS_TRUST_FORWARDED_FOR", default.trust_forwarded_for),
+        }
+    }
+}
@@ -6,12 +6,20 @@ use crate::errors::krist::KristErrorExt;
 pub enum WebSocketServerError {
     #[error("WebSocket token was not found in cache")]
     TokenNotFound,
+
+    #[error("Too many sessions from this IP")]
+    TooManySessionsForIp,
+
+    #[error("Too many sessions for this address")]
+    TooManySessionsForAddress,
 }
 
 impl KristErrorExt for WebSocketServerError {
     fn error_type(&self) -> &'static str {
         match self {
             WebSocketServerError::TokenNotFound => "token_not_found",
+            WebSocketServerError::TooManySessionsForIp
+            | WebSocketServerError::TooManySessionsForAddress => "too_many_sessions",
         }
     }
 }
@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+use std::net::IpAddr;
+
+use actix_web::web::Bytes;
+use dashmap::DashSet;
+use tokio::sync::mpsc;
+
+use super::types::common::WebSocketSubscriptionType;
+use crate::models::websockets::WebSocketEvent;
+
+/// Amount of frames an event stream client can fall behind before it gets disconnected.
+pub const EVENT_STREAM_BUFFER: usize = 256;
+
+/// An event that was broadcast, kept around so event stream clients can resume.
+#[derive(Debug, Clone)]
+pub struct RecordedEvent {
+    pub id: u64,
+    pub event: WebSocketEvent,
+    /// The event as it was sent to websocket clients.
+    pub data: String,
+}
+
+/// Ring buffer of the most recently broadcast events.
+#[derive(Debug, Clone)]
+pub struct EventHistory {
+    capacity: usize,
+    next_id: u64,
+    events: VecDeque<RecordedEvent>,
+}
+
+/// A client connected to the server-sent events stream.
+#[derive(Debug, Clone)]
+pub struct EventStreamClient {
+    /// The authenticated address, `None` for guests.
+    pub address: Option<String>,
+    /// Only receive events involving this address.
+    pub filter: Option<String>,
+    pub subscriptions: DashSet<WebSocketSubscriptionType>,
+    pub ip: Option<IpAddr>,
+    pub sender: mpsc::Sender<Bytes>,
+}
+
+impl EventHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            next_id: 1,
+            events: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Record an event, returning it with its assigned ID.
+    pub fn push(&mut self, event: WebSocketEvent, data: String) -> RecordedEvent {
+        let recorded = RecordedEvent {
+            id: self.next_id,
+            event,
+            data,
+        };
+        self.next_id += 1;
+
+        if self.capacity > 0 {
+            if self.events.len() == self.capacity {
+                self.events.pop_front();
+            }
+            self.events.push_back(recorded.clone());
+        }
+
+        recorded
+    }
+
+    /// All events still in the history that came after the given ID.
+    pub fn since(&self, last_id: u64) -> impl Iterator<Item = &RecordedEvent> {
+        self.events.iter().filter(move |event| event.id > last_id)
+    }
+}
+
+impl EventStreamClient {
+    pub fn wants(&self, event: &WebSocketEvent) -> bool {
+        let involved = match &self.filter {
+            Some(filter) => involves(event, filter),
+            None => true,
+        };
+
+        involved && is_subscribed(event, &self.subscriptions, self.address.as_deref())
+    }
+}
+
+impl RecordedEvent {
+    /// Format the event as a server-sent event frame.
+    pub fn to_frame(&self) -> Bytes {
+        Bytes::from(format!("id: {}\ndata: {}\n\n", self.id, self.data))
+    }
+}
+
+/// Whether a client with the given subscriptions should receive an event.
+///
+/// `address` is the address the client is authenticated as, guests never receive events for their "own" subscriptions.
+pub fn is_subscribed(
+    event: &WebSocketEvent,
+    subscriptions: &DashSet<WebSocketSubscriptionType>,
+    address: Option<&str>,
+) -> bool {
+    let owns = |owner: &str| address.is_some_and(|address| address == owner);
+
+    match event {
+        WebSocketEvent::Block { block, .. } => {
+            subscriptions.contains(&WebSocketSubscriptionType::Blocks)
+                || (owns(&block.address)
+                    && subscriptions.contains(&WebSocketSubscriptionType::OwnBlocks))
+        }
+        WebSocketEvent::Transaction { transaction } => {
+            subscriptions.contains(&WebSocketSubscriptionType::Transactions)
+                || ((owns(&transaction.to) || owns(&transaction.from))
+                    && subscriptions.contains(&WebSocketSubscriptionType::OwnTransactions))
+        }
+        WebSocketEvent::Name { name } => {
+            subscriptions.contains(&WebSocketSubscriptionType::Names)
+                || (owns(&name.owner)
+                    && subscriptions.contains(&WebSocketSubscriptionType::OwnNames))
+        }
+        // Only ever sent to the owner, there is no public variant.
+        WebSocketEvent::Security { address, .. } => {
+            owns(address) && subscriptions.contains(&WebSocketSubscriptionType::Security)
+        }
+        WebSocketEvent::Invoice { invoice } => {
+            owns(&invoice.merchant) && subscriptions.contains(&WebSocketSubscriptionType::Invoices)
+        }
+        WebSocketEvent::Escrow { escrow } => {
+            escrow.parties().any(owns)
+                && subscriptions.contains(&WebSocketSubscriptionType::Escrows)
+        }
+        WebSocketEvent::StandingOrder { order } => {
+            (owns(&order.from) || owns(&order.to))
+                && subscriptions.contains(&WebSocketSubscriptionType::StandingOrders)
+        }
+        WebSocketEvent::Hold { hold } => {
+            (owns(&hold.payer) || owns(&hold.merchant))
+                && subscriptions.contains(&WebSocketSubscriptionType::Holds)
+        }
+        WebSocketEvent::ScheduledTransfer { transfer } => {
+            (owns(&transfer.from) || owns(&transfer.to))
+                && subscriptions.contains(&WebSocketSubscriptionType::ScheduledTransfers)
+        }
+    }
+}
+
+/// Whether an address took part in an event.
+pub fn involves(event: &WebSocketEvent, address: &str) -> bool {
+    match event {
+        WebSocketEvent::Block { block, .. } => block.address == address,
+        WebSocketEvent::Transaction { transaction } => {
+            transaction.to == address || transaction.from == address
+        }
+        WebSocketEvent::Name { name } => name.owner == address,
+        WebSocketEvent::Security {
+            address: target, ..
+        } => target == address,
+        WebSocketEvent::Invoice { invoice } => invoice.merchant == address,
+        WebSocketEvent::Escrow { escrow } => escrow.parties().any(|party| party == address),
+        WebSocketEvent::StandingOrder { order } => order.from == address || order.to == address,
+        WebSocketEvent::Hold { hold } => hold.payer == address || hold.merchant == address,
+        WebSocketEvent::ScheduledTransfer { transfer } => {
+            transfer.from == address || transfer.to == address
+        }
+    }
+}
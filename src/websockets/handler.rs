@@ -1,5 +1,5 @@
 use chrono::Utc;
-use surrealdb::{engine::any::Any, Surreal, Uuid};
+use surrealdb::Uuid;
 
 use super::{types::convert_to_iso_string, WebSocketServer};
 use crate::{
@@ -9,14 +9,16 @@ use crate::{
         websockets::{WebSocketMessage, WebSocketMessageInner},
     },
     websockets::routes,
+    AppState,
 };
 
 pub async fn process_text_msg(
-    db: &Surreal<Any>,
+    state: &AppState,
     server: &WebSocketServer,
     uuid: &Uuid,
     text: &str,
 ) -> Result<WebSocketMessage, KromerError> {
+    let db = &state.db;
     // strip leading and trailing whitespace (spaces, newlines, etc.)
     let msg = text.trim();
 
@@ -33,7 +35,8 @@ pub async fn process_text_msg(
     };
 
     let msg_type = parsed_msg.r#type;
-    tracing::debug!("Message type was: {:?}", msg_type);
+    // Don't log the message itself, it may contain a privatekey.
+    tracing::debug!("Processing message for session {uuid}");
     let msg_id = parsed_msg.id; // NOTE: This is probably gonna error, lol
 
     let msg: WebSocketMessage = match msg_type {
@@ -44,9 +47,46 @@ pub async fn process_text_msg(
             let fetch_names = fetch_names.unwrap_or(false);
             routes::addresses::get_address(db, address, fetch_names, msg_id).await
         }
-        WebSocketMessageInner::Login { private_key } => {
-            routes::auth::perform_login(db, server, uuid, private_key, msg_id).await
-        }
+        WebSocketMessageInner::Login {
+            private_key,
+            format,
+            username,
+            address,
+            nonce,
+            signature,
+        } => match (private_key, address, nonce, signature) {
+            (Some(private_key), address, _, _) => {
+                match format.derive_key(&private_key, username.as_deref()) {
+                    Some(private_key) => {
+                        routes::auth::perform_login(
+                            state,
+                            server,
+                            uuid,
+                            private_key,
+                            address,
+                            msg_id,
+                        )
+                        .await
+                    }
+                    None => routes::error::error_message(
+                        msg_id,
+                        "missing_parameter",
+                        "Missing parameter username",
+                    ),
+                }
+            }
+            (None, Some(address), Some(nonce), Some(signature)) => {
+                routes::auth::perform_signed_login(
+                    db, server, uuid, address, nonce, signature, msg_id,
+                )
+                .await
+            }
+            _ => routes::error::error_message(
+                msg_id,
+                "missing_parameter",
+                "Missing parameter privatekey",
+            ),
+        },
         WebSocketMessageInner::Logout => routes::auth::perform_logout(server, uuid, msg_id).await,
         WebSocketMessageInner::Me => routes::me::get_myself(db, server, uuid, msg_id).await,
         WebSocketMessageInner::Subscribe { event } => {
@@ -66,9 +106,51 @@ pub async fn process_text_msg(
             to,
             amount,
             metadata,
+            invoice,
+            from,
         } => {
-            routes::transactions::make_transaction(db, private_key, to, amount, metadata, msg_id)
-                .await
+            routes::transactions::make_transaction(
+                state,
+                server,
+                uuid,
+                private_key,
+                to,
+                amount,
+                metadata,
+                invoice,
+                from,
+                msg_id,
+            )
+            .await
+        }
+        WebSocketMessageInner::MakeBatchTransaction {
+            private_key,
+            transfers,
+        } => {
+            routes::transactions::make_batch_transaction(
+                state,
+                server,
+                uuid,
+                private_key,
+                transfers,
+                msg_id,
+            )
+            .await
+        }
+        WebSocketMessageInner::Transaction { id } => {
+            routes::transactions::get_transaction(db, id, msg_id).await
+        }
+        WebSocketMessageInner::AddressTransactions { address, query } => {
+            routes::addresses::get_address_transactions(db, address, query, msg_id).await
+        }
+        WebSocketMessageInner::AddressNames {
+            address,
+            pagination,
+        } => routes::addresses::get_address_names(db, address, pagination, msg_id).await,
+        WebSocketMessageInner::Name { name } => routes::names::get_name(db, name, msg_id).await,
+        WebSocketMessageInner::Supply => routes::misc::get_supply(db, msg_id).await,
+        WebSocketMessageInner::Richlist { pagination } => {
+            routes::addresses::get_richlist(db, pagination, msg_id).await
         }
         WebSocketMessageInner::Work => WebSocketMessage {
             ok: Some(true),
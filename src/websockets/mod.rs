@@ -1,22 +1,37 @@
+pub mod config;
 pub mod errors;
+pub mod events;
 pub mod handler;
 pub mod routes;
 pub mod types;
 pub mod utils;
 
 use actix_web::rt::time;
-use actix_ws::Session;
+use actix_ws::{CloseCode, CloseReason, Session};
 use bytestring::ByteString;
 use dashmap::{DashMap, DashSet};
 use errors::WebSocketServerError;
 use futures_util::{stream::FuturesUnordered, StreamExt};
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::BTreeMap,
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use surrealdb::Uuid;
 use tokio::sync::Mutex;
 
-use types::common::{WebSocketSessionData, WebSocketSubscriptionType, WebSocketTokenData};
+use config::WebSocketConfig;
+use events::{EventHistory, EventStreamClient, RecordedEvent};
+use types::{
+    common::{
+        SessionCredential, WebSocketSessionData, WebSocketSubscriptionType, WebSocketTokenData,
+    },
+    rate_limit::TokenBucket,
+    stats::{MessageRate, WebSocketServerStats},
+};
 
-use crate::models::websockets::{WebSocketEvent, WebSocketMessage, WebSocketMessageInner};
+use crate::models::websockets::{WebSocketMessage, WebSocketMessageInner};
 
 // use crate::models::websockets::WebSocketEventMessage;
 
@@ -27,12 +42,16 @@ pub const TOKEN_EXPIRATION: Duration = Duration::from_secs(30);
 #[derive(Clone)]
 pub struct WebSocketServer {
     pub inner: Arc<Mutex<WebSocketServerInner>>,
+    pub message_rate: Arc<MessageRate>,
+    pub config: Arc<WebSocketConfig>,
 }
 
 #[derive(Clone)]
 pub struct WebSocketServerInner {
     pub sessions: DashMap<Uuid, WebSocketSessionData>,
     pub pending_tokens: DashMap<Uuid, WebSocketTokenData>,
+    pub event_streams: DashMap<Uuid, EventStreamClient>,
+    pub events: EventHistory,
 }
 
 impl Default for WebSocketServer {
@@ -43,17 +62,54 @@ impl Default for WebSocketServer {
 
 impl WebSocketServer {
     pub fn new() -> Self {
+        Self::with_config(WebSocketConfig::from_env())
+    }
+
+    pub fn with_config(config: WebSocketConfig) -> Self {
         let inner = WebSocketServerInner {
             sessions: DashMap::new(),
             pending_tokens: DashMap::new(),
+            event_streams: DashMap::new(),
+            events: EventHistory::new(config.event_history),
         };
 
         Self {
             inner: Arc::new(Mutex::new(inner)),
+            message_rate: Arc::new(MessageRate::default()),
+            config: Arc::new(config),
         }
     }
 
-    pub async fn insert_session(&self, uuid: Uuid, session: Session, data: WebSocketTokenData) {
+    /// Insert a new session, fails if the IP or address already has too many sessions.
+    pub async fn insert_session(
+        &self,
+        uuid: Uuid,
+        session: Session,
+        data: WebSocketTokenData,
+        ip: Option<IpAddr>,
+    ) -> Result<(), WebSocketServerError> {
+        let inner = self.inner.lock().await;
+
+        if let Some(ip) = ip {
+            let count = inner
+                .sessions
+                .iter()
+                .filter(|entry| entry.ip == Some(ip))
+                .count();
+            if count >= self.config.max_sessions_per_ip {
+                tracing::info!("Refusing session {uuid}, too many sessions from {ip}");
+                return Err(WebSocketServerError::TooManySessionsForIp);
+            }
+        }
+
+        if data.credential.is_some()
+            && Self::count_address_sessions(&inner, &data.address)
+                >= self.config.max_sessions_per_address
+        {
+            tracing::info!("Refusing session {uuid}, too many sessions for the address");
+            return Err(WebSocketServerError::TooManySessionsForAddress);
+        }
+
         let subscriptions = DashSet::from_iter(vec![
             WebSocketSubscriptionType::OwnTransactions,
             WebSocketSubscriptionType::Blocks,
@@ -61,12 +117,72 @@ impl WebSocketServer {
 
         let session_data = WebSocketSessionData {
             address: data.address,
-            private_key: data.private_key,
+            credential: data.credential,
             session,
             subscriptions,
+            ip,
+            rate_limit: TokenBucket::new(
+                self.config.message_burst,
+                self.config.messages_per_second,
+            ),
+            last_active: Instant::now(),
+        };
+
+        inner.sessions.insert(uuid, session_data);
+
+        Ok(())
+    }
+
+    fn count_address_sessions(inner: &WebSocketServerInner, address: &str) -> usize {
+        inner
+            .sessions
+            .iter()
+            .filter(|entry| !entry.is_guest() && entry.address == address)
+            .count()
+    }
+
+    /// Check whether a session is allowed to log in as an address without going over the session limit.
+    pub async fn check_address_limit(
+        &self,
+        uuid: &Uuid,
+        address: &str,
+    ) -> Result<(), WebSocketServerError> {
+        let inner = self.inner.lock().await;
+
+        // Logging in again as the same address doesn't open another session for it.
+        let already_counted = inner
+            .sessions
+            .get(uuid)
+            .is_some_and(|entry| !entry.is_guest() && entry.address == address);
+
+        if !already_counted
+            && Self::count_address_sessions(&inner, address) >= self.config.max_sessions_per_address
+        {
+            return Err(WebSocketServerError::TooManySessionsForAddress);
+        }
+
+        Ok(())
+    }
+
+    /// Take a message out of the session's rate limit, returns false if the session is being throttled.
+    pub async fn check_rate_limit(&self, uuid: &Uuid) -> bool {
+        let inner = self.inner.lock().await;
+
+        let Some(mut entry) = inner.sessions.get_mut(uuid) else {
+            return false;
         };
 
-        self.inner.lock().await.sessions.insert(uuid, session_data);
+        entry.last_active = Instant::now();
+        entry.rate_limit.try_consume()
+    }
+
+    /// Whether a session is a guest that has not sent a message for too long.
+    pub async fn is_idle_guest(&self, uuid: &Uuid) -> bool {
+        let inner = self.inner.lock().await;
+
+        inner.sessions.get(uuid).is_some_and(|entry| {
+            entry.is_guest() && entry.last_active.elapsed() > self.config.guest_idle_timeout
+        })
     }
 
     pub async fn cleanup_session(&self, uuid: &Uuid) {
@@ -113,6 +229,25 @@ impl WebSocketServer {
         Ok(token)
     }
 
+    /// Get the address and credential of an authenticated session, `None` for guests.
+    pub async fn get_session_credential(&self, uuid: &Uuid) -> Option<(String, SessionCredential)> {
+        let inner = self.inner.lock().await;
+
+        let entry = inner.sessions.get(uuid)?;
+        let credential = entry.credential.clone()?;
+
+        Some((entry.address.clone(), credential))
+    }
+
+    /// The IP address a session connected from.
+    pub async fn session_ip(&self, uuid: &Uuid) -> Option<IpAddr> {
+        let inner = self.inner.lock().await;
+
+        let ip = inner.sessions.get(uuid)?.ip;
+
+        ip
+    }
+
     pub async fn subscribe_to_event(&self, uuid: &Uuid, event: WebSocketSubscriptionType) {
         let inner = self.inner.lock().await;
 
@@ -148,59 +283,225 @@ impl WebSocketServer {
         Vec::new()
     }
 
+    /// Replace the subscriptions of a session, returns the new subscription list if the session exists.
+    pub async fn set_subscriptions(
+        &self,
+        uuid: &Uuid,
+        subscriptions: Vec<WebSocketSubscriptionType>,
+    ) -> Option<Vec<WebSocketSubscriptionType>> {
+        let inner = self.inner.lock().await;
+
+        let entry = inner.sessions.get_mut(uuid)?;
+        entry.subscriptions.clear();
+        for subscription in subscriptions {
+            entry.subscriptions.insert(subscription);
+        }
+        tracing::info!("Subscriptions of session {uuid} were replaced");
+
+        Some(entry.subscriptions.iter().map(|x| x.clone()).collect())
+    }
+
+    /// Forcefully disconnect a session, returns whether the session existed.
+    pub async fn kick_session(&self, uuid: &Uuid, reason: Option<String>) -> bool {
+        let removed = self.inner.lock().await.sessions.remove(uuid);
+
+        match removed {
+            Some((_, data)) => {
+                tracing::info!("Kicking session {uuid}");
+                Self::close_session(data.session, reason).await;
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forcefully disconnect every session logged in as an address, returns the amount of kicked sessions.
+    pub async fn kick_address(&self, address: &str, reason: Option<String>) -> usize {
+        let kicked: Vec<(Uuid, WebSocketSessionData)> = {
+            let inner = self.inner.lock().await;
+            let uuids: Vec<Uuid> = inner
+                .sessions
+                .iter()
+                .filter(|entry| !entry.is_guest() && entry.address == address)
+                .map(|entry| *entry.key())
+                .collect();
+
+            uuids
+                .iter()
+                .filter_map(|uuid| inner.sessions.remove(uuid))
+                .collect()
+        };
+
+        for (uuid, data) in &kicked {
+            tracing::info!("Kicking session {uuid} of address {address}");
+            Self::close_session(data.session.clone(), reason.clone()).await;
+        }
+
+        kicked.len()
+    }
+
+    async fn close_session(session: Session, reason: Option<String>) {
+        let reason = CloseReason {
+            code: CloseCode::Policy,
+            description: reason,
+        };
+
+        let _ = session.close(Some(reason)).await;
+    }
+
+    /// Send a message to a single session, returns whether it was delivered.
+    pub async fn send_to_session(&self, uuid: &Uuid, msg: &WebSocketMessage) -> bool {
+        let msg = serde_json::to_string(msg).expect("Failed to turn message into a string");
+
+        let mut session = match self.inner.lock().await.sessions.get(uuid) {
+            Some(data) => data.session.clone(),
+            None => return false,
+        };
+
+        if session.text(msg).await.is_err() {
+            tracing::warn!("Got an unexpected closed session");
+            self.cleanup_session(uuid).await;
+
+            return false;
+        }
+
+        true
+    }
+
+    /// Count a message received from a client towards the message rate.
+    #[inline]
+    pub fn record_message(&self) {
+        self.message_rate.record();
+    }
+
+    pub async fn stats(&self) -> WebSocketServerStats {
+        let inner = self.inner.lock().await;
+
+        let sessions = inner.sessions.len();
+        let guests = inner
+            .sessions
+            .iter()
+            .filter(|entry| entry.is_guest())
+            .count();
+
+        let mut subscriptions = BTreeMap::new();
+        for entry in inner.sessions.iter() {
+            for subscription in entry.subscriptions.iter() {
+                *subscriptions.entry(subscription.to_string()).or_insert(0) += 1;
+            }
+        }
+
+        WebSocketServerStats {
+            sessions,
+            guests,
+            authed: sessions - guests,
+            subscriptions,
+            pending_tokens: inner.pending_tokens.len(),
+            event_streams: inner.event_streams.len(),
+            messages_per_second: self.message_rate.per_second(),
+        }
+    }
+
     /// Broadcast an event to all connected clients
     pub async fn broadcast_event(&self, event: WebSocketMessage) {
         let msg =
             serde_json::to_string(&event).expect("Failed to turn event message into a string");
         tracing::debug!("Broadcasting event: {msg}");
 
-        let inner = self.inner.lock().await;
-        let sessions = inner.sessions.iter_mut();
+        let WebSocketMessageInner::Event { event } = event.r#type else {
+            return;
+        };
 
-        for mut session in sessions {
+        let mut inner = self.inner.lock().await;
+        let recorded = inner.events.push(event, msg);
+        let event = &recorded.event;
+
+        let mut closed = Vec::new();
+        for mut session in inner.sessions.iter_mut() {
             let (uuid, client_data) = session.pair_mut();
+            let address = (!client_data.is_guest()).then_some(client_data.address.as_str());
+
+            if events::is_subscribed(event, &client_data.subscriptions, address) {
+                let result = client_data.session.text(recorded.data.clone()).await;
+                if result.is_err() {
+                    tracing::warn!("Got an unexpected closed session");
 
-            if let WebSocketMessageInner::Event { ref event } = event.r#type {
-                match event {
-                    WebSocketEvent::Block { .. } => todo!(),
-                    WebSocketEvent::Transaction { transaction } => {
-                        let mut subs = client_data.subscriptions.iter();
-                        if (!client_data.is_guest()
-                            && (client_data.address == transaction.to
-                                || client_data.address == transaction.from)
-                            && subs.any(|t| t.eq(&WebSocketSubscriptionType::OwnTransactions)))
-                            || subs.any(|t| t.eq(&WebSocketSubscriptionType::Transactions))
-                        {
-                            let result = client_data.session.text(msg.clone()).await;
-                            if result.is_err() {
-                                tracing::warn!("Got an unexpected closed session");
-
-                                self.cleanup_session(uuid).await;
-                            }
-                        }
-                    }
-                    WebSocketEvent::Name { name } => {
-                        let mut subs = client_data.subscriptions.iter();
-                        if !client_data.is_guest()
-                            && (client_data.address == name.owner)
-                            && subs.any(|t| t.eq(&WebSocketSubscriptionType::OwnNames))
-                            || subs.any(|t| t.eq(&WebSocketSubscriptionType::Names))
-                        {
-                            let result = client_data.session.text(msg.clone()).await;
-                            if result.is_err() {
-                                tracing::warn!("Got an unexpected closed session");
-
-                                self.cleanup_session(uuid).await;
-                            }
-                        }
-                    }
+                    closed.push(*uuid);
                 }
             }
         }
+
+        for uuid in closed {
+            tracing::info!("Cleaning up session {uuid}");
+            inner.sessions.remove(&uuid);
+        }
+
+        let mut closed = Vec::new();
+        let frame = recorded.to_frame();
+        for client in inner.event_streams.iter() {
+            if client.wants(event) && client.sender.try_send(frame.clone()).is_err() {
+                // Either gone or too far behind, it can resume from the history by reconnecting.
+                closed.push(*client.key());
+            }
+        }
+
+        for uuid in closed {
+            tracing::info!("Dropping event stream {uuid}");
+            inner.event_streams.remove(&uuid);
+        }
     }
 
-    /// Broadcast a message to all connected clients
-    pub async fn broadcast(&self, msg: impl Into<ByteString>) {
+    /// Register an event stream client, returning the events it missed since `last_event_id`.
+    ///
+    /// Registering and collecting the missed events happens under the same lock, so no event is lost or sent twice.
+    pub async fn insert_event_stream(
+        &self,
+        uuid: Uuid,
+        client: EventStreamClient,
+        last_event_id: Option<u64>,
+    ) -> Result<Vec<RecordedEvent>, WebSocketServerError> {
+        let inner = self.inner.lock().await;
+
+        if let Some(ip) = client.ip {
+            let count = inner
+                .event_streams
+                .iter()
+                .filter(|entry| entry.ip == Some(ip))
+                .count();
+            if count >= self.config.max_sessions_per_ip {
+                tracing::info!("Refusing event stream {uuid}, too many streams from {ip}");
+                return Err(WebSocketServerError::TooManySessionsForIp);
+            }
+        }
+
+        let missed = match last_event_id {
+            Some(last_event_id) => inner
+                .events
+                .since(last_event_id)
+                .filter(|recorded| client.wants(&recorded.event))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+
+        tracing::info!("Event stream {uuid} connected");
+        inner.event_streams.insert(uuid, client);
+
+        Ok(missed)
+    }
+
+    pub async fn has_event_stream(&self, uuid: &Uuid) -> bool {
+        self.inner.lock().await.event_streams.contains_key(uuid)
+    }
+
+    pub async fn cleanup_event_stream(&self, uuid: &Uuid) {
+        tracing::info!("Cleaning up event stream {uuid}");
+        self.inner.lock().await.event_streams.remove(uuid);
+    }
+
+    /// Broadcast a message to all connected clients, returns the amount of clients it was delivered to.
+    pub async fn broadcast(&self, msg: impl Into<ByteString>) -> usize {
         let msg = msg.into();
 
         let inner = self.inner.lock().await;
@@ -212,15 +513,30 @@ impl WebSocketServer {
 
             futures.push(async move {
                 let (uuid, data) = entry.pair_mut();
-                let res = data.session.text(msg).await;
-                if res.is_err() {
-                    tracing::warn!("Got an unexpected closed session");
-
-                    self.cleanup_session(uuid).await;
+                match data.session.text(msg).await {
+                    Ok(_) => None,
+                    Err(_) => Some(*uuid),
                 }
             });
         }
 
-        while let Some(_result) = futures.next().await {}
+        let mut delivered = 0;
+        let mut closed = Vec::new();
+        while let Some(result) = futures.next().await {
+            match result {
+                Some(uuid) => closed.push(uuid),
+                None => delivered += 1,
+            }
+        }
+        drop(futures);
+
+        // The session entries have to be released before they can be removed.
+        for uuid in closed {
+            tracing::warn!("Got an unexpected closed session");
+            tracing::info!("Cleaning up session {uuid}");
+            inner.sessions.remove(&uuid);
+        }
+
+        delivered
     }
 }
@@ -1,8 +1,13 @@
 use surrealdb::{engine::any::Any, Surreal};
 
+use super::error;
+use crate::models::addresses::AddressJson;
+use crate::models::names::NameJson;
+use crate::models::transactions::{AddressTransactionQuery, TransactionJson};
 use crate::models::websockets::{
     WebSocketMessage, WebSocketMessageInner, WebSocketMessageResponse,
 };
+use crate::routes::PaginationParams;
 
 use crate::database::models::wallet::Model as Wallet;
 
@@ -53,3 +58,83 @@ pub async fn get_address(
         },
     }
 }
+
+pub async fn get_address_transactions(
+    db: &Surreal<Any>,
+    address: String,
+    query: AddressTransactionQuery,
+    msg_id: Option<usize>,
+) -> WebSocketMessage {
+    let transactions = match Wallet::transactions(db, address, &query).await {
+        Ok(transactions) => transactions,
+        Err(err) => return error::database_error(msg_id, err),
+    };
+    let transactions: Vec<TransactionJson> =
+        transactions.into_iter().map(|trans| trans.into()).collect();
+
+    WebSocketMessage {
+        ok: Some(true),
+        id: msg_id,
+        r#type: WebSocketMessageInner::Response {
+            responding_to: "address_transactions".to_owned(),
+            data: WebSocketMessageResponse::AddressTransactions {
+                count: transactions.len(),
+                transactions,
+            },
+        },
+    }
+}
+
+pub async fn get_address_names(
+    db: &Surreal<Any>,
+    address: String,
+    pagination: PaginationParams,
+    msg_id: Option<usize>,
+) -> WebSocketMessage {
+    let names = match Wallet::names(db, address, &pagination).await {
+        Ok(names) => names,
+        Err(err) => return error::database_error(msg_id, err),
+    };
+    let names: Vec<NameJson> = names.into_iter().map(|name| name.into()).collect();
+
+    WebSocketMessage {
+        ok: Some(true),
+        id: msg_id,
+        r#type: WebSocketMessageInner::Response {
+            responding_to: "address_names".to_owned(),
+            data: WebSocketMessageResponse::AddressNames {
+                count: names.len(),
+                names,
+            },
+        },
+    }
+}
+
+pub async fn get_richlist(
+    db: &Surreal<Any>,
+    pagination: PaginationParams,
+    msg_id: Option<usize>,
+) -> WebSocketMessage {
+    let total = match Wallet::count(db).await {
+        Ok(total) => total,
+        Err(err) => return error::database_error(msg_id, err),
+    };
+    let addresses = match Wallet::get_richest(db, &pagination).await {
+        Ok(addresses) => addresses,
+        Err(err) => return error::database_error(msg_id, err),
+    };
+    let addresses: Vec<AddressJson> = addresses.into_iter().map(|addr| addr.into()).collect();
+
+    WebSocketMessage {
+        ok: Some(true),
+        id: msg_id,
+        r#type: WebSocketMessageInner::Response {
+            responding_to: "richlist".to_owned(),
+            data: WebSocketMessageResponse::Richlist {
+                count: addresses.len(),
+                total,
+                addresses,
+            },
+        },
+    }
+}
@@ -1,73 +1,117 @@
 use surrealdb::Surreal;
 use surrealdb::{engine::any::Any, Uuid};
 
+use super::error;
+use crate::auth::{self, signature};
 use crate::database::models::wallet::Model as Wallet;
-use crate::errors::wallet::WalletError;
-use crate::errors::KromerError;
+use crate::errors::krist::{address::AddressError, KristError, KristErrorExt};
 use crate::models::websockets::{
     WebSocketMessage, WebSocketMessageInner, WebSocketMessageResponse,
 };
-use crate::websockets::WebSocketServer;
+use crate::utils::validation_kromer::is_valid_v1_address;
+use crate::websockets::{types::common::SessionCredential, WebSocketServer};
+use crate::AppState;
 
 pub async fn perform_login(
-    db: &Surreal<Any>,
+    state: &AppState,
     server: &WebSocketServer,
     uuid: &Uuid,
     private_key: String,
+    address: Option<String>,
     msg_id: Option<usize>,
 ) -> WebSocketMessage {
-    let wallet = Wallet::verify_address(db, private_key.clone())
-        .await
-        .map_err(|_| KromerError::Wallet(WalletError::InvalidPassword));
+    // Legacy v1 addresses can't be derived from the key alone, the client has to name them.
+    let v1_address = address.filter(|address| is_valid_v1_address(address));
+    let ip = server.session_ip(uuid).await;
+    let wallet =
+        auth::verify_private_key(state, Some(server), ip, &private_key, v1_address.as_deref())
+            .await;
 
-    // TODO: Refactor this fuckass match statement so we dont have a billion nested structs, lol
     match wallet {
-        Ok(response) => {
-            if response.authed {
-                let wallet = response.address;
+        Ok(wallet) => {
+            let credential = SessionCredential::derive(&wallet.address, &private_key);
+            login_session(server, uuid, wallet, credential, msg_id).await
+        }
+        Err(err @ KristError::Address(AddressError::TooManyAttempts(_))) => {
+            error::error_message(msg_id, err.error_type(), err.to_string())
+        }
+        Err(_) => guest_login(msg_id),
+    }
+}
 
-                let inner = server.inner.lock().await;
-                let mut session = inner
-                    .sessions
-                    .get_mut(uuid)
-                    .expect("Expected the session to exist, why doesn't it?");
-                session.address = wallet.address.clone();
-                session.private_key = Some(private_key);
+/// Log in with a nonce signed by the wallet's keypair, the private key never reaches the server.
+pub async fn perform_signed_login(
+    db: &Surreal<Any>,
+    server: &WebSocketServer,
+    uuid: &Uuid,
+    address: String,
+    nonce: String,
+    signature: String,
+    msg_id: Option<usize>,
+) -> WebSocketMessage {
+    let message = signature::signing_message(&address, &nonce, "WS", "login", b"");
 
-                WebSocketMessage {
-                    ok: Some(true),
-                    id: msg_id,
-                    r#type: WebSocketMessageInner::Response {
-                        responding_to: "login".to_owned(),
-                        data: WebSocketMessageResponse::Login {
-                            is_guest: false,
-                            address: Some(wallet.into()),
-                        },
-                    },
-                }
-            } else {
-                WebSocketMessage {
-                    ok: Some(true),
-                    id: msg_id,
-                    r#type: WebSocketMessageInner::Response {
-                        responding_to: "login".to_owned(),
-                        data: WebSocketMessageResponse::Login {
-                            is_guest: true,
-                            address: None,
-                        },
-                    },
-                }
-            }
+    match signature::verify_signed(db, &address, &nonce, &message, &signature).await {
+        Ok(wallet) => {
+            let Some(public_key) = wallet.public_key.clone() else {
+                return guest_login(msg_id);
+            };
+            let credential = SessionCredential::PublicKey(public_key);
+            login_session(server, uuid, wallet, credential, msg_id).await
         }
-        Err(_) => WebSocketMessage {
-            ok: Some(true),
+        Err(_) => guest_login(msg_id),
+    }
+}
+
+async fn login_session(
+    server: &WebSocketServer,
+    uuid: &Uuid,
+    wallet: Wallet,
+    credential: SessionCredential,
+    msg_id: Option<usize>,
+) -> WebSocketMessage {
+    if let Err(err) = server.check_address_limit(uuid, &wallet.address).await {
+        return WebSocketMessage {
+            ok: Some(false),
             id: msg_id,
-            r#type: WebSocketMessageInner::Response {
-                responding_to: "login".to_owned(),
-                data: WebSocketMessageResponse::Login {
-                    is_guest: true,
-                    address: None,
-                },
+            r#type: WebSocketMessageInner::Error {
+                error: err.error_type().to_owned(),
+                message: err.to_string(),
+            },
+        };
+    }
+
+    let inner = server.inner.lock().await;
+    let mut session = inner
+        .sessions
+        .get_mut(uuid)
+        .expect("Expected the session to exist, why doesn't it?");
+    session.address = wallet.address.clone();
+    session.credential = Some(credential);
+
+    WebSocketMessage {
+        ok: Some(true),
+        id: msg_id,
+        r#type: WebSocketMessageInner::Response {
+            responding_to: "login".to_owned(),
+            data: WebSocketMessageResponse::Login {
+                is_guest: false,
+                address: Some(wallet.into()),
+            },
+        },
+    }
+}
+
+/// Failed logins are not errors, the session just stays a guest.
+fn guest_login(msg_id: Option<usize>) -> WebSocketMessage {
+    WebSocketMessage {
+        ok: Some(true),
+        id: msg_id,
+        r#type: WebSocketMessageInner::Response {
+            responding_to: "login".to_owned(),
+            data: WebSocketMessageResponse::Login {
+                is_guest: true,
+                address: None,
             },
         },
     }
@@ -85,7 +129,7 @@ pub async fn perform_logout(
         .get_mut(uuid)
         .expect("Expected the session to exist, why doesn't it?");
     session.address = String::from("guest");
-    session.private_key = None;
+    session.credential = None;
 
     WebSocketMessage {
         ok: Some(true),
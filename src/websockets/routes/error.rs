@@ -1 +1,24 @@
+use crate::models::websockets::{WebSocketMessage, WebSocketMessageInner};
 
+/// Build an error reply to a message.
+pub fn error_message<S: Into<String>>(
+    msg_id: Option<usize>,
+    error: &str,
+    message: S,
+) -> WebSocketMessage {
+    WebSocketMessage {
+        ok: Some(false),
+        id: msg_id,
+        r#type: WebSocketMessageInner::Error {
+            error: error.to_owned(),
+            message: message.into(),
+        },
+    }
+}
+
+/// Build the error reply used when the database failed to handle a query.
+pub fn database_error(msg_id: Option<usize>, err: surrealdb::Error) -> WebSocketMessage {
+    tracing::error!("Caught an error: {err}");
+
+    error_message(msg_id, "database_error", "An error occured in the database")
+}
@@ -0,0 +1,23 @@
+use surrealdb::{engine::any::Any, Surreal};
+
+use super::error;
+use crate::database::models::wallet::Model as Wallet;
+use crate::models::websockets::{
+    WebSocketMessage, WebSocketMessageInner, WebSocketMessageResponse,
+};
+
+pub async fn get_supply(db: &Surreal<Any>, msg_id: Option<usize>) -> WebSocketMessage {
+    let supply = match Wallet::supply(db).await {
+        Ok(supply) => supply,
+        Err(err) => return error::database_error(msg_id, err),
+    };
+
+    WebSocketMessage {
+        ok: Some(true),
+        id: msg_id,
+        r#type: WebSocketMessageInner::Response {
+            responding_to: "supply".to_owned(),
+            data: WebSocketMessageResponse::Supply { supply },
+        },
+    }
+}
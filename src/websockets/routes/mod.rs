@@ -2,6 +2,8 @@ pub mod addresses;
 pub mod auth;
 pub mod error;
 pub mod me;
+pub mod misc;
+pub mod names;
 pub mod subscriptions;
 pub mod transactions;
 pub mod wallet;
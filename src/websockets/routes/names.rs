@@ -0,0 +1,28 @@
+use surrealdb::{engine::any::Any, Surreal};
+
+use super::error;
+use crate::database::models::name::Model as Name;
+use crate::models::websockets::{
+    WebSocketMessage, WebSocketMessageInner, WebSocketMessageResponse,
+};
+
+pub async fn get_name(db: &Surreal<Any>, name: String, msg_id: Option<usize>) -> WebSocketMessage {
+    let db_name = match Name::get_by_name(db, name.clone()).await {
+        Ok(db_name) => db_name,
+        Err(err) => return error::database_error(msg_id, err),
+    };
+
+    match db_name {
+        Some(db_name) => WebSocketMessage {
+            ok: Some(true),
+            id: msg_id,
+            r#type: WebSocketMessageInner::Response {
+                responding_to: "name".to_owned(),
+                data: WebSocketMessageResponse::Name {
+                    name: db_name.into(),
+                },
+            },
+        },
+        None => error::error_message(msg_id, "name_not_found", format!("Name {name} not found")),
+    }
+}
@@ -119,6 +119,12 @@ pub async fn get_valid_subscription_levels(msg_id: Option<usize>) -> WebSocketMe
         WebSocketSubscriptionType::Names,
         WebSocketSubscriptionType::OwnNames,
         WebSocketSubscriptionType::Motd,
+        WebSocketSubscriptionType::Security,
+        WebSocketSubscriptionType::Invoices,
+        WebSocketSubscriptionType::Escrows,
+        WebSocketSubscriptionType::StandingOrders,
+        WebSocketSubscriptionType::Holds,
+        WebSocketSubscriptionType::ScheduledTransfers,
     ];
     let subscription_list: Vec<String> = subscription_list
         .into_iter()
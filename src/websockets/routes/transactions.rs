@@ -1,26 +1,39 @@
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
-use surrealdb::{engine::any::Any, Surreal};
+use surrealdb::{engine::any::Any, Surreal, Uuid};
 
+use super::error;
 use crate::{
     database::models::transaction::TransactionCreateData,
     models::{
-        transactions::TransactionType,
+        transactions::{TransactionJson, TransactionType},
         websockets::{WebSocketMessage, WebSocketMessageInner, WebSocketMessageResponse},
     },
+    websockets::WebSocketServer,
 };
 
-use crate::database::models::transaction::Model as Transaction;
+use crate::audit::{Actor, AuditEntry, RequestInfo};
+use crate::auth;
+use crate::database::models::transaction::{BatchLeg, Model as Transaction};
 use crate::database::models::wallet::Model as Wallet;
+use crate::errors::krist::{address::AddressError, KristError, KristErrorExt};
+use crate::{allowances, batches, invoices, AppState};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn make_transaction(
-    db: &Surreal<Any>,
-    private_key: String,
+    state: &AppState,
+    server: &WebSocketServer,
+    uuid: &Uuid,
+    private_key: Option<String>,
     to: String,
     amount: Decimal,
     metadata: Option<String>,
+    invoice: Option<String>,
+    from: Option<String>,
     msg_id: Option<usize>,
 ) -> WebSocketMessage {
+    let db = &state.db;
+
     if amount < dec!(0.0) {
         return WebSocketMessage {
             ok: Some(false),
@@ -32,31 +45,35 @@ pub async fn make_transaction(
         };
     }
 
-    let resp = match Wallet::verify_address(db, private_key).await {
-        Ok(resp) => resp,
-        Err(_) => {
-            return WebSocketMessage {
-                ok: Some(false),
-                id: msg_id,
-                r#type: WebSocketMessageInner::Error {
-                    error: "database_error".to_owned(),
-                    message: "An error occured in the database".to_owned(),
-                },
+    let (to, amount, metadata) = match invoice {
+        Some(invoice) => {
+            match invoices::prepare_payment(db, &invoice, &to, amount, metadata).await {
+                Ok(payment) => (payment.to, payment.amount, Some(payment.metadata)),
+                Err(KristError::Database(err)) => return error::database_error(msg_id, err),
+                Err(err) => {
+                    return error::error_message(msg_id, err.error_type(), err.to_string());
+                }
             }
         }
+        None => (to, amount, metadata),
     };
-    if !resp.authed {
+
+    // A zero amount only stands for the outstanding amount of an invoice, which may be nothing.
+    if amount <= dec!(0.0) {
         return WebSocketMessage {
             ok: Some(false),
             id: msg_id,
             r#type: WebSocketMessageInner::Error {
                 error: "invalid_parameter".to_owned(),
-                message: "Invalid parameter privatekey".to_owned(),
+                message: "Invalid parameter amount".to_owned(),
             },
         };
     }
 
-    let sender = resp.address;
+    let sender = match verify_sender(state, server, uuid, private_key, msg_id).await {
+        Ok(wallet) => wallet,
+        Err(message) => return message,
+    };
 
     let recipient = match Wallet::get_by_address(db, to.clone()).await {
         Ok(model) => model,
@@ -73,6 +90,16 @@ pub async fn make_transaction(
     };
 
     let recipient = match recipient {
+        Some(wallet) if wallet.locked => {
+            return WebSocketMessage {
+                ok: Some(false),
+                id: msg_id,
+                r#type: WebSocketMessageInner::Error {
+                    error: "address_locked".to_owned(),
+                    message: format!("Address {} is locked", to),
+                },
+            }
+        }
         Some(wallet) => wallet,
         None => {
             return WebSocketMessage {
@@ -86,7 +113,10 @@ pub async fn make_transaction(
         }
     };
 
-    if sender.balance < amount {
+    // Spending from another wallet, its allowance and balance are checked along with the transfer.
+    let owner = from.filter(|from| *from != sender.address);
+
+    if owner.is_none() && sender.balance < amount {
         return WebSocketMessage {
             ok: Some(false),
             id: msg_id,
@@ -97,29 +127,60 @@ pub async fn make_transaction(
         };
     }
 
-    let creation_data = TransactionCreateData {
-        from: sender.address.clone(),
-        to: recipient.address.clone(),
-        amount,
-        metadata: metadata.clone(),
-        transaction_type: TransactionType::Transfer,
+    let transaction = match owner {
+        Some(owner) => {
+            match allowances::spend(
+                db,
+                owner,
+                &sender.address,
+                recipient.address,
+                amount,
+                metadata,
+            )
+            .await
+            {
+                Ok(transaction) => transaction,
+                Err(KristError::Database(err)) => return error::database_error(msg_id, err),
+                Err(err) => {
+                    return error::error_message(msg_id, err.error_type(), err.to_string());
+                }
+            }
+        }
+        None => {
+            let creation_data = TransactionCreateData {
+                from: sender.address.clone(),
+                to: recipient.address.clone(),
+                amount,
+                metadata: metadata.clone(),
+                transaction_type: TransactionType::Transfer,
+            };
+
+            let response: Result<Vec<Transaction>, _> =
+                db.insert("transaction").content(creation_data).await;
+            if response.is_err() {
+                return WebSocketMessage {
+                    ok: Some(false),
+                    id: msg_id,
+                    r#type: WebSocketMessageInner::Error {
+                        error: "database_error".to_owned(),
+                        message: "An error occured in the database".to_owned(),
+                    },
+                };
+            }
+            let response = response.unwrap();
+            let first = response.first().unwrap();
+            first.clone() // guh
+        }
     };
 
-    let response: Result<Vec<Transaction>, _> =
-        db.insert("transaction").content(creation_data).await;
-    if response.is_err() {
-        return WebSocketMessage {
-            ok: Some(false),
-            id: msg_id,
-            r#type: WebSocketMessageInner::Error {
-                error: "database_error".to_owned(),
-                message: "An error occured in the database".to_owned(),
-            },
-        };
-    }
-    let response = response.unwrap();
-    let first = response.first().unwrap();
-    let transaction = first.clone(); // guh
+    let request = RequestInfo::websocket(uuid, server.session_ip(uuid).await);
+    AuditEntry::new(Actor::Wallet(sender.address), "transaction.create")
+        .target(&transaction.to)
+        .after(TransactionJson::from(transaction.clone()))
+        .request(&request)
+        .write(db)
+        .await;
+    invoices::link_payment(db, server, &transaction).await;
 
     WebSocketMessage {
         ok: Some(true),
@@ -132,3 +193,133 @@ pub async fn make_transaction(
         },
     }
 }
+
+/// Verify the wallet a transfer is sent from, replying with an error message when that fails.
+///
+/// Sessions that are logged in do not need to send their privatekey again, their credential is
+/// re-verified against the wallet instead so a stale session can not keep spending.
+async fn verify_sender(
+    state: &AppState,
+    server: &WebSocketServer,
+    uuid: &Uuid,
+    private_key: Option<String>,
+    msg_id: Option<usize>,
+) -> Result<Wallet, WebSocketMessage> {
+    let verified = match private_key {
+        Some(private_key) => {
+            let ip = server.session_ip(uuid).await;
+            match auth::verify_private_key(state, Some(server), ip, &private_key, None).await {
+                Ok(wallet) => Ok(Some(wallet)),
+                Err(KristError::Address(AddressError::AuthFailed)) => Ok(None),
+                Err(err) => Err(err),
+            }
+        }
+        None => match server.get_session_credential(uuid).await {
+            Some((address, credential)) => {
+                Wallet::verify_credential(&state.db, &address, &credential)
+                    .await
+                    .map_err(KristError::from)
+            }
+            None => {
+                return Err(error::error_message(
+                    msg_id,
+                    "missing_parameter",
+                    "Missing parameter privatekey",
+                ));
+            }
+        },
+    };
+
+    match verified {
+        Ok(Some(wallet)) => Ok(wallet),
+        Ok(None) => Err(error::error_message(
+            msg_id,
+            "invalid_parameter",
+            "Invalid parameter privatekey",
+        )),
+        Err(KristError::Address(err)) => Err(error::error_message(
+            msg_id,
+            err.error_type(),
+            err.to_string(),
+        )),
+        Err(_) => Err(error::error_message(
+            msg_id,
+            "database_error",
+            "An error occured in the database",
+        )),
+    }
+}
+
+/// Send to many recipients at once, either every transfer is made or none are.
+pub async fn make_batch_transaction(
+    state: &AppState,
+    server: &WebSocketServer,
+    uuid: &Uuid,
+    private_key: Option<String>,
+    transfers: Vec<BatchLeg>,
+    msg_id: Option<usize>,
+) -> WebSocketMessage {
+    let db = &state.db;
+
+    if let Err(err) = batches::validate(&transfers) {
+        return error::error_message(msg_id, err.error_type(), err.to_string());
+    }
+
+    let sender = match verify_sender(state, server, uuid, private_key, msg_id).await {
+        Ok(wallet) => wallet,
+        Err(message) => return message,
+    };
+
+    let batch = match batches::send(db, server, &sender.address, &transfers).await {
+        Ok(batch) => batch,
+        Err(KristError::Database(err)) => return error::database_error(msg_id, err),
+        Err(err) => return error::error_message(msg_id, err.error_type(), err.to_string()),
+    };
+    let transactions: Vec<TransactionJson> =
+        batch.transactions.into_iter().map(Into::into).collect();
+
+    let request = RequestInfo::websocket(uuid, server.session_ip(uuid).await);
+    AuditEntry::new(Actor::Wallet(sender.address), "transaction.batch")
+        .target(&batch.id)
+        .after(serde_json::json!({ "batch": batch.id, "transactions": transactions }))
+        .request(&request)
+        .write(db)
+        .await;
+
+    WebSocketMessage {
+        ok: Some(true),
+        id: msg_id,
+        r#type: WebSocketMessageInner::Response {
+            responding_to: "make_batch_transaction".to_owned(),
+            data: WebSocketMessageResponse::MakeBatchTransaction {
+                batch: batch.id,
+                transactions,
+            },
+        },
+    }
+}
+
+pub async fn get_transaction(
+    db: &Surreal<Any>,
+    id: String,
+    msg_id: Option<usize>,
+) -> WebSocketMessage {
+    let transaction = match Transaction::get_partial(db, id).await {
+        Ok(transaction) => transaction,
+        Err(err) => return error::database_error(msg_id, err),
+    };
+
+    match transaction {
+        Some(transaction) => WebSocketMessage {
+            ok: Some(true),
+            id: msg_id,
+            r#type: WebSocketMessageInner::Response {
+                responding_to: "transaction".to_owned(),
+                data: WebSocketMessageResponse::Transaction {
+                    transaction: transaction.into(),
+                },
+            },
+        },
+        None => error::error_message(msg_id, "transaction_not_found", "Transaction not found"),
+    }
+}
@@ -1,19 +1,46 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::time::Instant;
+
 use dashmap::DashSet;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
+
+use super::rate_limit::TokenBucket;
+use crate::utils::crypto;
+
+/// What a session proved when it logged in.
+///
+/// Sessions keep this around instead of the private key, it is enough to re-verify the session
+/// against the stored wallet but can not be used to log in anywhere else.
+#[derive(Clone, PartialEq, Eq)]
+pub enum SessionCredential {
+    /// `sha256(address + privatekey)`, checked against the wallet hash.
+    Digest(String),
+    /// The ed25519 public key the login was signed with.
+    PublicKey(String),
+}
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct WebSocketTokenData {
     pub address: String,
-    pub private_key: Option<String>,
+    pub credential: Option<SessionCredential>,
 }
 
 #[derive(Clone, Serialize)]
 pub struct WebSocketSessionData {
     pub address: String,
-    pub private_key: Option<String>,
+    /// Never serialized as-is, only exposed as the `authed` capability flag.
+    #[serde(rename = "authed", serialize_with = "serialize_credential_flag")]
+    pub credential: Option<SessionCredential>,
     #[serde(skip)]
     pub session: actix_ws::Session,
     pub subscriptions: DashSet<WebSocketSubscriptionType>,
+    pub ip: Option<IpAddr>,
+    #[serde(skip)]
+    pub rate_limit: TokenBucket,
+    /// When the session last sent a message, used to evict idle guests.
+    #[serde(skip)]
+    pub last_active: Instant,
 }
 
 #[derive(Clone, Debug, Hash, Eq, Serialize, Deserialize, PartialEq, PartialOrd)]
@@ -26,6 +53,18 @@ pub enum WebSocketSubscriptionType {
     Names,
     OwnNames,
     Motd,
+    /// Alerts about failed logins to the session's own address.
+    Security,
+    /// Status changes of invoices payable to the session's own address.
+    Invoices,
+    /// Status changes of escrows the session's own address takes part in.
+    Escrows,
+    /// Changes to standing orders the session's own address pays or is paid by.
+    StandingOrders,
+    /// Changes to holds the session's own address authorized or was authorized.
+    Holds,
+    /// Changes to scheduled transfers from or to the session's own address.
+    ScheduledTransfers,
 }
 
 impl WebSocketSubscriptionType {
@@ -44,13 +83,42 @@ impl WebSocketSubscriptionType {
             WebSocketSubscriptionType::Names => "names".to_owned(),
             WebSocketSubscriptionType::OwnNames => "ownNames".to_owned(),
             WebSocketSubscriptionType::Motd => "motd".to_owned(),
+            WebSocketSubscriptionType::Security => "security".to_owned(),
+            WebSocketSubscriptionType::Invoices => "invoices".to_owned(),
+            WebSocketSubscriptionType::Escrows => "escrows".to_owned(),
+            WebSocketSubscriptionType::StandingOrders => "standingOrders".to_owned(),
+            WebSocketSubscriptionType::Holds => "holds".to_owned(),
+            WebSocketSubscriptionType::ScheduledTransfers => "scheduledTransfers".to_owned(),
         }
     }
 }
 
+impl SessionCredential {
+    /// Derive the credential for a wallet from its address and private key.
+    pub fn derive(address: &str, private_key: &str) -> Self {
+        Self::Digest(crypto::make_wallet_digest(address, private_key))
+    }
+}
+
+impl fmt::Debug for SessionCredential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SessionCredential(<redacted>)")
+    }
+}
+
+fn serialize_credential_flag<S>(
+    credential: &Option<SessionCredential>,
+    s: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    s.serialize_bool(credential.is_some())
+}
+
 impl WebSocketSessionData {
     pub fn is_guest(&self) -> bool {
-        self.address == *"guest"
+        self.credential.is_none()
     }
 }
 
@@ -66,6 +134,12 @@ impl std::str::FromStr for WebSocketSubscriptionType {
             "names" => Ok(Self::Names),
             "ownNames" => Ok(Self::OwnNames),
             "motd" => Ok(Self::Motd),
+            "security" => Ok(Self::Security),
+            "invoices" => Ok(Self::Invoices),
+            "escrows" => Ok(Self::Escrows),
+            "standingOrders" => Ok(Self::StandingOrders),
+            "holds" => Ok(Self::Holds),
+            "scheduledTransfers" => Ok(Self::ScheduledTransfers),
             _ => Err(()),
         }
     }
@@ -81,16 +155,43 @@ impl std::fmt::Display for WebSocketSubscriptionType {
             Self::Names => write!(f, "names"),
             Self::OwnNames => write!(f, "ownNames"),
             Self::Motd => write!(f, "motd"),
+            Self::Security => write!(f, "security"),
+            Self::Invoices => write!(f, "invoices"),
+            Self::Escrows => write!(f, "escrows"),
+            Self::StandingOrders => write!(f, "standingOrders"),
+            Self::Holds => write!(f, "holds"),
+            Self::ScheduledTransfers => write!(f, "scheduledTransfers"),
         }
     }
 }
 
 impl WebSocketTokenData {
     #[inline]
-    pub fn new(address: String, private_key: Option<String>) -> Self {
+    pub fn new(address: String, credential: Option<SessionCredential>) -> Self {
         Self {
             address,
-            private_key,
+            credential,
         }
     }
+
+    #[inline]
+    pub fn guest() -> Self {
+        Self::new("guest".into(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credential_is_redacted() {
+        let credential = SessionCredential::derive("krcgbmalxg", "test123");
+        let token = WebSocketTokenData::new("krcgbmalxg".to_owned(), Some(credential.clone()));
+
+        let digest = crypto::make_wallet_digest("krcgbmalxg", "test123");
+
+        assert!(!format!("{token:?}").contains(&digest));
+        assert!(!format!("{token:?}").contains("test123"));
+    }
 }
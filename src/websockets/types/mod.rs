@@ -1,4 +1,6 @@
 pub mod common;
+pub mod rate_limit;
+pub mod stats;
 
 use chrono::Utc;
 
@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use serde::Serialize;
+
+/// Amount of one second buckets kept around to calculate the message rate.
+const RATE_WINDOW: usize = 10;
+
+/// Live statistics of the WebSocket server, as reported to the internal API.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebSocketServerStats {
+    pub sessions: usize,
+    pub guests: usize,
+    pub authed: usize,
+    /// Amount of sessions subscribed to each subscription type.
+    pub subscriptions: BTreeMap<String, usize>,
+    pub pending_tokens: usize,
+    /// Amount of clients connected to the server-sent events stream.
+    pub event_streams: usize,
+    /// Messages received from clients per second, averaged over the last few seconds.
+    pub messages_per_second: f64,
+}
+
+#[derive(Debug, Default)]
+struct RateBucket {
+    second: AtomicU64,
+    count: AtomicU64,
+}
+
+/// Lock-free counter of incoming messages, bucketed per second.
+#[derive(Debug)]
+pub struct MessageRate {
+    started: Instant,
+    buckets: [RateBucket; RATE_WINDOW],
+}
+
+impl Default for MessageRate {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            buckets: Default::default(),
+        }
+    }
+}
+
+impl MessageRate {
+    pub fn record(&self) {
+        let second = self.started.elapsed().as_secs();
+        let bucket = &self.buckets[second as usize % RATE_WINDOW];
+
+        // The bucket still holds the count of an older second, start over.
+        if bucket.second.swap(second, Ordering::Relaxed) != second {
+            bucket.count.store(0, Ordering::Relaxed);
+        }
+        bucket.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Average rate over the last completed seconds, the current second is still being counted.
+    pub fn per_second(&self) -> f64 {
+        let now = self.started.elapsed().as_secs();
+
+        let total: u64 = self
+            .buckets
+            .iter()
+            .filter(|bucket| {
+                let age = now.saturating_sub(bucket.second.load(Ordering::Relaxed));
+                (1..RATE_WINDOW as u64).contains(&age)
+            })
+            .map(|bucket| bucket.count.load(Ordering::Relaxed))
+            .sum();
+
+        let seconds = now.clamp(1, RATE_WINDOW as u64 - 1);
+        total as f64 / seconds as f64
+    }
+}
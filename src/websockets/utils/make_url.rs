@@ -4,6 +4,7 @@ use surrealdb::Uuid;
 
 use crate::errors::{websocket::WebSocketError, KromerError};
 
+#[allow(clippy::result_large_err)]
 pub fn make_url(uuid: Uuid) -> Result<String, KromerError> {
     let force_insecure = env::var("FORCE_WS_INSECURE").unwrap_or("true".to_owned());
     let schema = if force_insecure == "true" {